@@ -47,7 +47,6 @@ fn parse_via_quick_xml(text: &str) {
             | Ok((_, quick_xml::events::Event::Empty(_))) => (),
             Ok((_, quick_xml::events::Event::Text(ref e))) => {
                 e.unescape().unwrap();
-                ()
             }
             Ok((_, quick_xml::events::Event::Eof)) => break,
             _ => (),
@@ -78,8 +77,10 @@ fn huge_quick_xml(bencher: &mut Bencher) {
 
 fn tiny_roxmltree(bencher: &mut Bencher) {
     let text = std::fs::read_to_string("fonts.conf").unwrap();
-    let mut opt = roxmltree::ParsingOptions::default();
-    opt.allow_dtd = true;
+    let opt = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..roxmltree::ParsingOptions::default()
+    };
     bencher.iter(|| roxmltree::Document::parse_with_options(&text, opt).unwrap())
 }
 
@@ -191,6 +192,16 @@ fn roxmltree_iter_descendants_inexpensive(bencher: &mut Bencher) {
     })
 }
 
+fn roxmltree_iter_descendants_named(bencher: &mut Bencher) {
+    let text = std::fs::read_to_string("large.plist").unwrap();
+    let doc = roxmltree::Document::parse(&text).unwrap();
+    let root = doc.root();
+    bencher.iter(|| {
+        let count = root.descendant_elements_named("string").count();
+        assert!(count == 3273);
+    })
+}
+
 fn roxmltree_iter_descendants_expensive(bencher: &mut Bencher) {
     let text = std::fs::read_to_string("large.plist").unwrap();
     let doc = roxmltree::Document::parse(&text).unwrap();
@@ -285,6 +296,7 @@ benchmark_group!(
 benchmark_group!(
     roxmltree_iter,
     roxmltree_iter_descendants_inexpensive,
+    roxmltree_iter_descendants_named,
     roxmltree_iter_descendants_expensive,
     roxmltree_iter_children
 );
@@ -23,13 +23,15 @@ fn main() {
 
     println!(
         "Elements count: {}",
-        doc.root().descendants().filter(|n| n.is_element()).count()
+        doc.root()
+            .descendants_of_type(roxmltree::NodeType::Element)
+            .count()
     );
 
-    let attrs_count: usize = doc.root().descendants().map(|n| n.attributes().len()).sum();
+    let attrs_count: usize = doc.root().descendants().map(|n| n.attributes_count()).sum();
     println!("Attributes count: {}", attrs_count);
 
-    let ns_count: usize = doc.root().descendants().map(|n| n.namespaces().len()).sum();
+    let ns_count: usize = doc.root().descendants().map(|n| n.namespaces_count()).sum();
     println!("Namespaces count: {}", ns_count);
 
     let mut uris = HashSet::new();
@@ -51,11 +53,13 @@ fn main() {
 
     println!(
         "Comments count: {}",
-        doc.root().descendants().filter(|n| n.is_comment()).count()
+        doc.root()
+            .descendants_of_type(roxmltree::NodeType::Comment)
+            .count()
     );
 
     println!("Comments:");
-    for node in doc.root().descendants().filter(|n| n.is_comment()) {
+    for node in doc.root().descendants_of_type(roxmltree::NodeType::Comment) {
         println!("{:?}", node.text().unwrap());
     }
 }
@@ -0,0 +1,515 @@
+use alloc::collections::BTreeSet;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::parse::check_attributes_limit;
+use crate::tokenizer::is_ncname;
+use crate::visitor::Edge;
+use crate::{
+    AttributeData, Document, Error, NamespaceIdx, Namespaces, Node, NodeData, NodeId, NodeKind,
+    ShortRange, StringStorage, TextPos, NS_XMLNS_URI, NS_XML_PREFIX, NS_XML_URI, PI, XMLNS,
+};
+
+type Result<T> = core::result::Result<T, Error>;
+
+/// A builder has no input text, so errors point to this invalid position.
+const NO_POS: TextPos = TextPos { row: 0, col: 0 };
+
+struct PendingAttribute<'input> {
+    prefix: &'input str,
+    local: &'input str,
+    value: StringStorage<'input>,
+}
+
+struct PendingElement<'input> {
+    prefix: &'input str,
+    local: &'input str,
+    attributes: Vec<PendingAttribute<'input>>,
+}
+
+/// A [`Document`] builder.
+///
+/// Allows constructing a document programmatically, without parsing.
+///
+/// Names are qualified names, just like in XML. So `svg:rect` will be resolved
+/// using the in-scope `svg` prefix declared via [`DocumentBuilder::namespace`].
+///
+/// Names are borrowed, while text and attribute values can be either borrowed or owned.
+/// So a document built from static names and owned values is a `Document<'static>`.
+///
+/// Unlike the parser, the builder doesn't require a single root element.
+/// The resulting document doesn't have an input text. Therefore, errors have
+/// a `0:0` position and nodes and attributes have empty ranges.
+///
+/// # Examples
+///
+/// ```
+/// use roxmltree::DocumentBuilder;
+///
+/// let mut builder = DocumentBuilder::new();
+/// builder
+///     .element("svg:svg")
+///     .namespace(Some("svg"), "http://www.w3.org/2000/svg")
+///     .attribute("width", String::from("10"))
+///     .element("svg:rect")
+///     .end()
+///     .text("text")
+///     .end();
+/// let doc = builder.finish().unwrap();
+///
+/// let svg = doc.root_element();
+/// assert!(svg.has_tag_name(("http://www.w3.org/2000/svg", "svg")));
+/// assert_eq!(svg.attribute("width"), Some("10"));
+/// assert!(svg.first_child().unwrap().has_tag_name(("http://www.w3.org/2000/svg", "rect")));
+/// assert_eq!(svg.last_child().unwrap().text(), Some("text"));
+/// ```
+pub struct DocumentBuilder<'input> {
+    doc: Document<'input>,
+    parent_id: NodeId,
+    namespace_start_idx: usize,
+    awaiting_subtree: Vec<NodeId>,
+    pending: Option<PendingElement<'input>>,
+    error: Option<Error>,
+}
+
+impl<'input> DocumentBuilder<'input> {
+    /// Creates a new builder with an empty document.
+    pub fn new() -> Self {
+        let mut doc = Document {
+            text: "",
+            nodes: Vec::new(),
+            attributes: Vec::new(),
+            namespaces: Namespaces::default(),
+            #[cfg(feature = "intern_names")]
+            names: Default::default(),
+            entities: Vec::new(),
+            element_content: BTreeSet::new(),
+            standalone: None,
+            external_subset: false,
+            trailing_content: None,
+        };
+
+        doc.nodes.push(NodeData {
+            parent: None,
+            prev_sibling: None,
+            next_subtree: None,
+            last_child: None,
+            kind: NodeKind::Root,
+            #[cfg(feature = "positions")]
+            range: 0..0,
+            #[cfg(feature = "line_positions")]
+            text_pos: TextPos::new(1, 1),
+        });
+
+        let mut builder = DocumentBuilder {
+            doc,
+            parent_id: NodeId::new(0),
+            namespace_start_idx: 1,
+            awaiting_subtree: Vec::new(),
+            pending: None,
+            error: None,
+        };
+
+        let xml_ns = StringStorage::Borrowed(NS_XML_URI);
+        let res = builder.doc.namespaces.push_ns(Some(NS_XML_PREFIX), xml_ns);
+        builder.check(res);
+
+        builder
+    }
+
+    /// Starts a new element.
+    ///
+    /// Namespaces and attributes can be added until any other node is added.
+    /// All following nodes will be children of this element until [`DocumentBuilder::end`].
+    pub fn element(&mut self, name: &'input str) -> &mut Self {
+        self.check_qname(name);
+        let (prefix, local) = split_qname(name);
+        self.element_parts(prefix, local)
+    }
+
+    fn element_parts(&mut self, prefix: &'input str, local: &'input str) -> &mut Self {
+        self.flush();
+
+        if prefix == XMLNS {
+            self.set_error(Error::InvalidElementNamePrefix(NO_POS));
+        }
+
+        self.pending = Some(PendingElement {
+            prefix,
+            local,
+            attributes: Vec::new(),
+        });
+
+        self
+    }
+
+    /// Declares a namespace on the current element.
+    ///
+    /// # Panics
+    ///
+    /// When called not right after [`DocumentBuilder::element`] or [`DocumentBuilder::attribute`].
+    pub fn namespace<V>(&mut self, prefix: Option<&'input str>, uri: V) -> &mut Self
+    where
+        V: Into<StringStorage<'input>>,
+    {
+        assert!(self.pending.is_some(), "namespaces must follow an element");
+
+        let uri = uri.into();
+        let pos = NO_POS;
+        let is_xml_ns_uri = uri.as_str() == NS_XML_URI;
+        if uri.as_str() == NS_XMLNS_URI {
+            self.set_error(Error::UnexpectedXmlnsUri(pos));
+        } else if prefix == Some(NS_XML_PREFIX) {
+            if !is_xml_ns_uri {
+                self.set_error(Error::InvalidXmlPrefixUri(pos));
+            }
+        } else if is_xml_ns_uri {
+            self.set_error(Error::UnexpectedXmlUri(pos));
+        } else if prefix.is_some_and(|prefix| !is_ncname(prefix)) {
+            self.set_error(Error::InvalidName(pos));
+        } else if self.doc.namespaces.exists(self.namespace_start_idx, prefix) {
+            let name = prefix.unwrap_or_default().to_string();
+            self.set_error(Error::DuplicatedNamespace(name, pos));
+        } else {
+            let res = self.doc.namespaces.push_ns(prefix, uri);
+            self.check(res);
+        }
+
+        self
+    }
+
+    /// Adds an attribute to the current element.
+    ///
+    /// # Panics
+    ///
+    /// When called not right after [`DocumentBuilder::element`] or [`DocumentBuilder::namespace`].
+    pub fn attribute<V>(&mut self, name: &'input str, value: V) -> &mut Self
+    where
+        V: Into<StringStorage<'input>>,
+    {
+        self.check_qname(name);
+        let (prefix, local) = split_qname(name);
+        self.attribute_parts(prefix, local, value.into())
+    }
+
+    fn attribute_parts(
+        &mut self,
+        prefix: &'input str,
+        local: &'input str,
+        value: StringStorage<'input>,
+    ) -> &mut Self {
+        let pending = self
+            .pending
+            .as_mut()
+            .expect("attributes must follow an element");
+        pending.attributes.push(PendingAttribute {
+            prefix,
+            local,
+            value,
+        });
+
+        self
+    }
+
+    /// Adds a text node.
+    ///
+    /// Consecutive text nodes will be merged, just like during parsing.
+    /// An empty text is ignored.
+    pub fn text<V>(&mut self, text: V) -> &mut Self
+    where
+        V: Into<StringStorage<'input>>,
+    {
+        self.flush();
+
+        let text = text.into();
+        if text.is_empty() {
+            return self;
+        }
+
+        let last_child = self.doc.nodes[self.parent_id.get_usize()].last_child;
+        if let Some(id) = last_child {
+            if let NodeKind::Text(ref mut prev_text, _) = self.doc.nodes[id.get_usize()].kind {
+                let mut concat_text = prev_text.as_str().to_string();
+                concat_text.push_str(&text);
+                *prev_text = StringStorage::new_owned(concat_text);
+                return self;
+            }
+        }
+
+        self.append_node(NodeKind::Text(text, false));
+        self
+    }
+
+    /// Adds a comment node.
+    pub fn comment<V>(&mut self, text: V) -> &mut Self
+    where
+        V: Into<StringStorage<'input>>,
+    {
+        self.flush();
+        self.append_node(NodeKind::Comment(text.into()));
+        self
+    }
+
+    /// Adds a processing instruction node.
+    pub fn pi(&mut self, target: &'input str, value: Option<&'input str>) -> &mut Self {
+        self.flush();
+        self.check_qname(target);
+        self.append_node(NodeKind::PI(PI { target, value }));
+        self
+    }
+
+    /// Ends the current element.
+    ///
+    /// # Panics
+    ///
+    /// When there are no open elements.
+    pub fn end(&mut self) -> &mut Self {
+        self.flush();
+
+        let parent = self.doc.nodes[self.parent_id.get_usize()].parent;
+        let parent = parent.expect("there are no open elements");
+        self.awaiting_subtree.push(self.parent_id);
+        self.parent_id = parent;
+        self
+    }
+
+    /// Finishes the document.
+    ///
+    /// Returns the first error that occurred during building.
+    /// All elements must be ended and there must be at least one element.
+    pub fn finish(mut self) -> Result<Document<'input>> {
+        self.flush();
+
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        if self.parent_id.get() != 0 {
+            return Err(Error::UnclosedRootNode);
+        }
+
+        let mut doc = self.doc;
+        if !doc.root().children().any(|n| n.is_element()) {
+            return Err(Error::NoRootNode);
+        }
+
+        doc.nodes.shrink_to_fit();
+        doc.attributes.shrink_to_fit();
+        doc.namespaces.shrink_to_fit();
+        #[cfg(feature = "intern_names")]
+        doc.names.shrink_to_fit();
+
+        Ok(doc)
+    }
+
+    /// Copies a node subtree into a new document.
+    ///
+    /// The root node is copied without the root itself.
+    pub(crate) fn from_subtree(node: Node<'_, 'input>) -> Result<Document<'input>> {
+        let mut builder = DocumentBuilder::new();
+        for edge in node.traverse() {
+            let child = match edge {
+                Edge::Open(child) => child,
+                Edge::Close(child) => {
+                    if child.is_element() {
+                        builder.end();
+                    }
+
+                    continue;
+                }
+            };
+
+            match child.d.kind {
+                NodeKind::Root => {}
+                NodeKind::Element { ref tag_name, .. } => {
+                    builder.element_parts(
+                        tag_name.prefix(node.doc).unwrap_or(""),
+                        tag_name.local_name(node.doc),
+                    );
+
+                    // The copy root must declare all namespaces in scope.
+                    // Others must declare only the ones that differ from the parent.
+                    let parent = child.parent_element().filter(|_| child != node);
+                    for ns in child.namespaces() {
+                        if ns.name == Some(NS_XML_PREFIX) {
+                            continue;
+                        }
+
+                        if parent.is_some_and(|p| p.namespaces().any(|p_ns| p_ns == ns)) {
+                            continue;
+                        }
+
+                        builder.namespace(ns.name, ns.uri.clone());
+                    }
+
+                    for attr in child.attributes() {
+                        let prefix = attr.data.name.prefix(node.doc).unwrap_or("");
+                        let value = attr.value_storage().clone();
+                        builder.attribute_parts(prefix, attr.name(), value);
+                    }
+                }
+                NodeKind::PI(pi) => {
+                    builder.pi(pi.target, pi.value);
+                }
+                NodeKind::Comment(ref text) => {
+                    builder.comment(text.clone());
+                }
+                NodeKind::Text(ref text, _) => {
+                    builder.text(text.clone());
+                }
+            }
+        }
+
+        builder.finish()
+    }
+
+    fn append_node(&mut self, kind: NodeKind<'input>) -> NodeId {
+        self.doc
+            .append_node(self.parent_id, kind, 0..0, &mut self.awaiting_subtree)
+    }
+
+    /// Appends a pending element, if any.
+    fn flush(&mut self) {
+        let pending = match self.pending.take() {
+            Some(pending) => pending,
+            None => return,
+        };
+
+        let res = self
+            .doc
+            .resolve_namespaces(self.parent_id, self.namespace_start_idx);
+        let namespaces = self.check(res).unwrap_or(ShortRange::new(0, 0));
+        self.namespace_start_idx = self.doc.namespaces.tree_order.len();
+
+        let res = self.resolve_attributes(namespaces, pending.attributes);
+        let attributes = self.check(res).unwrap_or(ShortRange::new(0, 0));
+
+        let res = self.ns_idx_by_prefix(namespaces, pending.prefix);
+        let namespace_idx = self.check(res).unwrap_or(None);
+
+        let tag_name = self.doc.new_name(namespace_idx, pending.local);
+        self.parent_id = self.append_node(NodeKind::Element {
+            tag_name,
+            attributes,
+            namespaces,
+            #[cfg(feature = "intern_names")]
+            local_name_offset: None,
+        });
+    }
+
+    fn resolve_attributes(
+        &mut self,
+        namespaces: ShortRange,
+        attributes: Vec<PendingAttribute<'input>>,
+    ) -> Result<ShortRange> {
+        check_attributes_limit(self.doc.attributes.len(), 0, attributes.len())?;
+
+        let start_idx = self.doc.attributes.len();
+        for attr in attributes {
+            let namespace_idx = if attr.prefix == NS_XML_PREFIX {
+                Some(NamespaceIdx(0))
+            } else if attr.prefix.is_empty() {
+                None
+            } else {
+                self.ns_idx_by_prefix(namespaces, attr.prefix)?
+            };
+
+            let attr_name = self.doc.new_name(namespace_idx, attr.local);
+
+            let doc = &self.doc;
+            if doc.attributes[start_idx..]
+                .iter()
+                .any(|a| a.name.as_expanded_name(doc) == attr_name.as_expanded_name(doc))
+            {
+                let name = attr.local.to_string();
+                return Err(Error::DuplicatedAttribute(name, NO_POS, NO_POS));
+            }
+
+            self.doc.attributes.push(AttributeData {
+                name: attr_name,
+                value: attr.value,
+                #[cfg(feature = "positions")]
+                range: 0..0,
+                #[cfg(feature = "positions")]
+                qname_len: 0,
+                #[cfg(feature = "positions")]
+                eq_len: 0,
+            });
+        }
+
+        Ok((start_idx..self.doc.attributes.len()).into())
+    }
+
+    fn ns_idx_by_prefix(
+        &self,
+        namespaces: ShortRange,
+        prefix: &str,
+    ) -> Result<Option<NamespaceIdx>> {
+        let prefix_opt = if prefix.is_empty() {
+            None
+        } else {
+            Some(prefix)
+        };
+
+        let idx = self.doc.namespaces.tree_order[namespaces.to_urange()]
+            .iter()
+            .find(|idx| self.doc.namespaces.get(**idx).name == prefix_opt);
+
+        match idx {
+            Some(idx) => Ok(Some(*idx)),
+            None if prefix.is_empty() => Ok(None),
+            None => Err(Error::UnknownNamespace(prefix.to_string(), NO_POS)),
+        }
+    }
+
+    /// Checks that a qualified name is well-formed.
+    fn check_qname(&mut self, name: &str) {
+        let is_valid = match name.split_once(':') {
+            Some((prefix, local)) => is_ncname(prefix) && is_ncname(local),
+            None => is_ncname(name),
+        };
+
+        if !is_valid {
+            self.set_error(Error::InvalidName(NO_POS));
+        }
+    }
+
+    fn check<T>(&mut self, res: Result<T>) -> Option<T> {
+        match res {
+            Ok(v) => Some(v),
+            Err(e) => {
+                self.set_error(e);
+                None
+            }
+        }
+    }
+
+    fn set_error(&mut self, error: Error) {
+        // Preserve only the first error.
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+    }
+}
+
+impl Default for DocumentBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::fmt::Debug for DocumentBuilder<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("DocumentBuilder")
+            .field("parent_id", &self.parent_id)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+fn split_qname(name: &str) -> (&str, &str) {
+    match name.split_once(':') {
+        Some((prefix, local)) => (prefix, local),
+        None => ("", name),
+    }
+}
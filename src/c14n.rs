@@ -0,0 +1,331 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{Document, Node, Visitor, NS_XML_PREFIX};
+
+/// A Canonical XML mode.
+///
+/// Used by [`Document::canonicalize`] and [`Node::canonicalize`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum C14nMode {
+    /// [Canonical XML 1.0](https://www.w3.org/TR/xml-c14n) without comments.
+    ///
+    /// For whole documents, the output is identical to Canonical XML 1.1.
+    Inclusive,
+
+    /// [Canonical XML 1.0](https://www.w3.org/TR/xml-c14n) with comments.
+    InclusiveWithComments,
+
+    /// [Exclusive XML Canonicalization 1.0](https://www.w3.org/TR/xml-exc-c14n/)
+    /// without comments.
+    Exclusive,
+
+    /// [Exclusive XML Canonicalization 1.0](https://www.w3.org/TR/xml-exc-c14n/)
+    /// with comments.
+    ExclusiveWithComments,
+}
+
+impl C14nMode {
+    #[inline]
+    fn with_comments(self) -> bool {
+        matches!(
+            self,
+            C14nMode::InclusiveWithComments | C14nMode::ExclusiveWithComments
+        )
+    }
+
+    #[inline]
+    fn is_exclusive(self) -> bool {
+        matches!(self, C14nMode::Exclusive | C14nMode::ExclusiveWithComments)
+    }
+}
+
+impl<'input> Document<'input> {
+    /// Returns the document in the Canonical XML form.
+    ///
+    /// Since the document is already parsed, entities are expanded,
+    /// CDATA sections are converted to text and line endings are normalized.
+    /// The XML declaration and DTD are omitted.
+    ///
+    /// Default attributes declared in the DTD are a part of the canonical form,
+    /// but are added to elements only when the document was parsed with
+    /// [`ParsingOptions::apply_attlist_defaults`]. Otherwise, the output
+    /// of documents with such declarations is not canonical.
+    ///
+    /// [`ParsingOptions::apply_attlist_defaults`]: crate::ParsingOptions::apply_attlist_defaults
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::{C14nMode, Document};
+    ///
+    /// let doc = Document::parse("<?xml version='1.0'?>\n<e b='2' a=\"&amp;1\"><c/></e>").unwrap();
+    /// assert_eq!(doc.canonicalize(C14nMode::Inclusive), "<e a=\"&amp;1\" b=\"2\"><c></c></e>");
+    /// ```
+    pub fn canonicalize(&self, mode: C14nMode) -> String {
+        self.root().canonicalize(mode)
+    }
+}
+
+impl<'a, 'input: 'a> Node<'a, 'input> {
+    /// Returns the node subtree in the Canonical XML form.
+    ///
+    /// When used on an element, all namespaces in scope are declared on it
+    /// in the inclusive mode and only the utilized ones in the exclusive mode.
+    /// `xml:*` attributes of ancestors are inherited in the inclusive mode as well.
+    ///
+    /// See [`Document::canonicalize`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::{C14nMode, Document};
+    ///
+    /// let doc = Document::parse(
+    ///     "<e xmlns:a='http://a' xmlns:b='http://b'><a:c b:d='1'/></e>"
+    /// ).unwrap();
+    /// let node = doc.root_element().first_child().unwrap();
+    ///
+    /// assert_eq!(
+    ///     node.canonicalize(C14nMode::Inclusive),
+    ///     "<a:c xmlns:a=\"http://a\" xmlns:b=\"http://b\" b:d=\"1\"></a:c>"
+    /// );
+    /// assert_eq!(
+    ///     node.canonicalize(C14nMode::Exclusive),
+    ///     "<a:c xmlns:a=\"http://a\" xmlns:b=\"http://b\" b:d=\"1\"></a:c>"
+    /// );
+    /// ```
+    pub fn canonicalize(&self, mode: C14nMode) -> String {
+        let mut c14n = Canonicalizer {
+            mode,
+            apex: *self,
+            out: String::new(),
+            rendered: Vec::new(),
+            rendered_lens: Vec::new(),
+            after_root_element: false,
+        };
+        self.accept(&mut c14n);
+        c14n.out
+    }
+}
+
+struct Canonicalizer<'a, 'input: 'a> {
+    mode: C14nMode,
+    apex: Node<'a, 'input>,
+    out: String,
+    // Namespace declarations rendered by the output ancestors.
+    rendered: Vec<(Option<&'input str>, &'a str)>,
+    rendered_lens: Vec<usize>,
+    after_root_element: bool,
+}
+
+impl<'a, 'input: 'a> Canonicalizer<'a, 'input> {
+    fn rendered_uri(&self, prefix: Option<&str>) -> Option<&'a str> {
+        let uri = self
+            .rendered
+            .iter()
+            .rev()
+            .find(|(p, _)| *p == prefix)
+            .map(|(_, uri)| *uri);
+
+        match prefix {
+            // An absent default namespace is the same as an empty one.
+            None => Some(uri.unwrap_or("")),
+            Some(_) => uri,
+        }
+    }
+
+    /// Writes a node located outside the root element.
+    fn write_top_level(&mut self, node: Node<'a, 'input>, f: impl FnOnce(&mut String)) {
+        let top_level = node.parent().is_some_and(|p| p.is_root());
+        if top_level && self.after_root_element {
+            self.out.push('\n');
+        }
+
+        f(&mut self.out);
+
+        if top_level && !self.after_root_element {
+            self.out.push('\n');
+        }
+    }
+}
+
+impl<'a, 'input: 'a> Visitor<'a, 'input> for Canonicalizer<'a, 'input> {
+    fn enter_element(&mut self, node: Node<'a, 'input>) {
+        self.out.push('<');
+        push_qname(&mut self.out, node.prefix(), node.tag_name().name());
+
+        // Collect namespace declarations.
+        let mut namespaces: Vec<(Option<&'input str>, &'a str)> = Vec::new();
+        if self.mode.is_exclusive() {
+            let mut utilized = Vec::new();
+            utilized.push(node.prefix());
+            for attr in node.attributes() {
+                if let Some(prefix) = attr.prefix() {
+                    utilized.push(Some(prefix));
+                }
+            }
+
+            for prefix in utilized {
+                if prefix == Some(NS_XML_PREFIX) {
+                    continue;
+                }
+
+                let uri = match prefix {
+                    Some(prefix) => node.lookup_namespace_uri(Some(prefix)).unwrap_or(""),
+                    None => node.default_namespace().unwrap_or(""),
+                };
+
+                if !namespaces.iter().any(|(p, _)| *p == prefix) {
+                    namespaces.push((prefix, uri));
+                }
+            }
+        } else {
+            namespaces.extend(node.namespaces().map(|ns| (ns.name(), ns.uri())));
+            if !namespaces.iter().any(|(p, _)| p.is_none()) {
+                namespaces.push((None, ""));
+            }
+        }
+
+        namespaces.retain(|(prefix, uri)| self.rendered_uri(*prefix) != Some(uri));
+        namespaces.sort_unstable();
+
+        self.rendered_lens.push(self.rendered.len());
+        for (prefix, uri) in namespaces {
+            self.out.push_str(" xmlns");
+            if let Some(prefix) = prefix {
+                self.out.push(':');
+                self.out.push_str(prefix);
+            }
+            self.out.push_str("=\"");
+            push_escaped_attribute(&mut self.out, uri);
+            self.out.push('"');
+
+            self.rendered.push((prefix, uri));
+        }
+
+        // Collect attributes.
+        let mut attributes: Vec<_> = node
+            .attributes()
+            .map(|attr| {
+                (
+                    attr.namespace().unwrap_or(""),
+                    attr.name(),
+                    attr.prefix(),
+                    attr.value(),
+                )
+            })
+            .collect();
+
+        // Inherit `xml:*` attributes from omitted ancestors.
+        if node == self.apex && !self.mode.is_exclusive() {
+            for ancestor in node.ancestors().skip(1) {
+                for attr in ancestor.attributes() {
+                    if attr.prefix() == Some(NS_XML_PREFIX)
+                        && !attributes
+                            .iter()
+                            .any(|a| a.2 == attr.prefix() && a.1 == attr.name())
+                    {
+                        let uri = attr.namespace().unwrap_or("");
+                        attributes.push((uri, attr.name(), attr.prefix(), attr.value()));
+                    }
+                }
+            }
+        }
+
+        attributes.sort_unstable_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+        for (_, name, prefix, value) in attributes {
+            self.out.push(' ');
+            push_qname(&mut self.out, prefix, name);
+            self.out.push_str("=\"");
+            push_escaped_attribute(&mut self.out, value);
+            self.out.push('"');
+        }
+
+        self.out.push('>');
+    }
+
+    fn leave_element(&mut self, node: Node<'a, 'input>) {
+        self.out.push_str("</");
+        push_qname(&mut self.out, node.prefix(), node.tag_name().name());
+        self.out.push('>');
+
+        if let Some(len) = self.rendered_lens.pop() {
+            self.rendered.truncate(len);
+        }
+
+        if node.parent().is_some_and(|p| p.is_root()) {
+            self.after_root_element = true;
+        }
+    }
+
+    fn visit_text(&mut self, node: Node<'a, 'input>) {
+        push_escaped_text(&mut self.out, node.text().unwrap_or(""));
+    }
+
+    fn visit_comment(&mut self, node: Node<'a, 'input>) {
+        if !self.mode.with_comments() {
+            return;
+        }
+
+        let text = node.comment_text().unwrap_or("");
+        self.write_top_level(node, |out| {
+            out.push_str("<!--");
+            out.push_str(text);
+            out.push_str("-->");
+        });
+    }
+
+    fn visit_pi(&mut self, node: Node<'a, 'input>) {
+        let pi = match node.pi() {
+            Some(pi) => pi,
+            None => return,
+        };
+
+        self.write_top_level(node, |out| {
+            out.push_str("<?");
+            out.push_str(pi.target);
+            if let Some(value) = pi.value.filter(|v| !v.is_empty()) {
+                out.push(' ');
+                out.push_str(value);
+            }
+            out.push_str("?>");
+        });
+    }
+}
+
+fn push_qname(out: &mut String, prefix: Option<&str>, name: &str) {
+    if let Some(prefix) = prefix {
+        out.push_str(prefix);
+        out.push(':');
+    }
+
+    out.push_str(name);
+}
+
+fn push_escaped_text(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' => out.push_str("&#xD;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+fn push_escaped_attribute(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#x9;"),
+            '\n' => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            _ => out.push(c),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::tokenizer::XmlByteExt;
+use crate::{Document, ExpandedName, Node, NodeType};
+
+/// Structural comparison options.
+///
+/// Used by [`Document::structural_eq`] and [`Node::structural_eq`].
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct CompareOptions {
+    /// Ignores text nodes that contain only whitespace characters.
+    ///
+    /// Default: false
+    pub ignore_whitespace: bool,
+
+    /// Ignores comments.
+    ///
+    /// Text nodes separated by ignored comments are compared as a single text.
+    ///
+    /// Default: false
+    pub ignore_comments: bool,
+}
+
+impl<'input> Document<'input> {
+    /// Checks that two documents have the same structure and content.
+    ///
+    /// Unlike a textual comparison, ignores:
+    ///
+    /// - namespace prefixes, since names are compared by namespace URI
+    /// - attributes order
+    /// - namespace declarations placement
+    /// - optionally, whitespace-only text and comments
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::{CompareOptions, Document};
+    ///
+    /// let doc1 = Document::parse("<a:e xmlns:a='http://www.w3.org' x='1' y='2'/>").unwrap();
+    /// let doc2 = Document::parse("<b:e y='2' x='1' xmlns:b='http://www.w3.org'/>").unwrap();
+    /// assert!(doc1.structural_eq(&doc2, CompareOptions::default()));
+    ///
+    /// let doc1 = Document::parse("<e>\n    <b/>\n</e>").unwrap();
+    /// let doc2 = Document::parse("<e><b/></e>").unwrap();
+    /// assert!(!doc1.structural_eq(&doc2, CompareOptions::default()));
+    ///
+    /// let opt = CompareOptions {
+    ///     ignore_whitespace: true,
+    ///     ..CompareOptions::default()
+    /// };
+    /// assert!(doc1.structural_eq(&doc2, opt));
+    /// ```
+    #[inline]
+    pub fn structural_eq(&self, other: &Document, opt: CompareOptions) -> bool {
+        self.root().structural_eq(other.root(), opt)
+    }
+}
+
+impl<'a, 'input: 'a> Node<'a, 'input> {
+    /// Checks that two nodes have the same structure and content.
+    ///
+    /// See [`Document::structural_eq`] for details.
+    pub fn structural_eq(&self, other: Node, opt: CompareOptions) -> bool {
+        if !shallow_eq(*self, other) {
+            return false;
+        }
+
+        // Not recursive on purpose, since documents can be deeply nested.
+        let mut stack = Vec::new();
+        stack.push((*self, other));
+        while let Some((node1, node2)) = stack.pop() {
+            let children1 = collect_children(node1, opt);
+            let children2 = collect_children(node2, opt);
+            if children1.len() != children2.len() {
+                return false;
+            }
+
+            for (child1, child2) in children1.into_iter().zip(children2) {
+                match (child1, child2) {
+                    (Item::Node(n1), Item::Node(n2)) => {
+                        if !shallow_eq(n1, n2) {
+                            return false;
+                        }
+
+                        if n1.is_element() {
+                            stack.push((n1, n2));
+                        }
+                    }
+                    (Item::Text(t1), Item::Text(t2)) => {
+                        if t1 != t2 {
+                            return false;
+                        }
+                    }
+                    _ => return false,
+                }
+            }
+        }
+
+        true
+    }
+}
+
+enum Item<'a, 'input: 'a> {
+    Node(Node<'a, 'input>),
+    Text(String),
+}
+
+/// Collects node's children, merging adjacent text nodes and skipping ignored nodes.
+fn collect_children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    opt: CompareOptions,
+) -> Vec<Item<'a, 'input>> {
+    let mut items: Vec<Item> = Vec::new();
+    for child in node.children() {
+        match child.node_type() {
+            NodeType::Comment if opt.ignore_comments => {}
+            NodeType::Text => {
+                let text = child.text().unwrap_or("");
+                if let Some(Item::Text(prev)) = items.last_mut() {
+                    prev.push_str(text);
+                } else {
+                    items.push(Item::Text(String::from(text)));
+                }
+            }
+            _ => items.push(Item::Node(child)),
+        }
+    }
+
+    if opt.ignore_whitespace {
+        items.retain(|item| match item {
+            Item::Text(text) => !text.bytes().all(|c| c.is_xml_space()),
+            Item::Node(_) => true,
+        });
+    }
+
+    items
+}
+
+/// Compares nodes without their children.
+fn shallow_eq(node1: Node, node2: Node) -> bool {
+    if node1.node_type() != node2.node_type() {
+        return false;
+    }
+
+    match node1.node_type() {
+        NodeType::Root => true,
+        NodeType::Element => {
+            if node1.tag_name() != node2.tag_name() {
+                return false;
+            }
+
+            if node1.attributes_count() != node2.attributes_count() {
+                return false;
+            }
+
+            sorted_attributes(node1) == sorted_attributes(node2)
+        }
+        NodeType::PI => {
+            let pi1 = node1.pi().map(|pi| (pi.target, pi.value));
+            let pi2 = node2.pi().map(|pi| (pi.target, pi.value));
+            pi1 == pi2
+        }
+        NodeType::Comment | NodeType::Text => node1.text() == node2.text(),
+    }
+}
+
+fn sorted_attributes<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+) -> Vec<(ExpandedName<'a, 'input>, &'a str)> {
+    let mut attrs: Vec<_> = node.attributes().pairs().collect();
+    attrs.sort();
+    attrs
+}
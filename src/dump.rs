@@ -0,0 +1,263 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+
+use crate::visitor::Edge;
+use crate::{Document, Node, NodeType};
+
+/// A tree dump format.
+///
+/// Used by [`Document::dump`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DumpFormat {
+    /// An indented, `Debug`-like format.
+    ///
+    /// This is the format used by the `Debug` implementation of the [`Document`].
+    Debug,
+
+    /// A YAML-like format.
+    ///
+    /// Attributes and namespaces are sorted by name, which makes the output
+    /// stable and suitable for snapshot testing.
+    Yaml,
+}
+
+impl<'input> Document<'input> {
+    /// Writes the document tree into the writer using the specified format.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e a='b'>text</e>").unwrap();
+    ///
+    /// let mut s = String::new();
+    /// doc.dump(&mut s, roxmltree::DumpFormat::Yaml).unwrap();
+    /// assert_eq!(s, "\
+    /// Document:
+    ///   - Element:
+    ///       tag_name: e
+    ///       attributes:
+    ///         a: \"b\"
+    ///       children:
+    ///         - Text: \"text\"
+    /// ");
+    /// ```
+    pub fn dump<W: Write>(&self, w: &mut W, format: DumpFormat) -> fmt::Result {
+        match format {
+            DumpFormat::Debug => dump_debug(self, w),
+            DumpFormat::Yaml => dump_yaml(self, w),
+        }
+    }
+}
+
+macro_rules! writeln_indented {
+    ($indent:expr, $depth:expr, $w:expr, $fmt:expr) => {
+        for _ in 0..$depth { $w.write_str($indent)?; }
+        writeln!($w, $fmt)?;
+    };
+    ($indent:expr, $depth:expr, $w:expr, $fmt:expr, $($arg:tt)*) => {
+        for _ in 0..$depth { $w.write_str($indent)?; }
+        writeln!($w, $fmt, $($arg)*)?;
+    };
+}
+
+fn dump_debug<W: Write>(doc: &Document, w: &mut W) -> fmt::Result {
+    if !doc.root().has_children() {
+        return write!(w, "Document []");
+    }
+
+    macro_rules! writeln_indented_debug {
+        ($($arg:tt)*) => { writeln_indented!("    ", $($arg)*) };
+    }
+
+    fn print_into_iter<
+        T: fmt::Debug,
+        E: ExactSizeIterator<Item = T>,
+        I: IntoIterator<Item = T, IntoIter = E>,
+        W: Write,
+    >(
+        prefix: &str,
+        data: I,
+        depth: usize,
+        w: &mut W,
+    ) -> fmt::Result {
+        let data = data.into_iter();
+        if data.len() == 0 {
+            return Ok(());
+        }
+
+        writeln_indented_debug!(depth, w, "{}: [", prefix);
+        for v in data {
+            writeln_indented_debug!(depth + 1, w, "{:?}", v);
+        }
+        writeln_indented_debug!(depth, w, "]");
+
+        Ok(())
+    }
+
+    writeln!(w, "Document [")?;
+    walk(
+        doc.root(),
+        w,
+        |child, level, w| {
+            let depth = 1 + level * 2;
+            if child.is_element() {
+                writeln_indented_debug!(depth, w, "Element {{");
+                writeln_indented_debug!(depth, w, "    tag_name: {:?}", child.tag_name());
+                print_into_iter("attributes", child.attributes(), depth + 1, w)?;
+                print_into_iter("namespaces", child.namespaces(), depth + 1, w)?;
+
+                if child.has_children() {
+                    writeln_indented_debug!(depth, w, "    children: [");
+                } else {
+                    writeln_indented_debug!(depth, w, "}}");
+                }
+            } else {
+                writeln_indented_debug!(depth, w, "{:?}", child);
+            }
+
+            Ok(())
+        },
+        |_, level, w| {
+            let depth = 1 + level * 2;
+            writeln_indented_debug!(depth, w, "    ]");
+            writeln_indented_debug!(depth, w, "}}");
+            Ok(())
+        },
+    )?;
+    writeln!(w, "]")?;
+
+    Ok(())
+}
+
+fn dump_yaml<W: Write>(doc: &Document, w: &mut W) -> fmt::Result {
+    if !doc.root().has_children() {
+        return write!(w, "Document:");
+    }
+
+    macro_rules! writeln_indented_yaml {
+        ($($arg:tt)*) => { writeln_indented!("  ", $($arg)*) };
+    }
+
+    fn print_node<W: Write>(child: Node, depth: usize, w: &mut W) -> fmt::Result {
+        match child.node_type() {
+            NodeType::Element => {
+                writeln_indented_yaml!(depth, w, "- Element:");
+
+                let tag_name = child.tag_name();
+                match tag_name.namespace() {
+                    Some(ns) if !ns.is_empty() => {
+                        writeln_indented_yaml!(
+                            depth + 2,
+                            w,
+                            "tag_name: {}@{}",
+                            tag_name.name(),
+                            ns
+                        );
+                    }
+                    _ => {
+                        writeln_indented_yaml!(depth + 2, w, "tag_name: {}", tag_name.name());
+                    }
+                }
+
+                let attributes = child.attributes();
+                if attributes.len() != 0 {
+                    let mut attrs: Vec<_> = attributes
+                        .map(|attr| match attr.namespace() {
+                            Some(ns) => (alloc::format!("{}@{}", attr.name(), ns), attr.value()),
+                            None => (attr.name().to_string(), attr.value()),
+                        })
+                        .collect();
+                    attrs.sort_by(|a, b| a.0.cmp(&b.0));
+
+                    writeln_indented_yaml!(depth + 2, w, "attributes:");
+                    for (name, value) in attrs {
+                        writeln_indented_yaml!(depth + 3, w, "{}: {:?}", name, value);
+                    }
+                }
+
+                let namespaces = child.namespaces();
+                if namespaces.len() != 0 {
+                    let mut ns_list: Vec<_> = namespaces
+                        .map(|ns| {
+                            let name = ns.name().unwrap_or("None");
+                            let uri = if ns.uri().is_empty() {
+                                "\"\""
+                            } else {
+                                ns.uri()
+                            };
+                            (name, uri)
+                        })
+                        .collect();
+                    ns_list.sort_by(|a, b| a.0.cmp(b.0));
+
+                    writeln_indented_yaml!(depth + 2, w, "namespaces:");
+                    for (name, uri) in ns_list {
+                        writeln_indented_yaml!(depth + 3, w, "{}: {}", name, uri);
+                    }
+                }
+
+                if child.has_children() {
+                    writeln_indented_yaml!(depth + 2, w, "children:");
+                }
+            }
+            NodeType::Text => {
+                writeln_indented_yaml!(depth, w, "- Text: {:?}", child.text().unwrap());
+            }
+            NodeType::Comment => {
+                writeln_indented_yaml!(depth, w, "- Comment: {:?}", child.text().unwrap());
+            }
+            NodeType::PI => {
+                writeln_indented_yaml!(depth, w, "- PI:");
+
+                let pi = child.pi().unwrap();
+                writeln_indented_yaml!(depth + 2, w, "target: {:?}", pi.target);
+                if let Some(value) = pi.value {
+                    writeln_indented_yaml!(depth + 2, w, "value: {:?}", value);
+                }
+            }
+            NodeType::Root => {}
+        }
+
+        Ok(())
+    }
+
+    writeln!(w, "Document:")?;
+    walk(
+        doc.root(),
+        w,
+        |child, level, w| print_node(child, 1 + level * 3, w),
+        |_, _, _| Ok(()),
+    )?;
+
+    Ok(())
+}
+
+/// Walks node's descendants in the document order.
+///
+/// `leave` is called only for elements with children.
+fn walk<'a, 'input: 'a, W: Write>(
+    root: Node<'a, 'input>,
+    w: &mut W,
+    mut enter: impl FnMut(Node<'a, 'input>, usize, &mut W) -> fmt::Result,
+    mut leave: impl FnMut(Node<'a, 'input>, usize, &mut W) -> fmt::Result,
+) -> fmt::Result {
+    let mut depth = 0;
+    for edge in root.traverse() {
+        match edge {
+            Edge::Open(node) if node != root => {
+                enter(node, depth, w)?;
+                if node.is_element() && node.has_children() {
+                    depth += 1;
+                }
+            }
+            Edge::Close(node) if node != root && node.is_element() && node.has_children() => {
+                depth -= 1;
+                leave(node, depth, w)?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
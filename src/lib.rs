@@ -30,15 +30,41 @@ use core::hash::{Hash, Hasher};
 use core::num::NonZeroU32;
 use core::ops::Range;
 
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 
+use crate::tokenizer::XmlByteExt;
+
+mod build;
+mod c14n;
+mod compare;
+mod dump;
+mod owned;
 mod parse;
+#[cfg(feature = "quick-xml")]
+mod quick_xml_events;
+#[cfg(feature = "serde")]
+mod ser;
 mod tokenizer;
+mod visitor;
 
+#[cfg(test)]
+mod parse_tests;
 #[cfg(test)]
 mod tokenizer_tests;
 
+pub use crate::build::DocumentBuilder;
+pub use crate::c14n::C14nMode;
+pub use crate::compare::CompareOptions;
+pub use crate::dump::DumpFormat;
+pub use crate::owned::OwnedDocument;
 pub use crate::parse::*;
+#[cfg(feature = "quick-xml")]
+pub use crate::quick_xml_events::QuickXmlEvents;
+#[cfg(feature = "serde")]
+pub use crate::ser::{NameStyle, SerializableNode};
+pub use crate::visitor::Visitor;
 
 /// The <http://www.w3.org/XML/1998/namespace> URI.
 pub const NS_XML_URI: &str = "http://www.w3.org/XML/1998/namespace";
@@ -103,6 +129,18 @@ pub struct Document<'input> {
     nodes: Vec<NodeData<'input>>,
     attributes: Vec<AttributeData<'input>>,
     namespaces: Namespaces<'input>,
+    #[cfg(feature = "intern_names")]
+    names: Names<'input>,
+    /// General entities declared in the DTD. External ones don't have a value.
+    entities: Vec<(&'input str, Option<&'input str>)>,
+    /// Elements declared with element content in the DTD, as prefix and local name pairs.
+    element_content: BTreeSet<(&'input str, &'input str)>,
+    /// The `standalone` value of the XML declaration.
+    standalone: Option<bool>,
+    /// Whether the DTD references an external subset.
+    external_subset: bool,
+    /// Ignored content after the root element.
+    trailing_content: Option<usize>,
 }
 
 impl<'input> Document<'input> {
@@ -158,6 +196,9 @@ impl<'input> Document<'input> {
     ///
     /// The root element always exists.
     ///
+    /// For documents parsed via [`Document::parse_fragment`] the first top-level element
+    /// will be returned.
+    ///
     /// # Examples
     ///
     /// ```
@@ -167,11 +208,79 @@ impl<'input> Document<'input> {
     #[inline]
     pub fn root_element<'a>(&'a self) -> Node<'a, 'input> {
         // `expect` is safe, because the `Document` is guarantee to have at least one element.
-        self.root()
-            .first_element_child()
+        self.try_root_element()
             .expect("XML documents must contain a root element")
     }
 
+    /// Returns the root element of the document, if any.
+    ///
+    /// A non-panicking variant of [`Document::root_element`].
+    /// Since parsing and building fail without a root element,
+    /// this method returns `None` only when this invariant is broken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<!-- comment --><e/>").unwrap();
+    /// assert!(doc.try_root_element().unwrap().has_tag_name("e"));
+    /// ```
+    #[inline]
+    pub fn try_root_element<'a>(&'a self) -> Option<Node<'a, 'input>> {
+        self.root().first_element_child()
+    }
+
+    /// Returns an iterator over document's top-level elements.
+    ///
+    /// Useful only for documents parsed via [`Document::parse_fragment`],
+    /// since a regular document has exactly one root element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opt = roxmltree::ParsingOptions::default();
+    /// let doc = roxmltree::Document::parse_fragment("<a/><!-- comment --><b/>", opt).unwrap();
+    /// let names: Vec<_> = doc.root_elements().map(|n| n.tag_name().name()).collect();
+    /// assert_eq!(names, ["a", "b"]);
+    /// ```
+    #[inline]
+    pub fn root_elements<'a>(&'a self) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+        self.root().children().filter(|n| n.is_element())
+    }
+
+    /// Returns an iterator over comments and processing instructions before the root element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("\
+    /// <?xml-stylesheet href='style.css'?>
+    /// <!-- license -->
+    /// <e/>
+    /// <!-- end -->
+    /// ").unwrap();
+    ///
+    /// let pi = doc.prologue_nodes().next().unwrap();
+    /// assert_eq!(pi.pi().unwrap().target, "xml-stylesheet");
+    /// assert_eq!(doc.prologue_nodes().count(), 2);
+    /// assert_eq!(doc.epilogue_nodes().next().unwrap().text(), Some(" end "));
+    /// ```
+    #[inline]
+    pub fn prologue_nodes<'a>(&'a self) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+        self.root().children().take_while(|n| !n.is_element())
+    }
+
+    /// Returns an iterator over comments and processing instructions after the root element.
+    ///
+    /// For documents parsed via [`Document::parse_fragment`],
+    /// returns nodes after the last top-level element.
+    #[inline]
+    pub fn epilogue_nodes<'a>(&'a self) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+        let last_element = self.root().last_element_child();
+        last_element
+            .into_iter()
+            .flat_map(|n| n.next_siblings().skip(1))
+    }
+
     /// Returns an iterator over document's descendant nodes.
     ///
     /// Shorthand for `doc.root().descendants()`.
@@ -183,6 +292,7 @@ impl<'input> Document<'input> {
     /// Calculates `TextPos` in the original document from position in bytes.
     ///
     /// **Note:** this operation is expensive.
+    /// Use [`Document::line_index`] when many positions must be calculated.
     ///
     /// # Examples
     ///
@@ -202,6 +312,77 @@ impl<'input> Document<'input> {
         tokenizer::Stream::new(self.text).gen_text_pos_from(pos)
     }
 
+    /// Builds a line index for the original document.
+    ///
+    /// Unlike [`Document::text_pos_at`], which rescans the text on each call,
+    /// scans the text only once and then calculates positions in O(log n),
+    /// regardless of the lines length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::*;
+    ///
+    /// let doc = Document::parse("\
+    /// <!-- comment -->
+    /// <e/>"
+    /// ).unwrap();
+    ///
+    /// let index = doc.line_index();
+    /// assert_eq!(index.pos_at(10), TextPos::new(1, 11));
+    /// assert_eq!(index.pos_at(9999), TextPos::new(2, 5));
+    /// ```
+    pub fn line_index(&self) -> LineIndex<'input> {
+        LineIndex::new(self.text)
+    }
+
+    /// Builds an index of elements by the value of a specified attribute.
+    ///
+    /// Useful for multiple `getElementById`-like lookups,
+    /// which otherwise require a linear search each.
+    ///
+    /// When multiple elements have the same value, the first one wins.
+    /// The rest can be retrieved via [`IdIndex::duplicates`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<svg id='svg1'><rect id='rect1'/><g xml:id='g1'/><rect id='rect1'/></svg>"
+    /// ).unwrap();
+    ///
+    /// let index = doc.build_id_index("id");
+    /// assert_eq!(index.len(), 2);
+    /// assert!(index.element_by_id("rect1").unwrap().has_tag_name("rect"));
+    /// assert!(index.element_by_id("g1").is_none());
+    /// assert_eq!(index.duplicates().len(), 1);
+    ///
+    /// let index = doc.build_id_index((roxmltree::NS_XML_URI, "id"));
+    /// assert!(index.element_by_id("g1").unwrap().has_tag_name("g"));
+    /// ```
+    pub fn build_id_index<'n, 'm, N>(&self, attr_name: N) -> IdIndex<'_, 'input>
+    where
+        N: Into<ExpandedName<'n, 'm>>,
+    {
+        let attr_name = attr_name.into();
+        let mut elements = BTreeMap::new();
+        let mut duplicates = Vec::new();
+        for node in self.descendants() {
+            if let Some(id) = node.attribute(attr_name) {
+                if elements.contains_key(id) {
+                    duplicates.push(node);
+                } else {
+                    elements.insert(id, node);
+                }
+            }
+        }
+
+        IdIndex {
+            elements,
+            duplicates,
+        }
+    }
+
     /// Returns the input text of the original document.
     ///
     /// # Examples
@@ -217,81 +398,291 @@ impl<'input> Document<'input> {
     pub fn input_text(&self) -> &'input str {
         self.text
     }
-}
 
-impl<'input> fmt::Debug for Document<'input> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        if !self.root().has_children() {
-            return write!(f, "Document []");
+    /// Returns the position in bytes of the content ignored after the root element.
+    ///
+    /// Can be set only when [`ParsingOptions::allow_trailing_content`] is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opt = roxmltree::ParsingOptions {
+    ///     allow_trailing_content: true,
+    ///     ..roxmltree::ParsingOptions::default()
+    /// };
+    ///
+    /// let doc = roxmltree::Document::parse_with_options("<e/>\n<!--c-->\nlog", opt).unwrap();
+    /// assert_eq!(doc.trailing_content_offset(), Some(14));
+    /// assert_eq!(doc.root().last_child().unwrap().text(), Some("c"));
+    ///
+    /// let doc = roxmltree::Document::parse_with_options("<e/>\n", opt).unwrap();
+    /// assert_eq!(doc.trailing_content_offset(), None);
+    /// ```
+    #[inline]
+    pub fn trailing_content_offset(&self) -> Option<usize> {
+        self.trailing_content
+    }
+
+    /// Returns the `standalone` value of the XML declaration.
+    ///
+    /// Returns `None` when there is no XML declaration, `standalone` is not set
+    /// or has a value other than `yes` or `no`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<?xml version='1.0' standalone='yes'?><e/>").unwrap();
+    /// assert_eq!(doc.is_standalone(), Some(true));
+    ///
+    /// let doc = roxmltree::Document::parse("<e/>").unwrap();
+    /// assert_eq!(doc.is_standalone(), None);
+    /// ```
+    #[inline]
+    pub fn is_standalone(&self) -> Option<bool> {
+        self.standalone
+    }
+
+    /// Checks that the DTD references an external subset via `SYSTEM` or `PUBLIC`.
+    ///
+    /// The external subset itself is never loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opt = roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() };
+    /// let doc = roxmltree::Document::parse_with_options(
+    ///     "<!DOCTYPE e SYSTEM 'e.dtd'><e/>", opt
+    /// ).unwrap();
+    /// assert!(doc.has_external_subset());
+    ///
+    /// let doc = roxmltree::Document::parse_with_options("<!DOCTYPE e []><e/>", opt).unwrap();
+    /// assert!(!doc.has_external_subset());
+    /// ```
+    #[inline]
+    pub fn has_external_subset(&self) -> bool {
+        self.external_subset
+    }
+
+    /// Returns an iterator over general entities declared in the DTD, as name and value pairs.
+    ///
+    /// Values are returned as declared, without resolving references.
+    /// External entities are not loaded, therefore they are returned with a `None` value.
+    /// Parameter entities are ignored.
+    /// When an entity is declared multiple times, only the first declaration is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opt = roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() };
+    /// let doc = roxmltree::Document::parse_with_options("\
+    /// <!DOCTYPE e [
+    ///     <!ENTITY a 'text&#x20;1'>
+    ///     <!ENTITY % b 'text2'>
+    ///     <!ENTITY c SYSTEM 'c.xml'>
+    /// ]>
+    /// <e/>
+    /// ", opt).unwrap();
+    ///
+    /// let entities: Vec<_> = doc.entities().collect();
+    /// assert_eq!(entities, [("a", Some("text&#x20;1")), ("c", None)]);
+    /// ```
+    #[inline]
+    pub fn entities(
+        &self,
+    ) -> impl ExactSizeIterator<Item = (&'input str, Option<&'input str>)> + '_ {
+        self.entities.iter().copied()
+    }
+
+    /// Returns document's memory statistics.
+    ///
+    /// Useful for tuning [`ParsingOptions::nodes_limit`].
+    ///
+    /// **Note:** this operation walks all nodes, attributes and namespaces.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e a='&amp;'>text</e>").unwrap();
+    /// let stats = doc.stats();
+    ///
+    /// assert_eq!(stats.nodes, 3);
+    /// assert_eq!(stats.attributes, 1);
+    /// assert_eq!(stats.owned_string_bytes, 1);
+    /// ```
+    pub fn stats(&self) -> DocumentStats {
+        fn owned_len(s: &StringStorage) -> usize {
+            match s {
+                StringStorage::Borrowed(_) => 0,
+                StringStorage::Owned(s) => s.len(),
+            }
         }
 
-        macro_rules! writeln_indented {
-            ($depth:expr, $f:expr, $fmt:expr) => {
-                for _ in 0..$depth { write!($f, "    ")?; }
-                writeln!($f, $fmt)?;
-            };
-            ($depth:expr, $f:expr, $fmt:expr, $($arg:tt)*) => {
-                for _ in 0..$depth { write!($f, "    ")?; }
-                writeln!($f, $fmt, $($arg)*)?;
-            };
+        let nodes_bytes: usize = self
+            .nodes
+            .iter()
+            .map(|d| match d.kind {
+                NodeKind::Text(ref s, _) | NodeKind::Comment(ref s) => owned_len(s),
+                _ => 0,
+            })
+            .sum();
+        let attributes_bytes: usize = self.attributes.iter().map(|a| owned_len(&a.value)).sum();
+        let namespaces_bytes: usize = self
+            .namespaces
+            .values
+            .iter()
+            .map(|ns| owned_len(&ns.uri))
+            .sum();
+
+        DocumentStats {
+            nodes: self.nodes.len(),
+            nodes_capacity: self.nodes.capacity(),
+            attributes: self.attributes.len(),
+            attributes_capacity: self.attributes.capacity(),
+            namespaces: self.namespaces.values.len(),
+            owned_string_bytes: nodes_bytes + attributes_bytes + namespaces_bytes,
         }
+    }
+}
 
-        fn print_into_iter<
-            T: fmt::Debug,
-            E: ExactSizeIterator<Item = T>,
-            I: IntoIterator<Item = T, IntoIter = E>,
-        >(
-            prefix: &str,
-            data: I,
-            depth: usize,
-            f: &mut fmt::Formatter,
-        ) -> Result<(), fmt::Error> {
-            let data = data.into_iter();
-            if data.len() == 0 {
-                return Ok(());
-            }
+/// Document's memory statistics.
+///
+/// Can be retrieved via [`Document::stats`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DocumentStats {
+    /// The number of nodes, including the root node.
+    pub nodes: usize,
+    /// The capacity of the nodes storage.
+    pub nodes_capacity: usize,
+    /// The number of attributes.
+    pub attributes: usize,
+    /// The capacity of the attributes storage.
+    pub attributes_capacity: usize,
+    /// The number of unique namespaces, including the predefined `xml` one.
+    pub namespaces: usize,
+    /// The total length in bytes of all strings that are not borrowed from the input,
+    /// like unescaped text and attribute values.
+    pub owned_string_bytes: usize,
+}
 
-            writeln_indented!(depth, f, "{}: [", prefix);
-            for v in data {
-                writeln_indented!(depth + 1, f, "{:?}", v);
-            }
-            writeln_indented!(depth, f, "]");
-
-            Ok(())
-        }
-
-        fn print_children(
-            parent: Node,
-            depth: usize,
-            f: &mut fmt::Formatter,
-        ) -> Result<(), fmt::Error> {
-            for child in parent.children() {
-                if child.is_element() {
-                    writeln_indented!(depth, f, "Element {{");
-                    writeln_indented!(depth, f, "    tag_name: {:?}", child.tag_name());
-                    print_into_iter("attributes", child.attributes(), depth + 1, f)?;
-                    print_into_iter("namespaces", child.namespaces(), depth + 1, f)?;
-
-                    if child.has_children() {
-                        writeln_indented!(depth, f, "    children: [");
-                        print_children(child, depth + 2, f)?;
-                        writeln_indented!(depth, f, "    ]");
-                    }
+/// A precomputed index of line starts.
+///
+/// Can be retrieved via [`Document::line_index`].
+#[derive(Clone)]
+pub struct LineIndex<'input> {
+    text: &'input str,
+    // Byte offsets of all line starts. The first one is always 0.
+    line_starts: Vec<usize>,
+    // The number of characters before each `CHARS_CHUNK_LEN` bytes chunk.
+    // Allows counting columns without scanning the whole line.
+    chunk_chars: Vec<usize>,
+}
 
-                    writeln_indented!(depth, f, "}}");
-                } else {
-                    writeln_indented!(depth, f, "{:?}", child);
-                }
-            }
+const CHARS_CHUNK_LEN: usize = 256;
+
+#[inline]
+fn is_char_start(c: u8) -> bool {
+    // Not a UTF-8 continuation byte.
+    c & 0xC0 != 0x80
+}
+
+impl<'input> LineIndex<'input> {
+    fn new(text: &'input str) -> Self {
+        let mut line_starts = alloc::vec![0];
+        line_starts.extend(
+            text.bytes()
+                .enumerate()
+                .filter(|(_, c)| *c == b'\n')
+                .map(|(idx, _)| idx + 1),
+        );
+
+        let mut chunk_chars = Vec::with_capacity(text.len() / CHARS_CHUNK_LEN + 2);
+        let mut chars = 0;
+        chunk_chars.push(chars);
+        for chunk in text.as_bytes().chunks(CHARS_CHUNK_LEN) {
+            chars += chunk.iter().filter(|c| is_char_start(**c)).count();
+            chunk_chars.push(chars);
+        }
 
-            Ok(())
+        LineIndex {
+            text,
+            line_starts,
+            chunk_chars,
         }
+    }
 
-        writeln!(f, "Document [")?;
-        print_children(self.root(), 1, f)?;
-        writeln!(f, "]")?;
+    // The number of characters before the specified position.
+    fn chars_before(&self, pos: usize) -> usize {
+        let chunk = pos / CHARS_CHUNK_LEN;
+        let chunk_start = chunk * CHARS_CHUNK_LEN;
+        let rest = &self.text.as_bytes()[chunk_start..pos];
+        self.chunk_chars[chunk] + rest.iter().filter(|c| is_char_start(**c)).count()
+    }
 
-        Ok(())
+    /// Calculates `TextPos` in the original document from position in bytes.
+    ///
+    /// Produces the same result as [`Document::text_pos_at`].
+    pub fn pos_at(&self, pos: usize) -> TextPos {
+        let pos = core::cmp::min(pos, self.text.len());
+        // Never zero, since the first line start is always 0.
+        let row = self.line_starts.partition_point(|start| *start <= pos);
+        let line_start = self.line_starts[row - 1];
+        let col = self.chars_before(pos) - self.chars_before(line_start) + 1;
+        TextPos::new(row as u32, col as u32)
+    }
+
+    /// Returns the number of lines.
+    #[inline]
+    pub fn lines_count(&self) -> usize {
+        self.line_starts.len()
+    }
+}
+
+/// An index of elements by an ID-like attribute value.
+///
+/// Can be retrieved via [`Document::build_id_index`].
+#[derive(Clone, Debug)]
+pub struct IdIndex<'a, 'input: 'a> {
+    elements: BTreeMap<&'a str, Node<'a, 'input>>,
+    duplicates: Vec<Node<'a, 'input>>,
+}
+
+impl<'a, 'input: 'a> IdIndex<'a, 'input> {
+    /// Returns an element with a specified ID.
+    #[inline]
+    pub fn element_by_id(&self, id: &str) -> Option<Node<'a, 'input>> {
+        self.elements.get(id).copied()
+    }
+
+    /// Returns elements with an already used ID, in the document order.
+    #[inline]
+    pub fn duplicates(&self) -> &[Node<'a, 'input>] {
+        &self.duplicates
+    }
+
+    /// Returns the number of unique IDs.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Checks that the index is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+}
+
+impl fmt::Debug for LineIndex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("LineIndex")
+            .field("lines_count", &self.lines_count())
+            .finish()
+    }
+}
+
+impl<'input> fmt::Debug for Document<'input> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        self.dump(f, DumpFormat::Debug)
     }
 }
 
@@ -312,6 +703,33 @@ pub enum NodeType {
     Text,
 }
 
+/// A kind of element content.
+///
+/// Comments and processing instructions are not taken into account.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ContentKind {
+    /// No child elements and no text.
+    Empty,
+    /// Only text, including whitespace-only text.
+    TextOnly,
+    /// Only child elements, optionally separated by whitespace-only text.
+    ElementOnly,
+    /// Child elements interleaved with non-whitespace text.
+    Mixed,
+}
+
+/// A whitespace handling mode specified via the `xml:space` attribute.
+///
+/// [Spec](https://www.w3.org/TR/xml/#sec-white-space)
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum XmlSpace {
+    /// Application's default whitespace handling.
+    #[default]
+    Default,
+    /// Whitespace must be preserved.
+    Preserve,
+}
+
 /// A processing instruction.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[allow(missing_docs)]
@@ -332,8 +750,8 @@ struct ShortRange {
 impl From<Range<usize>> for ShortRange {
     #[inline]
     fn from(range: Range<usize>) -> Self {
-        debug_assert!(range.start <= core::u32::MAX as usize);
-        debug_assert!(range.end <= core::u32::MAX as usize);
+        debug_assert!(range.start <= u32::MAX as usize);
+        debug_assert!(range.end <= u32::MAX as usize);
         ShortRange::new(range.start as u32, range.end as u32)
     }
 }
@@ -348,6 +766,11 @@ impl ShortRange {
     fn to_urange(self) -> Range<usize> {
         self.start as usize..self.end as usize
     }
+
+    #[inline]
+    fn len(self) -> usize {
+        (self.end - self.start) as usize
+    }
 }
 
 /// A node ID stored as `u32`.
@@ -365,7 +788,7 @@ impl NodeId {
     /// Construct a new `NodeId` from a `u32`.
     #[inline]
     pub fn new(id: u32) -> Self {
-        debug_assert!(id < core::u32::MAX);
+        debug_assert!(id < u32::MAX);
 
         // We are using `NonZeroU32` to reduce overhead of `Option<NodeId>`.
         NodeId(NonZeroU32::new(id + 1).unwrap())
@@ -395,7 +818,7 @@ impl From<usize> for NodeId {
     #[inline]
     fn from(id: usize) -> Self {
         // We already checked that `id` is limited by u32::MAX.
-        debug_assert!(id <= core::u32::MAX as usize);
+        debug_assert!(id <= u32::MAX as usize);
         NodeId::new(id as u32)
     }
 }
@@ -407,10 +830,29 @@ enum NodeKind<'input> {
         tag_name: ExpandedNameIndexed<'input>,
         attributes: ShortRange,
         namespaces: ShortRange,
+        // Interned names point to their first occurrence,
+        // so the position of the element's own name is stored separately.
+        #[cfg(feature = "intern_names")]
+        local_name_offset: Option<usize>,
     },
     PI(PI<'input>),
     Comment(StringStorage<'input>),
-    Text(StringStorage<'input>),
+    // The flag is set when the text doesn't come from a single contiguous part
+    // of the input, i.e. it was merged from several parts or produced by an entity.
+    Text(StringStorage<'input>, bool),
+}
+
+impl NodeKind<'_> {
+    #[inline]
+    fn node_type(&self) -> NodeType {
+        match self {
+            NodeKind::Root => NodeType::Root,
+            NodeKind::Element { .. } => NodeType::Element,
+            NodeKind::PI { .. } => NodeType::PI,
+            NodeKind::Comment(_) => NodeType::Comment,
+            NodeKind::Text(..) => NodeType::Text,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -422,6 +864,8 @@ struct NodeData<'input> {
     kind: NodeKind<'input>,
     #[cfg(feature = "positions")]
     range: Range<usize>,
+    #[cfg(feature = "line_positions")]
+    text_pos: TextPos,
 }
 
 #[cfg(target_has_atomic = "ptr")]
@@ -462,6 +906,43 @@ impl StringStorage<'_> {
             StringStorage::Owned(s) => s,
         }
     }
+
+    /// Converts into a reference-counted string that doesn't borrow the input.
+    ///
+    /// An owned string is returned as is, while a borrowed one will be allocated.
+    /// The result is `Arc<str>` or `Rc<str>` on targets where `Arc` isn't available.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e>text</e>").unwrap();
+    /// let storage = doc.root_element().text_storage().unwrap().clone();
+    /// let text = storage.into_owned_arc();
+    /// drop(doc);
+    ///
+    /// assert_eq!(&*text, "text");
+    /// ```
+    #[inline]
+    pub fn into_owned_arc(self) -> OwnedSharedString {
+        match self {
+            StringStorage::Borrowed(s) => s.into(),
+            StringStorage::Owned(s) => s,
+        }
+    }
+}
+
+impl<'input> From<&'input str> for StringStorage<'input> {
+    #[inline]
+    fn from(s: &'input str) -> Self {
+        StringStorage::Borrowed(s)
+    }
+}
+
+impl From<String> for StringStorage<'_> {
+    #[inline]
+    fn from(s: String) -> Self {
+        StringStorage::new_owned(s)
+    }
 }
 
 impl PartialEq for StringStorage<'_> {
@@ -491,9 +972,21 @@ struct AttributeData<'input> {
     #[cfg(feature = "positions")]
     range: Range<usize>,
     #[cfg(feature = "positions")]
-    qname_len: u16,
+    qname_len: u32, // zero when the attribute has no source, see `has_source`
     #[cfg(feature = "positions")]
-    eq_len: u8, // includes any surrounding spaces
+    eq_len: u32, // includes any surrounding spaces
+}
+
+#[cfg(feature = "positions")]
+impl AttributeData<'_> {
+    /// Checks that the attribute was parsed from the input text.
+    ///
+    /// Attributes created via [`DocumentBuilder`] have no source
+    /// and are marked with a zero `qname_len`, since a parsed qname cannot be empty.
+    #[inline]
+    fn has_source(&self) -> bool {
+        self.qname_len != 0
+    }
 }
 
 /// An attribute.
@@ -521,6 +1014,30 @@ impl<'a, 'input> Attribute<'a, 'input> {
         self.data.name.namespace(self.doc).map(Namespace::uri)
     }
 
+    /// Returns attribute's namespace handle.
+    ///
+    /// Cheaper to compare and hash than [`Attribute::namespace`].
+    /// See [`NamespaceId`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<e xmlns:n='http://www.w3.org' a='b' n:a='c' n:b='d'/>"
+    /// ).unwrap();
+    ///
+    /// let mut iter = doc.root_element().attributes();
+    /// assert_eq!(iter.next().unwrap().namespace_id(), None);
+    /// let id1 = iter.next().unwrap().namespace_id();
+    /// let id2 = iter.next().unwrap().namespace_id();
+    /// assert!(id1.is_some());
+    /// assert_eq!(id1, id2);
+    /// ```
+    #[inline]
+    pub fn namespace_id(&self) -> Option<NamespaceId> {
+        self.data.name.namespace_idx.map(NamespaceId::from)
+    }
+
     /// Returns attribute's name.
     ///
     /// # Examples
@@ -535,29 +1052,49 @@ impl<'a, 'input> Attribute<'a, 'input> {
     /// ```
     #[inline]
     pub fn name(&self) -> &'input str {
-        self.data.name.local_name
+        self.data.name.local_name(self.doc)
     }
 
-    /// Returns attribute's value.
+    /// Returns attribute's namespace prefix.
+    ///
+    /// Returns `None` for unprefixed attributes.
     ///
     /// # Examples
     ///
     /// ```
     /// let doc = roxmltree::Document::parse(
-    ///     "<e xmlns:n='http://www.w3.org' a='b' n:a='c'/>"
+    ///     "<e xmlns:n='http://www.w3.org' a='b' n:a='c' xml:space='preserve'/>"
     /// ).unwrap();
     ///
-    /// assert_eq!(doc.root_element().attributes().nth(0).unwrap().value(), "b");
-    /// assert_eq!(doc.root_element().attributes().nth(1).unwrap().value(), "c");
+    /// assert_eq!(doc.root_element().attributes().nth(0).unwrap().prefix(), None);
+    /// assert_eq!(doc.root_element().attributes().nth(1).unwrap().prefix(), Some("n"));
+    /// assert_eq!(doc.root_element().attributes().nth(2).unwrap().prefix(), Some("xml"));
     /// ```
     #[inline]
-    pub fn value(&self) -> &'a str {
-        &self.data.value
+    pub fn prefix(&self) -> Option<&'input str> {
+        self.data.name.prefix(self.doc)
     }
 
-    /// Returns attribute's value storage.
+    /// Returns attribute's value.
     ///
-    /// Useful when you need a more low-level access to an allocated string.
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<e xmlns:n='http://www.w3.org' a='b' n:a='c'/>"
+    /// ).unwrap();
+    ///
+    /// assert_eq!(doc.root_element().attributes().nth(0).unwrap().value(), "b");
+    /// assert_eq!(doc.root_element().attributes().nth(1).unwrap().value(), "c");
+    /// ```
+    #[inline]
+    pub fn value(&self) -> &'a str {
+        &self.data.value
+    }
+
+    /// Returns attribute's value storage.
+    ///
+    /// Useful when you need a more low-level access to an allocated string.
     #[inline]
     pub fn value_storage(&self) -> &StringStorage<'input> {
         &self.data.value
@@ -586,9 +1123,16 @@ impl<'a, 'input> Attribute<'a, 'input> {
     /// <e n:attr='value'/>
     ///    ^^^^^^^^^^^^^^
     /// ```
+    ///
+    /// Returns an empty `0..0` range for attributes without an input text,
+    /// like the ones created via [`DocumentBuilder`].
     #[cfg(feature = "positions")]
     #[inline]
     pub fn range(&self) -> Range<usize> {
+        if !self.data.has_source() {
+            return 0..0;
+        }
+
         self.data.range.clone()
     }
 
@@ -599,12 +1143,16 @@ impl<'a, 'input> Attribute<'a, 'input> {
     ///    ^^^^^^
     /// ```
     ///
-    /// To reduce memory usage the qname length is limited by u16::MAX.
-    /// If the attribute exceeds that limit then the end of the returned range will be incorrect.
+    /// Returns an empty `0..0` range for attributes without an input text,
+    /// like the ones created via [`DocumentBuilder`].
     #[cfg(feature = "positions")]
     #[inline]
     pub fn range_qname(&self) -> Range<usize> {
-        let end = self.data.range.start + usize::from(self.data.qname_len);
+        if !self.data.has_source() {
+            return 0..0;
+        }
+
+        let end = self.data.range.start + self.data.qname_len as usize;
         self.data.range.start..end
     }
 
@@ -617,17 +1165,77 @@ impl<'a, 'input> Attribute<'a, 'input> {
     ///            ^^^^^
     /// ```
     ///
-    /// To reduce memory usage the qname length is limited by u16::MAX,
-    /// and the number of spaces around the equal sign is limited by u8::MAX.
-    /// If the attribute exceeds those limits then the start of the returned range will be incorrect.
+    /// Returns an empty `0..0` range for attributes without an input text,
+    /// like the ones created via [`DocumentBuilder`].
+    #[doc(alias = "value_range")]
     #[cfg(feature = "positions")]
     #[inline]
     pub fn range_value(&self) -> Range<usize> {
+        if !self.data.has_source() {
+            return 0..0;
+        }
+
         // +1 on start and -1 on end are to exclude the quotes around the value (all valid quotes are 1 byte)
-        let start = self.data.range.start + usize::from(self.data.qname_len) + usize::from(self.data.eq_len) + 1;
+        let start = self.data.range.start + self.data.qname_len as usize + self.data.eq_len as usize + 1;
         let end = self.data.range.end - 1;
         start..end
     }
+
+    /// Returns the quote character used around attribute's value in the original document.
+    ///
+    /// Either `b'"'` or `b'\''`. Returns `b'"'` for attributes without an input text,
+    /// like the ones created via [`DocumentBuilder`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e a='1' b=\"2\"/>").unwrap();
+    ///
+    /// let mut attrs = doc.root_element().attributes();
+    /// assert_eq!(attrs.next().unwrap().quote_char(), b'\'');
+    /// assert_eq!(attrs.next().unwrap().quote_char(), b'"');
+    /// ```
+    #[cfg(feature = "positions")]
+    #[inline]
+    pub fn quote_char(&self) -> u8 {
+        if !self.data.has_source() {
+            return b'"';
+        }
+
+        // The range always ends with a quote.
+        let last = self.data.range.end.checked_sub(1);
+        match last.and_then(|i| self.doc.text.as_bytes().get(i)) {
+            Some(&c) if c == b'\'' => c,
+            _ => b'"',
+        }
+    }
+
+    /// Returns attribute's value as written in the original document, without the quotes.
+    ///
+    /// Unlike [`Attribute::value`], references are not resolved and whitespace
+    /// is not normalized.
+    ///
+    /// Returns an empty string for attributes without an input text,
+    /// like the ones created via [`DocumentBuilder`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e a='A &amp; &#66;'/>").unwrap();
+    ///
+    /// let attr = doc.root_element().attribute_node("a").unwrap();
+    /// assert_eq!(attr.value(), "A & B");
+    /// assert_eq!(attr.raw_value(), "A &amp; &#66;");
+    /// ```
+    #[cfg(feature = "positions")]
+    #[inline]
+    pub fn raw_value(&self) -> &'input str {
+        if !self.data.has_source() {
+            return "";
+        }
+
+        self.doc.text.get(self.range_value()).unwrap_or("")
+    }
 }
 
 impl PartialEq for Attribute<'_, '_> {
@@ -683,6 +1291,44 @@ impl<'input> Namespace<'input> {
         self.name
     }
 
+    /// Returns namespace prefix.
+    ///
+    /// An alias for [`Namespace::name`], since the namespace name can be confused with its URI.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<e xmlns:n='http://www.w3.org'/>"
+    /// ).unwrap();
+    ///
+    /// assert_eq!(doc.root_element().namespaces().nth(0).unwrap().prefix(), Some("n"));
+    /// ```
+    #[inline]
+    pub fn prefix(&self) -> Option<&'input str> {
+        self.name
+    }
+
+    /// Checks that this is a default namespace, i.e. the one without a prefix.
+    ///
+    /// Note that `xmlns=""` is a default namespace as well, with an empty URI.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<e xmlns='http://www.w3.org' xmlns:n='http://www.w3.org'/>"
+    /// ).unwrap();
+    ///
+    /// let mut iter = doc.root_element().namespaces();
+    /// assert!(iter.next().unwrap().is_default());
+    /// assert!(!iter.next().unwrap().is_default());
+    /// ```
+    #[inline]
+    pub fn is_default(&self) -> bool {
+        self.name.is_none()
+    }
+
     /// Returns namespace URI.
     ///
     /// # Examples
@@ -725,10 +1371,10 @@ impl<'input> Namespaces<'input> {
         }) {
             Ok(sorted_idx) => self.sorted_order[sorted_idx],
             Err(sorted_idx) => {
-                if self.values.len() > core::u16::MAX as usize {
-                    return Err(Error::NamespacesLimitReached);
-                }
-                let idx = NamespaceIdx(self.values.len() as u16);
+                let idx = match u16::try_from(self.values.len()) {
+                    Ok(idx) => NamespaceIdx(idx),
+                    Err(_) => return Err(Error::NamespacesLimitReached),
+                };
                 self.values.push(Namespace { name, uri });
                 self.sorted_order.insert(sorted_idx, idx);
                 idx
@@ -765,27 +1411,144 @@ impl<'input> Namespaces<'input> {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 #[repr(transparent)]
 struct NamespaceIdx(u16);
 
+/// An opaque namespace handle.
+///
+/// Namespaces are deduplicated by prefix and URI, so equal IDs guarantee equal URIs.
+/// But different IDs can still have equal URIs, when declared with different prefixes.
+///
+/// IDs are document-local and must not be compared across documents.
+///
+/// Can be retrieved via [`Node::tag_namespace_id`] and [`Attribute::namespace_id`].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct NamespaceId(u16);
+
+impl From<NamespaceIdx> for NamespaceId {
+    #[inline]
+    fn from(idx: NamespaceIdx) -> Self {
+        NamespaceId(idx.0)
+    }
+}
+
+/// An index into the interned names pool.
+#[cfg(feature = "intern_names")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct NameIdx(u32);
+
+#[cfg(feature = "intern_names")]
+#[derive(Default)]
+struct Names<'input> {
+    // Deduplicated element and attribute local names used throughout the document
+    values: Vec<&'input str>,
+    // Indices into the above by value used for deduplication
+    indices: BTreeMap<&'input str, NameIdx>,
+}
+
+#[cfg(feature = "intern_names")]
+impl<'input> Names<'input> {
+    fn push(&mut self, name: &'input str) -> NameIdx {
+        use alloc::collections::btree_map::Entry;
+
+        match self.indices.entry(name) {
+            Entry::Occupied(e) => *e.get(),
+            Entry::Vacant(e) => {
+                // Cannot overflow, since there are less names than nodes and attributes,
+                // which are already limited by `u32`.
+                let idx = NameIdx(self.values.len() as u32);
+                self.values.push(name);
+                *e.insert(idx)
+            }
+        }
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.values.shrink_to_fit();
+        // Not needed after parsing.
+        self.indices = BTreeMap::new();
+    }
+
+    #[inline]
+    fn get(&self, idx: NameIdx) -> &'input str {
+        self.values[idx.0 as usize]
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 struct ExpandedNameIndexed<'input> {
     namespace_idx: Option<NamespaceIdx>,
+    #[cfg(not(feature = "intern_names"))]
     local_name: &'input str,
+    #[cfg(feature = "intern_names")]
+    local_name: NameIdx,
+    #[cfg(feature = "intern_names")]
+    phantom: core::marker::PhantomData<&'input str>,
+}
+
+impl<'input> Document<'input> {
+    /// Creates a new expanded name, interning the local name when required.
+    #[inline]
+    pub(crate) fn new_name(
+        &mut self,
+        namespace_idx: Option<NamespaceIdx>,
+        local_name: &'input str,
+    ) -> ExpandedNameIndexed<'input> {
+        ExpandedNameIndexed {
+            namespace_idx,
+            #[cfg(not(feature = "intern_names"))]
+            local_name,
+            #[cfg(feature = "intern_names")]
+            local_name: self.names.push(local_name),
+            #[cfg(feature = "intern_names")]
+            phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+impl Document<'_> {
+    /// Returns the position of a slice in the original text, if it's a part of it.
+    fn text_offset(&self, s: &str) -> Option<usize> {
+        let offset = (s.as_ptr() as usize).checked_sub(self.text.as_ptr() as usize)?;
+        if offset < self.text.len() {
+            Some(offset)
+        } else {
+            None
+        }
+    }
 }
 
 impl<'input> ExpandedNameIndexed<'input> {
+    #[inline]
+    fn local_name(&self, doc: &Document<'input>) -> &'input str {
+        #[cfg(not(feature = "intern_names"))]
+        {
+            let _ = doc;
+            self.local_name
+        }
+
+        #[cfg(feature = "intern_names")]
+        {
+            doc.names.get(self.local_name)
+        }
+    }
+
     #[inline]
     fn namespace<'a>(&self, doc: &'a Document<'input>) -> Option<&'a Namespace<'input>> {
         self.namespace_idx.map(|idx| doc.namespaces.get(idx))
     }
 
+    #[inline]
+    fn prefix(&self, doc: &Document<'input>) -> Option<&'input str> {
+        self.namespace(doc).and_then(|ns| ns.name)
+    }
+
     #[inline]
     fn as_expanded_name<'a>(&self, doc: &'a Document<'input>) -> ExpandedName<'a, 'input> {
         ExpandedName {
             uri: self.namespace(doc).map(Namespace::uri),
-            name: self.local_name,
+            name: self.local_name(doc),
         }
     }
 }
@@ -793,7 +1556,7 @@ impl<'input> ExpandedNameIndexed<'input> {
 /// An expanded name.
 ///
 /// Contains an namespace URI and name pair.
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ExpandedName<'a, 'b> {
     uri: Option<&'a str>,
     name: &'b str,
@@ -827,6 +1590,70 @@ impl<'a, 'b> ExpandedName<'a, 'b> {
     pub fn name(&self) -> &'b str {
         self.name
     }
+
+    /// Returns the name in the [Clark notation](http://www.jclark.com/xml/xmlns.htm).
+    ///
+    /// The same as the `Display` output: `{uri}name` when a namespace is set
+    /// and just `name` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<n:e xmlns:n='http://www.w3.org'><b/></n:e>").unwrap();
+    ///
+    /// assert_eq!(doc.root_element().tag_name().to_clark_notation(), "{http://www.w3.org}e");
+    /// assert_eq!(doc.root_element().first_child().unwrap().tag_name().to_clark_notation(), "b");
+    /// ```
+    pub fn to_clark_notation(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ExpandedName<'_, '_> {
+    /// Checks that names are equal, ignoring the ASCII case of local names.
+    ///
+    /// Namespaces are compared exactly.
+    #[inline]
+    fn eq_ignore_ascii_case(&self, other: &ExpandedName) -> bool {
+        self.uri == other.uri && self.name.eq_ignore_ascii_case(other.name)
+    }
+}
+
+impl<'a> ExpandedName<'a, 'a> {
+    /// Parses a name in the [Clark notation](http://www.jclark.com/xml/xmlns.htm).
+    ///
+    /// Returns `None` when the closing brace is missing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::ExpandedName;
+    ///
+    /// let name = ExpandedName::from_clark("{http://www.w3.org}e").unwrap();
+    /// assert_eq!(name.namespace(), Some("http://www.w3.org"));
+    /// assert_eq!(name.name(), "e");
+    ///
+    /// assert_eq!(ExpandedName::from_clark("e").unwrap().namespace(), None);
+    /// assert!(ExpandedName::from_clark("{http://www.w3.org e").is_none());
+    ///
+    /// let doc = roxmltree::Document::parse("<e xmlns='http://www.w3.org'/>").unwrap();
+    /// assert!(doc.root_element().has_tag_name(ExpandedName::from_clark("{http://www.w3.org}e").unwrap()));
+    /// ```
+    pub fn from_clark(text: &'a str) -> Option<Self> {
+        match text.strip_prefix('{') {
+            Some(rest) => {
+                let (uri, name) = rest.split_once('}')?;
+                Some(ExpandedName {
+                    uri: Some(uri),
+                    name,
+                })
+            }
+            None => Some(ExpandedName {
+                uri: None,
+                name: text,
+            }),
+        }
+    }
 }
 
 impl ExpandedName<'static, 'static> {
@@ -856,6 +1683,15 @@ impl fmt::Debug for ExpandedName<'_, '_> {
     }
 }
 
+impl fmt::Display for ExpandedName<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.namespace() {
+            Some(ns) => write!(f, "{{{}}}{}", ns, self.name),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
 impl<'a, 'b> From<&'b str> for ExpandedName<'a, 'b> {
     #[inline]
     fn from(v: &'b str) -> Self {
@@ -941,13 +1777,7 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
     /// Returns node's type.
     #[inline]
     pub fn node_type(&self) -> NodeType {
-        match self.d.kind {
-            NodeKind::Root => NodeType::Root,
-            NodeKind::Element { .. } => NodeType::Element,
-            NodeKind::PI { .. } => NodeType::PI,
-            NodeKind::Comment(_) => NodeType::Comment,
-            NodeKind::Text(_) => NodeType::Text,
-        }
+        self.d.kind.node_type()
     }
 
     /// Checks that node is a root node.
@@ -980,6 +1810,70 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
         self.node_type() == NodeType::Text
     }
 
+    /// Checks that node is a text node that contains only XML whitespace characters.
+    ///
+    /// Always returns `false` for non-text nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e>\n    <a>text</a>\n</e>").unwrap();
+    ///
+    /// let mut iter = doc.root_element().children();
+    /// assert!(iter.next().unwrap().is_whitespace());
+    /// let a = iter.next().unwrap();
+    /// assert!(!a.is_whitespace());
+    /// assert!(!a.first_child().unwrap().is_whitespace());
+    /// ```
+    pub fn is_whitespace(&self) -> bool {
+        match self.d.kind {
+            NodeKind::Text(ref text, _) => text.as_str().bytes().all(|c| c.is_xml_space()),
+            _ => false,
+        }
+    }
+
+    /// Checks that node is an ignorable whitespace text node.
+    ///
+    /// Whitespace is ignorable when the parent element is declared
+    /// with element content in the DTD, like `<!ELEMENT e (a, b)>`.
+    /// Requires [`ParsingOptions::detect_ignorable_whitespace`].
+    ///
+    /// Since CDATA sections are merged with text, whitespace-only CDATA
+    /// is treated as ignorable as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opt = roxmltree::ParsingOptions {
+    ///     allow_dtd: true,
+    ///     detect_ignorable_whitespace: true,
+    ///     ..roxmltree::ParsingOptions::default()
+    /// };
+    /// let doc = roxmltree::Document::parse_with_options("\
+    /// <!DOCTYPE e [
+    ///     <!ELEMENT e (a)>
+    ///     <!ELEMENT a (#PCDATA)>
+    /// ]>
+    /// <e> <a> </a> </e>", opt).unwrap();
+    ///
+    /// let e = doc.root_element();
+    /// assert!(e.first_child().unwrap().is_ignorable_whitespace());
+    /// assert!(!e.first_element_child().unwrap().first_child().unwrap().is_ignorable_whitespace());
+    /// ```
+    pub fn is_ignorable_whitespace(&self) -> bool {
+        if self.doc.element_content.is_empty() || !self.is_whitespace() {
+            return false;
+        }
+
+        match self.parent_element() {
+            Some(parent) => {
+                let name = (parent.prefix().unwrap_or(""), parent.tag_name().name());
+                self.doc.element_content.contains(&name)
+            }
+            None => false,
+        }
+    }
+
     /// Returns node's document.
     #[inline]
     pub fn document(&self) -> &'a Document<'input> {
@@ -1006,19 +1900,78 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
         }
     }
 
+    /// Returns element's namespace prefix.
+    ///
+    /// Returns `None` for unprefixed elements, including the one in the default namespace,
+    /// and for non-element nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<svg xmlns='http://www.w3.org/2000/svg' xmlns:svg='http://www.w3.org/2000/svg'>\
+    ///         <svg:rect/>\
+    ///     </svg>"
+    /// ).unwrap();
+    ///
+    /// assert_eq!(doc.root_element().prefix(), None);
+    /// assert_eq!(doc.root_element().first_child().unwrap().prefix(), Some("svg"));
+    /// ```
+    #[inline]
+    pub fn prefix(&self) -> Option<&'input str> {
+        match self.d.kind {
+            NodeKind::Element { ref tag_name, .. } => tag_name.prefix(self.doc),
+            _ => None,
+        }
+    }
+
+    /// Returns element's namespace handle.
+    ///
+    /// Cheaper to compare and hash than the namespace URI.
+    /// See [`NamespaceId`] for details.
+    ///
+    /// Returns `None` for elements without a namespace, unlike `xmlns=''`,
+    /// which is a namespace with an empty URI, and for non-element nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<n:e xmlns:n='http://www.w3.org'><n:a/><b/></n:e>"
+    /// ).unwrap();
+    ///
+    /// let e = doc.root_element();
+    /// let mut iter = e.children();
+    /// assert!(e.tag_namespace_id().is_some());
+    /// assert_eq!(iter.next().unwrap().tag_namespace_id(), e.tag_namespace_id());
+    /// assert_eq!(iter.next().unwrap().tag_namespace_id(), None);
+    /// ```
+    #[inline]
+    pub fn tag_namespace_id(&self) -> Option<NamespaceId> {
+        match self.d.kind {
+            NodeKind::Element { ref tag_name, .. } => tag_name.namespace_idx.map(NamespaceId::from),
+            _ => None,
+        }
+    }
+
     /// Checks that node has a specified tag name.
     ///
+    /// Always returns `false` for non-element nodes, so it can be used in filters directly,
+    /// like `node.descendants().filter(|n| n.has_tag_name("rect"))`.
+    ///
     /// # Examples
     ///
     /// ```
-    /// let doc = roxmltree::Document::parse("<e xmlns='http://www.w3.org'/>").unwrap();
+    /// let doc = roxmltree::Document::parse("<e xmlns='http://www.w3.org'>e</e>").unwrap();
     ///
     /// assert!(doc.root_element().has_tag_name("e"));
     /// assert!(doc.root_element().has_tag_name(("http://www.w3.org", "e")));
     ///
     /// assert!(!doc.root_element().has_tag_name("b"));
     /// assert!(!doc.root_element().has_tag_name(("http://www.w4.org", "e")));
+    /// assert!(!doc.root_element().first_child().unwrap().has_tag_name("e"));
     /// ```
+    #[doc(alias = "matches")]
     pub fn has_tag_name<'n, 'm, N>(&self, name: N) -> bool
     where
         N: Into<ExpandedName<'n, 'm>>,
@@ -1028,41 +1981,150 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
         match self.d.kind {
             NodeKind::Element { ref tag_name, .. } => match name.namespace() {
                 Some(_) => tag_name.as_expanded_name(self.doc) == name,
-                None => tag_name.local_name == name.name,
+                None => tag_name.local_name(self.doc) == name.name,
             },
             _ => false,
         }
     }
 
-    /// Returns node's default namespace URI.
+    /// Checks that node has one of the specified local tag names.
     ///
-    /// # Examples
-    ///
-    /// ```
-    /// let doc = roxmltree::Document::parse("<e xmlns='http://www.w3.org'/>").unwrap();
+    /// Namespaces are ignored.
     ///
-    /// assert_eq!(doc.root_element().default_namespace(), Some("http://www.w3.org"));
-    /// ```
+    /// # Examples
     ///
     /// ```
-    /// let doc = roxmltree::Document::parse("<e xmlns:n='http://www.w3.org'/>").unwrap();
+    /// let doc = roxmltree::Document::parse("<rect xmlns='http://www.w3.org'/>").unwrap();
     ///
-    /// assert_eq!(doc.root_element().default_namespace(), None);
+    /// assert!(doc.root_element().tag_name_is_one_of(&["circle", "rect"]));
+    /// assert!(!doc.root_element().tag_name_is_one_of(&["circle", "path"]));
     /// ```
-    pub fn default_namespace(&self) -> Option<&'a str> {
-        self.namespaces()
-            .find(|ns| ns.name.is_none())
-            .map(|v| v.uri.as_ref())
+    pub fn tag_name_is_one_of(&self, names: &[&str]) -> bool {
+        match self.d.kind {
+            NodeKind::Element { ref tag_name, .. } => {
+                names.contains(&tag_name.local_name(self.doc))
+            }
+            _ => false,
+        }
     }
 
-    /// Returns a prefix for a given namespace URI.
+    /// Checks that node has a specified local tag name, ignoring the namespace.
     ///
-    /// # Examples
+    /// Same as [`Node::has_tag_name`] with a bare `&str`, but states the intent explicitly.
     ///
-    /// ```
-    /// let doc = roxmltree::Document::parse("<e xmlns:n='http://www.w3.org'/>").unwrap();
+    /// Always returns `false` for non-element nodes.
+    ///
+    /// # Examples
     ///
-    /// assert_eq!(doc.root_element().lookup_prefix("http://www.w3.org"), Some("n"));
+    /// ```
+    /// let doc = roxmltree::Document::parse("<n:e xmlns:n='http://www.w3.org'/>").unwrap();
+    ///
+    /// assert!(doc.root_element().has_local_name("e"));
+    /// assert!(!doc.root_element().has_local_name("n:e"));
+    /// ```
+    #[inline]
+    pub fn has_local_name(&self, local: &str) -> bool {
+        match self.d.kind {
+            NodeKind::Element { ref tag_name, .. } => tag_name.local_name(self.doc) == local,
+            _ => false,
+        }
+    }
+
+    /// Checks that node has one of the specified tag names.
+    ///
+    /// Each name is matched the same way as in [`Node::has_tag_name`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::ExpandedName;
+    ///
+    /// let doc = roxmltree::Document::parse("<rect xmlns='http://www.w3.org'/>").unwrap();
+    ///
+    /// let names = [
+    ///     ExpandedName::from(("http://www.w3.org", "circle")),
+    ///     ExpandedName::from(("http://www.w3.org", "rect")),
+    /// ];
+    /// assert!(doc.root_element().has_tag_name_in(&names));
+    /// assert!(doc.root_element().has_tag_name_in(&[ExpandedName::from("rect")]));
+    /// assert!(!doc.root_element().has_tag_name_in(&[("http://www.w4.org", "rect").into()]));
+    /// ```
+    pub fn has_tag_name_in(&self, names: &[ExpandedName]) -> bool {
+        let tag_name = match self.d.kind {
+            NodeKind::Element { ref tag_name, .. } => tag_name,
+            _ => return false,
+        };
+
+        names.iter().any(|name| {
+            name.name == tag_name.local_name(self.doc)
+                && match name.uri {
+                    Some(uri) => tag_name.namespace(self.doc).map(Namespace::uri) == Some(uri),
+                    None => true,
+                }
+        })
+    }
+
+    /// Checks that node has a specified tag name, ignoring the ASCII case of the local name.
+    ///
+    /// Namespaces are still compared exactly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<Rect xmlns='http://www.w3.org'/>").unwrap();
+    ///
+    /// assert!(doc.root_element().has_tag_name_ignore_ascii_case("rect"));
+    /// assert!(doc.root_element().has_tag_name_ignore_ascii_case(("http://www.w3.org", "RECT")));
+    ///
+    /// assert!(!doc.root_element().has_tag_name_ignore_ascii_case(("http://www.W3.org", "rect")));
+    /// ```
+    pub fn has_tag_name_ignore_ascii_case<'n, 'm, N>(&self, name: N) -> bool
+    where
+        N: Into<ExpandedName<'n, 'm>>,
+    {
+        let name = name.into();
+
+        match self.d.kind {
+            NodeKind::Element { ref tag_name, .. } => {
+                let tag_name = tag_name.as_expanded_name(self.doc);
+                match name.namespace() {
+                    Some(_) => tag_name.eq_ignore_ascii_case(&name),
+                    None => tag_name.name.eq_ignore_ascii_case(name.name),
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns node's default namespace URI.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e xmlns='http://www.w3.org'/>").unwrap();
+    ///
+    /// assert_eq!(doc.root_element().default_namespace(), Some("http://www.w3.org"));
+    /// ```
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e xmlns:n='http://www.w3.org'/>").unwrap();
+    ///
+    /// assert_eq!(doc.root_element().default_namespace(), None);
+    /// ```
+    pub fn default_namespace(&self) -> Option<&'a str> {
+        self.namespaces()
+            .find(|ns| ns.name.is_none())
+            .map(|v| v.uri.as_ref())
+    }
+
+    /// Returns a prefix for a given namespace URI.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e xmlns:n='http://www.w3.org'/>").unwrap();
+    ///
+    /// assert_eq!(doc.root_element().lookup_prefix("http://www.w3.org"), Some("n"));
     /// ```
     ///
     /// ```
@@ -1130,6 +2192,61 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
             .map(|a| a.value())
     }
 
+    /// Parses element's attribute value using [`FromStr`].
+    ///
+    /// Returns `None` when the attribute is not set
+    /// and `Some(Err)` when it cannot be parsed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e x='10' y='ten'/>").unwrap();
+    ///
+    /// let e = doc.root_element();
+    /// assert_eq!(e.parse_attribute::<u32, _>("x"), Some(Ok(10)));
+    /// assert!(e.parse_attribute::<u32, _>("y").unwrap().is_err());
+    /// assert_eq!(e.parse_attribute::<u32, _>("z"), None);
+    /// ```
+    ///
+    /// [`FromStr`]: core::str::FromStr
+    pub fn parse_attribute<'n, 'm, T, N>(&self, name: N) -> Option<Result<T, T::Err>>
+    where
+        T: core::str::FromStr,
+        N: Into<ExpandedName<'n, 'm>>,
+    {
+        self.attribute(name).map(str::parse)
+    }
+
+    /// Returns element's attribute value, ignoring the ASCII case of the local name.
+    ///
+    /// Namespaces are still compared exactly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<e xmlns:n='http://www.w3.org' Width='b' n:Height='c'/>"
+    /// ).unwrap();
+    ///
+    /// assert_eq!(doc.root_element().attribute_ignore_ascii_case("width"), Some("b"));
+    /// assert_eq!(doc.root_element().attribute_ignore_ascii_case(("http://www.w3.org", "HEIGHT")), Some("c"));
+    /// assert_eq!(doc.root_element().attribute_ignore_ascii_case("height"), None);
+    /// ```
+    pub fn attribute_ignore_ascii_case<'n, 'm, N>(&self, name: N) -> Option<&'a str>
+    where
+        N: Into<ExpandedName<'n, 'm>>,
+    {
+        let name = name.into();
+        self.attributes()
+            .find(|a| {
+                a.data
+                    .name
+                    .as_expanded_name(self.doc)
+                    .eq_ignore_ascii_case(&name)
+            })
+            .map(|a| a.value())
+    }
+
     /// Returns element's attribute object.
     ///
     /// The same as [`attribute()`], but returns the `Attribute` itself instead of a value string.
@@ -1144,6 +2261,90 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
             .find(|a| a.data.name.as_expanded_name(self.doc) == name)
     }
 
+    /// Returns element's attribute value by a namespace prefix and a local name.
+    ///
+    /// The prefix is resolved using namespaces in scope of this node.
+    /// An empty prefix matches attributes without a namespace,
+    /// since the default namespace doesn't apply to attributes.
+    ///
+    /// Returns `None` when the prefix is not declared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<e xmlns:n='http://www.w3.org' a='b' n:a='c' xml:space='preserve'/>"
+    /// ).unwrap();
+    ///
+    /// assert_eq!(doc.root_element().attribute_by_prefix("n", "a"), Some("c"));
+    /// assert_eq!(doc.root_element().attribute_by_prefix("", "a"), Some("b"));
+    /// assert_eq!(doc.root_element().attribute_by_prefix("xml", "space"), Some("preserve"));
+    /// assert_eq!(doc.root_element().attribute_by_prefix("m", "a"), None);
+    /// ```
+    pub fn attribute_by_prefix(&self, prefix: &str, local: &str) -> Option<&'a str> {
+        if prefix.is_empty() {
+            return self.attribute(local);
+        }
+
+        let uri = if prefix == NS_XML_PREFIX {
+            NS_XML_URI
+        } else {
+            self.lookup_namespace_uri(Some(prefix))?
+        };
+
+        self.attribute((uri, local))
+    }
+
+    /// Returns the whitespace handling mode of this node.
+    ///
+    /// Uses the `xml:space` attribute of the nearest ancestor, including this node.
+    /// Attributes with invalid values are ignored.
+    ///
+    /// Returns `XmlSpace::Default` when there are no such attributes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::XmlSpace;
+    ///
+    /// let doc = roxmltree::Document::parse(
+    ///     "<e><pre xml:space='preserve'><b> text </b></pre><p/></e>"
+    /// ).unwrap();
+    ///
+    /// let b = doc.descendants().find(|n| n.has_tag_name("b")).unwrap();
+    /// assert_eq!(b.xml_space(), XmlSpace::Preserve);
+    /// assert_eq!(b.first_child().unwrap().xml_space(), XmlSpace::Preserve);
+    ///
+    /// let p = doc.descendants().find(|n| n.has_tag_name("p")).unwrap();
+    /// assert_eq!(p.xml_space(), XmlSpace::Default);
+    /// ```
+    pub fn xml_space(&self) -> XmlSpace {
+        self.ancestors()
+            .find_map(|n| match n.attribute((NS_XML_URI, "space")) {
+                Some("default") => Some(XmlSpace::Default),
+                Some("preserve") => Some(XmlSpace::Preserve),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns element's attribute at the specified index.
+    ///
+    /// Attributes are stored in the document order, so this is an O(1) operation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e a='1' b='2'/>").unwrap();
+    ///
+    /// assert_eq!(doc.root_element().attribute_at(1).unwrap().name(), "b");
+    /// assert!(doc.root_element().attribute_at(2).is_none());
+    /// ```
+    #[inline]
+    pub fn attribute_at(&self, index: usize) -> Option<Attribute<'a, 'input>> {
+        self.attributes().get(index)
+    }
+
     /// Checks that element has a specified attribute.
     ///
     /// # Examples
@@ -1170,6 +2371,9 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
 
     /// Returns element's attributes.
     ///
+    /// Attributes are always returned in the same order as in the original document.
+    /// Namespace declarations, like `xmlns:n`, are not attributes and are not included.
+    ///
     /// # Examples
     ///
     /// ```
@@ -1186,6 +2390,12 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
 
     /// Returns element's namespaces.
     ///
+    /// Includes all the in-scope namespaces and not only the one declared on this element.
+    /// A prefix redeclared on a descendant shadows the ancestor's one,
+    /// so each prefix is present only once.
+    ///
+    /// The implicit `xml` namespace is not included.
+    ///
     /// # Examples
     ///
     /// ```
@@ -1210,11 +2420,85 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
         }
     }
 
+    /// Returns the number of element's attributes.
+    ///
+    /// Same as `attributes().len()`, but doesn't create an iterator.
+    /// Returns 0 for non-element nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e a='b' c='d'/>").unwrap();
+    ///
+    /// assert_eq!(doc.root_element().attributes_count(), 2);
+    /// ```
+    #[inline]
+    pub fn attributes_count(&self) -> usize {
+        match self.d.kind {
+            NodeKind::Element { ref attributes, .. } => attributes.len(),
+            _ => 0,
+        }
+    }
+
+    /// Returns the number of element's namespaces.
+    ///
+    /// Same as `namespaces().len()`, but doesn't create an iterator.
+    /// Returns 0 for non-element nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<e xmlns:n='http://www.w3.org'/>"
+    /// ).unwrap();
+    ///
+    /// assert_eq!(doc.root_element().namespaces_count(), 1);
+    /// ```
+    #[inline]
+    pub fn namespaces_count(&self) -> usize {
+        match self.d.kind {
+            NodeKind::Element { ref namespaces, .. } => namespaces.len(),
+            _ => 0,
+        }
+    }
+
+    /// Returns element's in-scope namespaces as a prefix to URI map.
+    ///
+    /// The default namespace has a `None` prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<e xmlns='http://www.w3.org' xmlns:n='http://www.w4.org'>\
+    ///         <n:b xmlns:n='http://www.w5.org'/>\
+    ///     </e>"
+    /// ).unwrap();
+    ///
+    /// let node = doc.root_element().first_child().unwrap();
+    /// let map = node.namespace_map();
+    /// assert_eq!(map.len(), 2);
+    /// assert_eq!(map[&None], "http://www.w3.org");
+    /// assert_eq!(map[&Some("n")], "http://www.w5.org");
+    /// ```
+    pub fn namespace_map(&self) -> BTreeMap<Option<&'input str>, &'a str> {
+        self.namespaces().map(|ns| (ns.name(), ns.uri())).collect()
+    }
+
     /// Returns node's text.
     ///
-    /// - for an element will return a first text child
+    /// - for an element will return a first child text, but only when the first child
+    ///   is a text node, i.e. the leading text
     /// - for a comment will return a self text
     /// - for a text node will return a self text
+    /// - for the root node and processing instructions will return `None`
+    ///
+    /// Therefore, `<e><b/>text</e>` has no text, even though it has text content.
+    /// Use [`Node::direct_text_storage`] to get the text of all text children
+    /// and [`Node::inner_text_storage`] to get the text of all descendant text nodes.
+    ///
+    /// Note that comments are also handled. Use [`Node::comment_text`]
+    /// or check [`Node::is_text`] first when only text content is needed.
     ///
     /// # Examples
     ///
@@ -1248,17 +2532,110 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
         match self.d.kind {
             NodeKind::Element { .. } => match self.first_child() {
                 Some(child) if child.is_text() => match self.doc.nodes[child.id.get_usize()].kind {
-                    NodeKind::Text(ref text) => Some(text),
+                    NodeKind::Text(ref text, _) => Some(text),
                     _ => None,
                 },
                 _ => None,
             },
             NodeKind::Comment(ref text) => Some(text),
-            NodeKind::Text(ref text) => Some(text),
+            NodeKind::Text(ref text, _) => Some(text),
             _ => None,
         }
     }
 
+    /// Returns the text of all element's text children.
+    ///
+    /// Unlike [`Node::text_storage`], not only the leading text is included.
+    /// And unlike [`Node::inner_text_storage`], descendants' text is not included.
+    ///
+    /// When there is only one text child, its storage will be returned as is,
+    /// which means that a borrowed string will stay borrowed.
+    /// Otherwise, a new string will be allocated.
+    ///
+    /// Returns an empty string for elements without text children
+    /// and `None` for non-element nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<p><b>1</b>text<b>2</b> more</p>").unwrap();
+    ///
+    /// let p = doc.root_element();
+    /// assert_eq!(p.text(), None);
+    /// assert_eq!(p.direct_text_storage().unwrap().as_str(), "text more");
+    /// assert_eq!(p.inner_text_storage().unwrap().as_str(), "1text2 more");
+    /// ```
+    pub fn direct_text_storage(&self) -> Option<StringStorage<'input>> {
+        if !self.is_element() {
+            return None;
+        }
+
+        Some(concat_text_nodes(self.children()))
+    }
+
+    /// Returns node's whole text content storage.
+    ///
+    /// Unlike [`Node::text_storage`], includes the text of all descendant text nodes.
+    ///
+    /// When there is only one text node, its storage will be returned as is,
+    /// which means that a borrowed string will stay borrowed.
+    /// Otherwise, a new string will be allocated.
+    ///
+    /// Returns an empty string for elements without text and `None` for processing instructions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::StringStorage;
+    ///
+    /// let doc = roxmltree::Document::parse("<p>text<b>1</b>2</p>").unwrap();
+    ///
+    /// let p = doc.root_element();
+    /// assert_eq!(p.inner_text_storage().unwrap().as_str(), "text12");
+    ///
+    /// let b = p.first_element_child().unwrap();
+    /// assert!(matches!(b.inner_text_storage(), Some(StringStorage::Borrowed("1"))));
+    /// ```
+    pub fn inner_text_storage(&self) -> Option<StringStorage<'input>> {
+        match self.d.kind {
+            NodeKind::Root | NodeKind::Element { .. } => {
+                Some(concat_text_nodes(self.descendants()))
+            }
+            NodeKind::Comment(ref text) | NodeKind::Text(ref text, _) => Some(text.clone()),
+            NodeKind::PI(_) => None,
+        }
+    }
+
+    /// Appends the text of all descendant text nodes to a specified writer.
+    ///
+    /// Allows reusing a buffer when extracting text from multiple nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<p>text<b>1</b><b>2</b></p>").unwrap();
+    ///
+    /// let mut buf = String::new();
+    /// for (node, text) in doc.root_element().children().zip(["text", "1", "2"]) {
+    ///     buf.clear();
+    ///     node.write_inner_text(&mut buf).unwrap();
+    ///     assert_eq!(buf, text);
+    /// }
+    ///
+    /// buf.clear();
+    /// doc.root_element().write_inner_text(&mut buf).unwrap();
+    /// assert_eq!(buf, "text12");
+    /// ```
+    pub fn write_inner_text<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        for node in self.descendants() {
+            if let NodeKind::Text(ref text, _) = node.d.kind {
+                w.write_str(text.as_str())?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Returns element's tail text.
     ///
     /// # Examples
@@ -1290,13 +2667,34 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
 
         match self.next_sibling().map(|n| n.id) {
             Some(id) => match self.doc.nodes[id.get_usize()].kind {
-                NodeKind::Text(ref text) => Some(text),
+                NodeKind::Text(ref text, _) => Some(text),
                 _ => None,
             },
             None => None,
         }
     }
 
+    /// Returns comment's text.
+    ///
+    /// Unlike [`Node::text`], returns `None` for all nodes except comments.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<!-- comment --><e>text</e>").unwrap();
+    ///
+    /// assert_eq!(doc.root().first_child().unwrap().comment_text(), Some(" comment "));
+    /// assert_eq!(doc.root_element().comment_text(), None);
+    /// assert_eq!(doc.root_element().first_child().unwrap().comment_text(), None);
+    /// ```
+    #[inline]
+    pub fn comment_text(&self) -> Option<&'a str> {
+        match self.d.kind {
+            NodeKind::Comment(ref text) => Some(text.as_str()),
+            _ => None,
+        }
+    }
+
     /// Returns node as Processing Instruction.
     #[inline]
     pub fn pi(&self) -> Option<PI<'input>> {
@@ -1314,7 +2712,7 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
 
     /// Returns the parent element of this node.
     pub fn parent_element(&self) -> Option<Self> {
-        self.ancestors().skip(1).find(|n| n.is_element())
+        self.proper_ancestors().find(|n| n.is_element())
     }
 
     /// Returns the previous sibling of this node.
@@ -1328,13 +2726,49 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
         self.prev_siblings().skip(1).find(|n| n.is_element())
     }
 
-    /// Returns the next sibling of this node.
-    #[inline]
-    pub fn next_sibling(&self) -> Option<Self> {
-        self.d
-            .next_subtree
-            .map(|id| self.doc.get_node(id).unwrap())
-            .and_then(|node| {
+    /// Returns the previous sibling of this node with the specified type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::NodeType;
+    ///
+    /// let doc = roxmltree::Document::parse("<e><!--c-->text<a/><b/></e>").unwrap();
+    ///
+    /// let b = doc.root_element().last_child().unwrap();
+    /// let comment = b.prev_sibling_of_type(NodeType::Comment).unwrap();
+    /// assert_eq!(comment.text(), Some("c"));
+    /// assert!(b.prev_sibling_of_type(NodeType::PI).is_none());
+    /// ```
+    pub fn prev_sibling_of_type(&self, kind: NodeType) -> Option<Self> {
+        self.prev_siblings().skip(1).find(|n| n.node_type() == kind)
+    }
+
+    /// Returns the previous sibling text node of this node.
+    #[inline]
+    pub fn prev_sibling_text(&self) -> Option<Self> {
+        self.prev_sibling_of_type(NodeType::Text)
+    }
+
+    /// Returns the previous sibling comment of this node.
+    #[inline]
+    pub fn prev_sibling_comment(&self) -> Option<Self> {
+        self.prev_sibling_of_type(NodeType::Comment)
+    }
+
+    /// Returns the previous sibling processing instruction of this node.
+    #[inline]
+    pub fn prev_sibling_pi(&self) -> Option<Self> {
+        self.prev_sibling_of_type(NodeType::PI)
+    }
+
+    /// Returns the next sibling of this node.
+    #[inline]
+    pub fn next_sibling(&self) -> Option<Self> {
+        self.d
+            .next_subtree
+            .map(|id| self.doc.get_node(id).unwrap())
+            .and_then(|node| {
                 let possibly_self = node
                     .d
                     .prev_sibling
@@ -1352,6 +2786,138 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
         self.next_siblings().skip(1).find(|n| n.is_element())
     }
 
+    /// Returns the next sibling of this node with the specified type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::NodeType;
+    ///
+    /// let doc = roxmltree::Document::parse("<e><a/><b/>text<?pi?></e>").unwrap();
+    ///
+    /// let a = doc.root_element().first_child().unwrap();
+    /// let text = a.next_sibling_of_type(NodeType::Text).unwrap();
+    /// assert_eq!(text.text(), Some("text"));
+    /// assert!(a.next_sibling_of_type(NodeType::Comment).is_none());
+    /// ```
+    pub fn next_sibling_of_type(&self, kind: NodeType) -> Option<Self> {
+        self.next_siblings().skip(1).find(|n| n.node_type() == kind)
+    }
+
+    /// Returns the next sibling text node of this node.
+    #[inline]
+    pub fn next_sibling_text(&self) -> Option<Self> {
+        self.next_sibling_of_type(NodeType::Text)
+    }
+
+    /// Returns the next sibling comment of this node.
+    #[inline]
+    pub fn next_sibling_comment(&self) -> Option<Self> {
+        self.next_sibling_of_type(NodeType::Comment)
+    }
+
+    /// Returns the next sibling processing instruction of this node.
+    #[inline]
+    pub fn next_sibling_pi(&self) -> Option<Self> {
+        self.next_sibling_of_type(NodeType::PI)
+    }
+
+    /// Returns the position of this node among all its siblings.
+    ///
+    /// The first child has an index of 0. The root node has an index of 0 as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e><a/>text<b/></e>").unwrap();
+    ///
+    /// let b = doc.root_element().last_child().unwrap();
+    /// assert_eq!(b.sibling_index(), 2);
+    /// ```
+    pub fn sibling_index(&self) -> usize {
+        self.prev_siblings().skip(1).count()
+    }
+
+    /// Returns the position of this element among its sibling elements.
+    ///
+    /// Returns `None` when this node is not an element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e><a/>text<b/></e>").unwrap();
+    ///
+    /// let b = doc.root_element().last_child().unwrap();
+    /// assert_eq!(b.element_sibling_index(), Some(1));
+    /// ```
+    pub fn element_sibling_index(&self) -> Option<usize> {
+        if !self.is_element() {
+            return None;
+        }
+
+        let count = self
+            .prev_siblings()
+            .skip(1)
+            .filter(|n| n.is_element())
+            .count();
+        Some(count)
+    }
+
+    /// Returns the kind of node's content.
+    ///
+    /// Walks the children only once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::ContentKind;
+    ///
+    /// let doc = roxmltree::Document::parse("\
+    /// <e>
+    ///     <a/>
+    ///     <b>text</b>
+    ///     <c>text<d/></c>
+    ///     <f><!-- comment --></f>
+    /// </e>
+    /// ").unwrap();
+    ///
+    /// let mut iter = doc.root_element().children().filter(|n| n.is_element());
+    /// assert_eq!(doc.root_element().content_kind(), ContentKind::ElementOnly);
+    /// assert_eq!(iter.next().unwrap().content_kind(), ContentKind::Empty);
+    /// assert_eq!(iter.next().unwrap().content_kind(), ContentKind::TextOnly);
+    /// assert_eq!(iter.next().unwrap().content_kind(), ContentKind::Mixed);
+    /// assert_eq!(iter.next().unwrap().content_kind(), ContentKind::Empty);
+    /// ```
+    pub fn content_kind(&self) -> ContentKind {
+        let mut has_elements = false;
+        let mut has_text = false;
+        let mut has_significant_text = false;
+        for child in self.children() {
+            match child.d.kind {
+                NodeKind::Element { .. } => has_elements = true,
+                NodeKind::Text(..) => {
+                    has_text = true;
+                    if !child.is_whitespace() {
+                        has_significant_text = true;
+                    }
+                }
+                _ => {}
+            }
+
+            if has_elements && has_significant_text {
+                return ContentKind::Mixed;
+            }
+        }
+
+        if has_elements {
+            ContentKind::ElementOnly
+        } else if has_text {
+            ContentKind::TextOnly
+        } else {
+            ContentKind::Empty
+        }
+    }
+
     /// Returns the first child of this node.
     #[inline]
     pub fn first_child(&self) -> Option<Self> {
@@ -1365,6 +2931,73 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
         self.children().find(|n| n.is_element())
     }
 
+    /// Returns the child of this node at the specified index.
+    ///
+    /// Children are not stored contiguously, because of their own descendants,
+    /// so this is an O(index) operation. But each step is O(1),
+    /// since it jumps over the whole subtree of the previous child.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e><a/>text<b><c/></b></e>").unwrap();
+    ///
+    /// assert!(doc.root_element().child_at(1).unwrap().is_text());
+    /// assert!(doc.root_element().child_at(2).unwrap().has_tag_name("b"));
+    /// assert!(doc.root_element().child_at(3).is_none());
+    /// ```
+    #[inline]
+    pub fn child_at(&self, index: usize) -> Option<Self> {
+        self.children().nth(index)
+    }
+
+    /// Returns the child element of this node at the specified index.
+    ///
+    /// Only element nodes are counted. Has the same complexity as [`Node::child_at`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e><a/>text<b><c/></b></e>").unwrap();
+    ///
+    /// assert!(doc.root_element().child_element_at(1).unwrap().has_tag_name("b"));
+    /// assert!(doc.root_element().child_element_at(2).is_none());
+    /// ```
+    ///
+    /// [`Node::child_at`]: struct.Node.html#method.child_at
+    #[inline]
+    pub fn child_element_at(&self, index: usize) -> Option<Self> {
+        self.children().filter(|n| n.is_element()).nth(index)
+    }
+
+    /// Returns the text of the first child element with the specified name.
+    ///
+    /// The same as `children().find(|n| n.has_tag_name(name)).and_then(|n| n.text())`.
+    /// Just like [`Node::text`], returns only the first text child of the found element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<config><host>localhost</host><port>9</port><empty/></config>"
+    /// ).unwrap();
+    ///
+    /// assert_eq!(doc.root_element().child_element_text("port"), Some("9"));
+    /// assert_eq!(doc.root_element().child_element_text("empty"), None);
+    /// assert_eq!(doc.root_element().child_element_text("user"), None);
+    /// ```
+    ///
+    /// [`Node::text`]: struct.Node.html#method.text
+    pub fn child_element_text<'n, 'm, N>(&self, name: N) -> Option<&'a str>
+    where
+        N: Into<ExpandedName<'n, 'm>>,
+    {
+        let name = name.into();
+        self.children()
+            .find(|n| n.has_tag_name(name))
+            .and_then(|n| n.text())
+    }
+
     /// Returns the last child of this node.
     #[inline]
     pub fn last_child(&self) -> Option<Self> {
@@ -1373,7 +3006,7 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
 
     /// Returns the last element child of this node.
     pub fn last_element_child(&self) -> Option<Self> {
-        self.children().filter(|n| n.is_element()).last()
+        self.children().rfind(|n| n.is_element())
     }
 
     /// Returns true if this node has siblings.
@@ -1388,6 +3021,50 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
         self.d.last_child.is_some()
     }
 
+    /// Checks that node is an element without any child nodes.
+    ///
+    /// Text, comments and processing instructions are child nodes too,
+    /// so an element with whitespace-only text is not empty.
+    /// Both `<e/>` and `<e></e>` are empty, since they cannot be distinguished.
+    ///
+    /// Always returns `false` for non-element nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e><a/><b></b><c> </c></e>").unwrap();
+    ///
+    /// let e = doc.root_element();
+    /// let flags: Vec<_> = e.children().map(|n| n.is_empty()).collect();
+    /// assert_eq!(flags, [true, true, false]);
+    /// assert!(!e.is_empty());
+    /// ```
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.is_element() && self.d.last_child.is_none()
+    }
+
+    /// Returns node's depth in the tree.
+    ///
+    /// The root node has a depth of 0, the root element has a depth of 1 and so on.
+    ///
+    /// This method walks the ancestors, therefore it's O(depth).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<a><b>text</b></a>").unwrap();
+    ///
+    /// assert_eq!(doc.root().depth(), 0);
+    /// assert_eq!(doc.root_element().depth(), 1);
+    /// assert_eq!(doc.descendants().last().unwrap().depth(), 3);
+    /// ```
+    #[inline]
+    pub fn depth(&self) -> usize {
+        // `ancestors` includes the current node.
+        self.ancestors().count() - 1
+    }
+
     /// Returns an iterator over ancestor nodes starting at this node.
     #[inline]
     pub fn ancestors(&self) -> AxisIter<'a, 'input> {
@@ -1397,6 +3074,17 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
         }
     }
 
+    /// Returns an iterator over ancestor nodes starting at the parent of this node.
+    ///
+    /// Same as `ancestors().skip(1)`.
+    #[inline]
+    pub fn proper_ancestors(&self) -> AxisIter<'a, 'input> {
+        AxisIter {
+            node: self.parent(),
+            next: Node::parent,
+        }
+    }
+
     /// Returns an iterator over previous sibling nodes starting at this node.
     #[inline]
     pub fn prev_siblings(&self) -> AxisIter<'a, 'input> {
@@ -1433,26 +3121,492 @@ impl<'a, 'input: 'a> Node<'a, 'input> {
         }
     }
 
-    /// Returns an iterator over children nodes.
+    /// Returns an iterator over children nodes.
+    #[inline]
+    pub fn children(&self) -> Children<'a, 'input> {
+        Children {
+            front: self.first_child(),
+            back: self.last_child(),
+        }
+    }
+
+    /// Returns an iterator over all sibling nodes, including this node, in document order.
+    ///
+    /// Same as `parent().children()`. Since the root node has no parent,
+    /// it yields only the root node itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e><a/><b/><c/></e>").unwrap();
+    /// let b = doc.root_element().children().nth(1).unwrap();
+    ///
+    /// let names: Vec<_> = b.siblings().map(|n| n.tag_name().name()).collect();
+    /// assert_eq!(names, ["a", "b", "c"]);
+    /// assert_eq!(doc.root().siblings().count(), 1);
+    /// ```
+    #[inline]
+    pub fn siblings(&self) -> Children<'a, 'input> {
+        match self.parent() {
+            Some(parent) => parent.children(),
+            None => Children {
+                front: Some(*self),
+                back: Some(*self),
+            },
+        }
+    }
+
+    /// Returns the number of children.
+    ///
+    /// Children are not stored contiguously, so they still have to be walked,
+    /// but their descendants are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e><a><b/></a>text<c/></e>").unwrap();
+    ///
+    /// assert_eq!(doc.root_element().children_count(), 3);
+    /// assert_eq!(doc.root_element().last_child().unwrap().children_count(), 0);
+    /// ```
+    #[inline]
+    pub fn children_count(&self) -> usize {
+        self.children().count()
+    }
+
+    /// Copies this node and its descendants into a new document.
+    ///
+    /// Namespaces in scope of this node are declared on the new root element,
+    /// so the copy stays well-formed.
+    ///
+    /// The copy is not an owned document. Strings are shared with the original document,
+    /// while names are borrowed from the same input, hence the resulting document
+    /// cannot outlive the input text.
+    ///
+    /// When called on the root node, copies the whole document.
+    /// The resulting document doesn't have an input text, just like one created via
+    /// [`DocumentBuilder`].
+    ///
+    /// # Errors
+    ///
+    /// - [`Error::NoRootNode`] for nodes other than elements and the root node.
+    /// - Any [`DocumentBuilder`] error, which should not happen for a parsed document.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<svg xmlns='http://www.w3.org/2000/svg'><g id='g1'><rect/></g></svg>"
+    /// ).unwrap();
+    ///
+    /// let g = doc.root_element().first_child().unwrap();
+    /// let g_doc = g.to_document().unwrap();
+    /// assert!(g_doc.root_element().has_tag_name(("http://www.w3.org/2000/svg", "g")));
+    /// assert_eq!(g_doc.root_element().attribute("id"), Some("g1"));
+    /// assert_eq!(g_doc.descendants().count(), 3);
+    /// ```
+    pub fn to_document(&self) -> Result<Document<'input>, Error> {
+        if !self.is_element() && !self.is_root() {
+            return Err(Error::NoRootNode);
+        }
+
+        DocumentBuilder::from_subtree(*self)
+    }
+
+    /// Returns an iterator over this node and its descendants.
+    #[inline]
+    pub fn descendants(&self) -> Descendants<'a, 'input> {
+        Descendants::new(*self)
+    }
+
+    /// Returns an iterator over this node and its descendants of a specified type.
+    ///
+    /// Faster than filtering [`Node::descendants`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::NodeType;
+    ///
+    /// let doc = roxmltree::Document::parse("<e><!-- c1 --><b/>text<!-- c2 --></e>").unwrap();
+    ///
+    /// assert_eq!(doc.root().descendants_of_type(NodeType::Element).count(), 2);
+    /// assert_eq!(doc.root().descendants_of_type(NodeType::Comment).count(), 2);
+    /// ```
+    #[inline]
+    pub fn descendants_of_type(&self, node_type: NodeType) -> DescendantsOfType<'a, 'input> {
+        DescendantsOfType {
+            descendants: Descendants::new(*self),
+            node_type,
+        }
+    }
+
+    /// Returns an iterator over this node and its descendant elements with a specified tag name.
+    ///
+    /// Matches tag names the same way as [`Node::has_tag_name`],
+    /// but faster than filtering [`Node::descendants`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("\
+    /// <e xmlns:n='http://www.w3.org'>
+    ///     <b/><n:b/><c><b/></c>
+    /// </e>
+    /// ").unwrap();
+    ///
+    /// assert_eq!(doc.root().descendant_elements_named("b").count(), 3);
+    /// assert_eq!(doc.root().descendant_elements_named(("http://www.w3.org", "b")).count(), 1);
+    /// ```
+    #[inline]
+    pub fn descendant_elements_named<'n, 'm, N>(
+        &self,
+        name: N,
+    ) -> DescendantElementsNamed<'a, 'input, 'm>
+    where
+        N: Into<ExpandedName<'n, 'm>>,
+    {
+        DescendantElementsNamed::new(*self, name.into())
+    }
+
+    /// Returns an iterator over this node and its descendants along with their depth.
+    ///
+    /// The depth is relative to this node, which has a depth of 0.
+    /// Unlike calling [`Node::depth`] for each node, the depth is tracked incrementally
+    /// and without allocations.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e><a><b/></a><c/></e>").unwrap();
+    ///
+    /// let depths: Vec<_> = doc.root_element()
+    ///     .descendants_with_depth()
+    ///     .map(|(n, depth)| (n.tag_name().name(), depth))
+    ///     .collect();
+    /// assert_eq!(depths, [("e", 0), ("a", 1), ("b", 2), ("c", 1)]);
+    /// ```
+    #[inline]
+    pub fn descendants_with_depth(&self) -> DescendantsWithDepth<'a, 'input> {
+        DescendantsWithDepth {
+            descendants: Descendants::new(*self),
+            prev: None,
+        }
+    }
+
+    /// Returns an iterator over this node and its descendants,
+    /// skipping subtrees the `should_enter` predicate returns `false` for.
+    ///
+    /// When `should_enter` returns `false` for a node, neither the node itself
+    /// nor any of its descendants will be yielded.
+    /// Skipped subtrees are not iterated at all, unlike filtering [`Node::descendants`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<e><metadata><a/><b/></metadata><c><d/></c></e>"
+    /// ).unwrap();
+    ///
+    /// let names: Vec<_> = doc.root_element()
+    ///     .descendants_pruned(|n| !n.has_tag_name("metadata"))
+    ///     .map(|n| n.tag_name().name())
+    ///     .collect();
+    /// assert_eq!(names, ["e", "c", "d"]);
+    /// ```
+    #[inline]
+    pub fn descendants_pruned<F>(&self, should_enter: F) -> DescendantsPruned<'a, 'input, F>
+    where
+        F: Fn(Node<'a, 'input>) -> bool,
+    {
+        let until = self
+            .d
+            .next_subtree
+            .map(NodeId::get_usize)
+            .unwrap_or(self.doc.nodes.len());
+
+        DescendantsPruned {
+            doc: self.doc,
+            next: self.id.get_usize(),
+            until,
+            should_enter,
+        }
+    }
+
+    /// Returns elements selected by a simple path, in document order.
+    ///
+    /// This is not an XPath implementation. A path is a `/`-separated list
+    /// of local names, relative to this node, where:
+    ///
+    /// - `name` selects child elements with a specified local name
+    /// - `*` selects all child elements
+    /// - an empty segment, like in `a//b`, selects this node and its descendant elements
+    ///
+    /// Namespaces, attributes and predicates are not supported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("\
+    /// <svg>
+    ///     <g><rect id='r1'/></g>
+    ///     <g><g><rect id='r2'/></g></g>
+    /// </svg>
+    /// ").unwrap();
+    ///
+    /// let ids = |path| -> Vec<_> {
+    ///     doc.root().select(path).map(|n| n.attribute("id").unwrap()).collect()
+    /// };
+    ///
+    /// assert_eq!(ids("svg/g/rect"), ["r1"]);
+    /// assert_eq!(ids("svg/*/*/rect"), ["r2"]);
+    /// assert_eq!(ids("//rect"), ["r1", "r2"]);
+    /// ```
+    pub fn select(&self, path: &str) -> impl Iterator<Item = Node<'a, 'input>> {
+        let mut nodes = alloc::vec![*self];
+        for segment in path.split('/') {
+            let mut next_nodes = Vec::new();
+            if segment.is_empty() {
+                // `nodes` are in document order, so a node within the previous node subtree
+                // was already added.
+                let mut covered_until = 0;
+                for node in nodes {
+                    if node.id.get_usize() < covered_until {
+                        continue;
+                    }
+
+                    covered_until = node
+                        .d
+                        .next_subtree
+                        .map(NodeId::get_usize)
+                        .unwrap_or(node.doc.nodes.len());
+                    next_nodes.push(node);
+                    next_nodes.extend(node.descendants().skip(1).filter(|n| n.is_element()));
+                }
+            } else {
+                for node in nodes {
+                    next_nodes.extend(node.children().filter(|n| {
+                        n.is_element() && (segment == "*" || n.tag_name().name() == segment)
+                    }));
+                }
+
+                // Children of nested nodes can be out of order.
+                next_nodes.sort_unstable_by_key(|n| n.id.get());
+                next_nodes.dedup();
+            }
+
+            nodes = next_nodes;
+        }
+
+        nodes.into_iter()
+    }
+
+    /// Returns node's range in bytes in the original document.
+    ///
+    /// For a text node, covers exactly its character data,
+    /// including entity references and all merged parts, like text and CDATA.
+    /// Text produced by an entity reference covers the text with the reference itself
+    /// and not the entity definition.
+    ///
+    /// For a comment and CDATA, delimiters are included.
+    /// Use [`Node::value_range`] to get the content only.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let text = "<e>a&amp;b<![CDATA[c]]></e>";
+    /// let doc = roxmltree::Document::parse(text).unwrap();
+    ///
+    /// let node = doc.root_element().first_child().unwrap();
+    /// assert_eq!(&text[node.range()], "a&amp;b<![CDATA[c]]>");
+    /// ```
+    #[cfg(feature = "positions")]
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        self.d.range.clone()
+    }
+
+    /// Returns element's close tag range in bytes in the original document.
+    ///
+    /// Returns `None` for self-closing elements, unclosed elements
+    /// when parsed via [`Document::parse_lossy`] and non-element nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let text = "<e><a/>text</e >";
+    /// let doc = roxmltree::Document::parse(text).unwrap();
+    ///
+    /// let e = doc.root_element();
+    /// assert_eq!(&text[e.end_tag_range().unwrap()], "</e >");
+    /// assert_eq!(e.first_child().unwrap().end_tag_range(), None);
+    /// ```
+    #[cfg(feature = "positions")]
+    pub fn end_tag_range(&self) -> Option<Range<usize>> {
+        if !self.is_element() {
+            return None;
+        }
+
+        // The range of a closed element ends with the close tag. And since attribute values
+        // cannot contain `<`, the last `</` is always the close tag start.
+        let range = self.d.range.clone();
+        let start = self.doc.text.get(range.clone())?.rfind("</")?;
+        Some(range.start + start..range.end)
+    }
+
+    /// Returns text or comment content range in bytes in the original document.
+    ///
+    /// Unlike [`Node::range`], excludes comment delimiters and leading/trailing
+    /// CDATA delimiters of a text node.
+    ///
+    /// Returns `None` for other node types and for text nodes that don't map
+    /// to a single part of the input, i.e. merged from several parts or produced by an entity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let text = "<e><!--comment--><![CDATA[text]]></e>";
+    /// let doc = roxmltree::Document::parse(text).unwrap();
+    ///
+    /// let mut iter = doc.root_element().children();
+    /// let comment = iter.next().unwrap();
+    /// assert_eq!(&text[comment.range()], "<!--comment-->");
+    /// assert_eq!(&text[comment.value_range().unwrap()], "comment");
+    ///
+    /// let cdata = iter.next().unwrap();
+    /// assert_eq!(&text[cdata.range()], "<![CDATA[text]]>");
+    /// assert_eq!(&text[cdata.value_range().unwrap()], "text");
+    ///
+    /// assert_eq!(doc.root_element().value_range(), None);
+    /// ```
+    #[cfg(feature = "positions")]
+    pub fn value_range(&self) -> Option<Range<usize>> {
+        let (open, close) = match self.d.kind {
+            NodeKind::Text(_, false) => ("<![CDATA[", "]]>"),
+            NodeKind::Comment(_) => ("<!--", "-->"),
+            _ => return None,
+        };
+
+        let mut range = self.d.range.clone();
+        let source = self.source_text();
+        if source.starts_with(open) && source.ends_with(close) {
+            range.start += open.len();
+            range.end -= close.len();
+        } else if self.is_comment() {
+            // A comment produced by an entity has the range of the reference.
+            return None;
+        }
+
+        Some(range)
+    }
+
+    /// Returns node's start position in the original document.
+    ///
+    /// Unlike [`Document::text_pos_at`], doesn't calculate anything,
+    /// since positions are precomputed during parsing.
+    ///
+    /// Returns `1:1` for nodes created via [`DocumentBuilder`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::*;
+    ///
+    /// let doc = Document::parse("<e>\n    <b/></e>").unwrap();
+    ///
+    /// let b = doc.root_element().first_element_child().unwrap();
+    /// assert_eq!(b.text_pos(), TextPos::new(2, 5));
+    /// assert_eq!(b.text_pos(), doc.text_pos_at(b.range().start));
+    /// ```
+    #[cfg(feature = "line_positions")]
     #[inline]
-    pub fn children(&self) -> Children<'a, 'input> {
-        Children {
-            front: self.first_child(),
-            back: self.last_child(),
-        }
+    pub fn text_pos(&self) -> TextPos {
+        self.d.text_pos
     }
 
-    /// Returns an iterator over this node and its descendants.
+    /// Returns node's source text in the original document.
+    ///
+    /// Unlike [`Node::text`], returns raw, not normalized text.
+    /// So for a text node, line breaks and entity references are preserved as is.
+    ///
+    /// Same as `&doc.input_text()[node.range()]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e a='1'>&lt;text&gt;</e>").unwrap();
+    ///
+    /// let e = doc.root_element();
+    /// assert_eq!(e.source_text(), "<e a='1'>&lt;text&gt;</e>");
+    /// assert_eq!(e.first_child().unwrap().source_text(), "&lt;text&gt;");
+    /// assert_eq!(e.text(), Some("<text>"));
+    /// ```
+    #[cfg(feature = "positions")]
     #[inline]
-    pub fn descendants(&self) -> Descendants<'a, 'input> {
-        Descendants::new(*self)
+    pub fn source_text(&self) -> &'input str {
+        self.doc.text.get(self.d.range.clone()).unwrap_or("")
     }
 
-    /// Returns node's range in bytes in the original document.
+    /// Checks that the element was written using the self-closing form, like `<e/>`.
+    ///
+    /// Returns `false` for `<e></e>`, non-element nodes and elements without an input text,
+    /// like the ones created via [`DocumentBuilder`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e><a/><b></b></e>").unwrap();
+    ///
+    /// let mut iter = doc.root_element().children();
+    /// assert!(iter.next().unwrap().was_self_closing());
+    /// assert!(!iter.next().unwrap().was_self_closing());
+    /// assert!(!doc.root_element().was_self_closing());
+    /// ```
     #[cfg(feature = "positions")]
     #[inline]
-    pub fn range(&self) -> Range<usize> {
-        self.d.range.clone()
+    pub fn was_self_closing(&self) -> bool {
+        // Doesn't require any additional memory, since the element range
+        // ends with `/>` only when it was self-closing.
+        self.is_element() && self.source_text().ends_with("/>")
+    }
+
+    /// Returns element's local name position in bytes in the original document.
+    ///
+    /// Unlike [`Node::range`], doesn't require the `positions` build feature,
+    /// since it is calculated from the tag name slice itself.
+    ///
+    /// For elements produced by an entity reference, points to the name
+    /// inside the entity definition.
+    ///
+    /// Returns `None` for non-element nodes and for elements created via [`DocumentBuilder`].
+    ///
+    /// ```text
+    /// <n:e/>
+    ///    ^
+    /// ```
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e><n:b xmlns:n='http://www.w3.org'/></e>").unwrap();
+    ///
+    /// assert_eq!(doc.root_element().tag_name_offset(), Some(1));
+    /// assert_eq!(doc.root_element().first_child().unwrap().tag_name_offset(), Some(6));
+    /// assert_eq!(doc.root().tag_name_offset(), None);
+    /// ```
+    ///
+    /// [`Node::range`]: struct.Node.html#method.range
+    /// [`DocumentBuilder`]: struct.DocumentBuilder.html
+    pub fn tag_name_offset(&self) -> Option<usize> {
+        match self.d.kind {
+            #[cfg(not(feature = "intern_names"))]
+            NodeKind::Element { ref tag_name, .. } => self.doc.text_offset(tag_name.local_name),
+            #[cfg(feature = "intern_names")]
+            NodeKind::Element {
+                local_name_offset, ..
+            } => local_name_offset,
+            _ => None,
+        }
     }
 
     /// Returns node's NodeId
@@ -1479,12 +3633,49 @@ impl<'a, 'input: 'a> fmt::Debug for Node<'a, 'input> {
                 write!(f, "PI {{ target: {:?}, value: {:?} }}", pi.target, pi.value)
             }
             NodeKind::Comment(ref text) => write!(f, "Comment({:?})", text.as_str()),
-            NodeKind::Text(ref text) => write!(f, "Text({:?})", text.as_str()),
+            NodeKind::Text(ref text, _) => write!(f, "Text({:?})", text.as_str()),
+        }
+    }
+}
+
+/// Concatenates the text of all text nodes in the iterator.
+///
+/// Avoids allocation when there is only one text node.
+fn concat_text_nodes<'a, 'input: 'a>(
+    nodes: impl Iterator<Item = Node<'a, 'input>>,
+) -> StringStorage<'input> {
+    let mut texts = nodes.filter_map(|n| match n.d.kind {
+        NodeKind::Text(ref text, _) => Some(text),
+        _ => None,
+    });
+
+    let first = match texts.next() {
+        Some(text) => text,
+        None => return StringStorage::Borrowed(""),
+    };
+
+    let mut concat_text = match texts.next() {
+        Some(text) => {
+            let mut concat_text = String::from(first.as_str());
+            concat_text.push_str(text.as_str());
+            concat_text
         }
+        None => return first.clone(),
+    };
+
+    for text in texts {
+        concat_text.push_str(text.as_str());
     }
+
+    StringStorage::new_owned(concat_text)
 }
 
 /// Iterator over a node's attributes
+///
+/// Attributes are guaranteed to be in the same order as in the original document.
+///
+/// Since attributes are stored contiguously, [`Attributes::get`] and [`ExactSizeIterator::len`]
+/// are O(1), which allows indexing and binary searching without collecting attributes first.
 #[derive(Clone)]
 pub struct Attributes<'a, 'input> {
     doc: &'a Document<'input>,
@@ -1505,6 +3696,103 @@ impl<'a, 'input> Attributes<'a, 'input> {
             attrs: attrs.iter(),
         }
     }
+
+    /// Returns an attribute at the specified index.
+    ///
+    /// The index is relative to the attributes that were not consumed by the iterator yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse("<e a='1' b='2'/>").unwrap();
+    ///
+    /// let attrs = doc.root_element().attributes();
+    /// assert_eq!(attrs.get(1).unwrap().value(), "2");
+    /// assert_eq!(attrs.get(0).unwrap().value(), "1");
+    /// assert!(attrs.get(2).is_none());
+    /// ```
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<Attribute<'a, 'input>> {
+        self.attrs.as_slice().get(index).map(|attr| Attribute {
+            doc: self.doc,
+            data: attr,
+        })
+    }
+
+    /// Returns the first attribute with a specified local name, ignoring namespaces.
+    ///
+    /// Searches only the attributes that were not consumed by the iterator yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<e xmlns:n='http://www.w3.org' n:a='1' b='2'/>"
+    /// ).unwrap();
+    ///
+    /// let attrs = doc.root_element().attributes();
+    /// assert_eq!(attrs.find_local("a").unwrap().value(), "1");
+    /// assert_eq!(attrs.find_local("b").unwrap().value(), "2");
+    /// assert!(attrs.find_local("c").is_none());
+    /// ```
+    #[inline]
+    pub fn find_local(&self, local: &str) -> Option<Attribute<'a, 'input>> {
+        self.clone().find(|attr| attr.name() == local)
+    }
+
+    /// Returns the index of the first attribute with a specified name.
+    ///
+    /// The index is relative to the attributes that were not consumed by the iterator yet,
+    /// just like in [`Attributes::get`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let doc = roxmltree::Document::parse(
+    ///     "<e xmlns:n='http://www.w3.org' a='1' n:a='2'/>"
+    /// ).unwrap();
+    ///
+    /// let attrs = doc.root_element().attributes();
+    /// assert_eq!(attrs.position_of("a"), Some(0));
+    /// assert_eq!(attrs.position_of(("http://www.w3.org", "a")), Some(1));
+    /// assert_eq!(attrs.position_of("b"), None);
+    /// ```
+    pub fn position_of<'n, 'm, N>(&self, name: N) -> Option<usize>
+    where
+        N: Into<ExpandedName<'n, 'm>>,
+    {
+        let name = name.into();
+        self.attrs
+            .as_slice()
+            .iter()
+            .position(|attr| attr.name.as_expanded_name(self.doc) == name)
+    }
+
+    /// Returns an iterator over expanded name and value pairs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use roxmltree::ExpandedName;
+    ///
+    /// let doc = roxmltree::Document::parse(
+    ///     "<e xmlns:n='http://www.w3.org' n:a='1' a='2'/>"
+    /// ).unwrap();
+    ///
+    /// let map: BTreeMap<_, _> = doc.root_element().attributes().pairs().collect();
+    /// assert_eq!(map[&ExpandedName::from(("http://www.w3.org", "a"))], "1");
+    /// assert_eq!(map[&ExpandedName::from("a")], "2");
+    /// ```
+    #[inline]
+    pub fn pairs(
+        self,
+    ) -> impl DoubleEndedIterator<Item = (ExpandedName<'a, 'input>, &'a str)> + ExactSizeIterator
+    {
+        let doc = self.doc;
+        self.attrs
+            .map(move |attr| (attr.name.as_expanded_name(doc), attr.value.as_str()))
+    }
 }
 
 impl<'a, 'input> Iterator for Attributes<'a, 'input> {
@@ -1601,6 +3889,16 @@ impl<'a, 'input: 'a> Iterator for Children<'a, 'input> {
             node
         }
     }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match (self.front, self.back) {
+            // Children are stored in the document order, so there cannot be more of them
+            // than nodes between the first and the last one.
+            (Some(front), Some(back)) => (1, Some(back.id.get_usize() - front.id.get_usize() + 1)),
+            _ => (0, Some(0)),
+        }
+    }
 }
 
 impl<'a, 'input: 'a> DoubleEndedIterator for Children<'a, 'input> {
@@ -1696,6 +3994,278 @@ impl fmt::Debug for Descendants<'_, '_> {
     }
 }
 
+/// Iterator over a node and its descendants of a specified type.
+///
+/// Created via [`Node::descendants_of_type`].
+#[derive(Clone)]
+pub struct DescendantsOfType<'a, 'input> {
+    descendants: Descendants<'a, 'input>,
+    node_type: NodeType,
+}
+
+impl<'a, 'input> Iterator for DescendantsOfType<'a, 'input> {
+    type Item = Node<'a, 'input>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let node_type = self.node_type;
+        let from = self.descendants.from;
+        let (idx, data) = self
+            .descendants
+            .nodes
+            .find(|(_, data)| data.kind.node_type() == node_type)?;
+
+        Some(Node {
+            id: NodeId::from(from + idx),
+            d: data,
+            doc: self.descendants.doc,
+        })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.descendants.size_hint().1)
+    }
+}
+
+impl<'a, 'input> DoubleEndedIterator for DescendantsOfType<'a, 'input> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let node_type = self.node_type;
+        let from = self.descendants.from;
+        let (idx, data) = self
+            .descendants
+            .nodes
+            .rfind(|(_, data)| data.kind.node_type() == node_type)?;
+
+        Some(Node {
+            id: NodeId::from(from + idx),
+            d: data,
+            doc: self.descendants.doc,
+        })
+    }
+}
+
+impl fmt::Debug for DescendantsOfType<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("DescendantsOfType")
+            .field("descendants", &self.descendants)
+            .field("node_type", &self.node_type)
+            .finish()
+    }
+}
+
+/// Iterator over a node and its descendants along with their depth.
+///
+/// Created via [`Node::descendants_with_depth`].
+#[derive(Clone)]
+pub struct DescendantsWithDepth<'a, 'input> {
+    descendants: Descendants<'a, 'input>,
+    prev: Option<(Node<'a, 'input>, usize)>,
+}
+
+impl<'a, 'input> Iterator for DescendantsWithDepth<'a, 'input> {
+    type Item = (Node<'a, 'input>, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.descendants.next()?;
+        let depth = match self.prev {
+            None => 0,
+            Some((prev, depth)) if node.d.parent == Some(prev.id) => depth + 1,
+            Some((mut prev, mut depth)) => {
+                // The node is a next sibling of the previous node or one of its ancestors.
+                // Each node is left only once, so this is amortized O(1).
+                while prev.d.parent != node.d.parent {
+                    prev = prev.parent()?;
+                    depth -= 1;
+                }
+
+                depth
+            }
+        };
+
+        self.prev = Some((node, depth));
+        Some((node, depth))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.descendants.size_hint()
+    }
+}
+
+impl ExactSizeIterator for DescendantsWithDepth<'_, '_> {}
+
+impl fmt::Debug for DescendantsWithDepth<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("DescendantsWithDepth")
+            .field("descendants", &self.descendants)
+            .field("depth", &self.prev.map(|(_, depth)| depth))
+            .finish()
+    }
+}
+
+/// Iterator over a node and its descendant elements with a specified tag name.
+///
+/// Created via [`Node::descendant_elements_named`].
+#[derive(Clone)]
+pub struct DescendantElementsNamed<'a, 'input, 'm> {
+    descendants: Descendants<'a, 'input>,
+    name: &'m str,
+    // `None` when any namespace is allowed.
+    namespaces: Option<Vec<NamespaceIdx>>,
+}
+
+impl<'a, 'input, 'm> DescendantElementsNamed<'a, 'input, 'm> {
+    fn new(start: Node<'a, 'input>, name: ExpandedName<'_, 'm>) -> Self {
+        // Resolve the namespace URI to indices once, since multiple prefixes
+        // can be bound to the same URI.
+        let namespaces = name.namespace().map(|uri| {
+            let values = &start.doc.namespaces.values;
+            (0..values.len())
+                .filter(|idx| values[*idx].uri() == uri)
+                .map(|idx| NamespaceIdx(idx as u16))
+                .collect()
+        });
+
+        Self {
+            descendants: Descendants::new(start),
+            name: name.name(),
+            namespaces,
+        }
+    }
+
+    #[inline]
+    fn is_match(&self, data: &NodeData) -> bool {
+        match data.kind {
+            NodeKind::Element { ref tag_name, .. } => {
+                if tag_name.local_name(self.descendants.doc) != self.name {
+                    return false;
+                }
+
+                match self.namespaces {
+                    Some(ref namespaces) => tag_name
+                        .namespace_idx
+                        .is_some_and(|idx| namespaces.contains(&idx)),
+                    None => true,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'a, 'input> Iterator for DescendantElementsNamed<'a, 'input, '_> {
+    type Item = Node<'a, 'input>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let from = self.descendants.from;
+        let (idx, data) = loop {
+            let (idx, data) = self.descendants.nodes.next()?;
+            if self.is_match(data) {
+                break (idx, data);
+            }
+        };
+
+        Some(Node {
+            id: NodeId::from(from + idx),
+            d: data,
+            doc: self.descendants.doc,
+        })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.descendants.size_hint().1)
+    }
+}
+
+impl<'a, 'input> DoubleEndedIterator for DescendantElementsNamed<'a, 'input, '_> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let from = self.descendants.from;
+        let (idx, data) = loop {
+            let (idx, data) = self.descendants.nodes.next_back()?;
+            if self.is_match(data) {
+                break (idx, data);
+            }
+        };
+
+        Some(Node {
+            id: NodeId::from(from + idx),
+            d: data,
+            doc: self.descendants.doc,
+        })
+    }
+}
+
+impl fmt::Debug for DescendantElementsNamed<'_, '_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("DescendantElementsNamed")
+            .field("descendants", &self.descendants)
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+/// Iterator over a node and its descendants, skipping pruned subtrees.
+///
+/// Created via [`Node::descendants_pruned`].
+#[derive(Clone)]
+pub struct DescendantsPruned<'a, 'input, F> {
+    doc: &'a Document<'input>,
+    next: usize,
+    until: usize,
+    should_enter: F,
+}
+
+impl<'a, 'input, F> Iterator for DescendantsPruned<'a, 'input, F>
+where
+    F: Fn(Node<'a, 'input>) -> bool,
+{
+    type Item = Node<'a, 'input>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.next < self.until {
+            let node = Node {
+                id: NodeId::from(self.next),
+                d: &self.doc.nodes[self.next],
+                doc: self.doc,
+            };
+
+            if (self.should_enter)(node) {
+                self.next += 1;
+                return Some(node);
+            }
+
+            // Jump over the whole subtree.
+            self.next = node
+                .d
+                .next_subtree
+                .map(NodeId::get_usize)
+                .unwrap_or(self.until)
+                .min(self.until);
+        }
+
+        None
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.until - self.next))
+    }
+}
+
+impl<F> fmt::Debug for DescendantsPruned<'_, '_, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_struct("DescendantsPruned")
+            .field("next", &self.next)
+            .field("until", &self.until)
+            .finish()
+    }
+}
+
 /// Iterator over the namespaces attached to a node.
 #[derive(Clone)]
 pub struct NamespaceIter<'a, 'input> {
@@ -1730,7 +4300,7 @@ impl<'a, 'input> DoubleEndedIterator for NamespaceIter<'a, 'input> {
     #[inline]
     fn next_back(&mut self) -> Option<Self::Item> {
         self.namespaces
-            .next()
+            .next_back()
             .map(|idx| self.doc.namespaces.get(*idx))
     }
 }
@@ -0,0 +1,105 @@
+use alloc::string::String;
+
+use crate::{Document, Error, ParsingOptions};
+
+type Result<T> = core::result::Result<T, Error>;
+
+/// An XML document that owns its input text.
+///
+/// [`Document`] borrows the input text, which makes it impossible to store
+/// both of them in the same struct. `OwnedDocument` solves this by storing
+/// only the text and parsing options and producing a [`Document`] on demand.
+///
+/// **Note:** since self-referential structs cannot be implemented without `unsafe`,
+/// the whole text is parsed again on each [`OwnedDocument::document`] call.
+/// Therefore, the returned document should be reused whenever possible.
+///
+/// # Examples
+///
+/// ```
+/// struct Config {
+///     doc: roxmltree::OwnedDocument,
+/// }
+///
+/// let config = Config {
+///     doc: "<config><port>9</port></config>".parse().unwrap(),
+/// };
+///
+/// let doc = config.doc.document();
+/// assert_eq!(doc.root_element().child_element_text("port"), Some("9"));
+/// ```
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct OwnedDocument {
+    text: String,
+    opt: ParsingOptions,
+}
+
+impl OwnedDocument {
+    /// Parses the input XML string.
+    ///
+    /// Accepts anything convertible into a `String`, like `&str` or `Cow<str>`.
+    /// A `String` or an owned `Cow` is not copied.
+    ///
+    /// The text is validated right away, so [`OwnedDocument::document`] cannot fail.
+    /// See [`OwnedDocument::parse_with_options`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    ///
+    /// let text: Cow<str> = Cow::Owned(String::from("<e/>"));
+    /// let doc = roxmltree::OwnedDocument::parse(text).unwrap();
+    /// assert_eq!(doc.document().root_element().tag_name().name(), "e");
+    /// ```
+    #[inline]
+    pub fn parse(text: impl Into<String>) -> Result<Self> {
+        Self::parse_with_options(text, ParsingOptions::default())
+    }
+
+    /// Parses the input XML string using the specified options.
+    ///
+    /// The text is validated right away via [`validate`](crate::validate),
+    /// which doesn't build a tree, so [`OwnedDocument::document`] cannot fail.
+    pub fn parse_with_options(text: impl Into<String>, opt: ParsingOptions) -> Result<Self> {
+        let text = text.into();
+        crate::validate(&text, opt)?;
+        Ok(OwnedDocument { text, opt })
+    }
+
+    /// Returns a document borrowing the owned text.
+    ///
+    /// **Note:** the whole text is parsed again on each call.
+    /// Therefore, the returned document should be reused whenever possible.
+    pub fn document(&self) -> Document<'_> {
+        // The text was already successfully validated with the same options.
+        Document::parse_with_options(&self.text, self.opt).unwrap()
+    }
+
+    /// Returns the owned input text.
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the parsing options used by this document.
+    #[inline]
+    pub fn options(&self) -> ParsingOptions {
+        self.opt
+    }
+
+    /// Returns the owned input text, consuming the document.
+    #[inline]
+    pub fn into_text(self) -> String {
+        self.text
+    }
+}
+
+impl core::str::FromStr for OwnedDocument {
+    type Err = Error;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
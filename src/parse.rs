@@ -1,14 +1,15 @@
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::string::{String, ToString};
+use alloc::vec;
 use alloc::vec::Vec;
-use core::ops::Range;
+use core::ops::{ControlFlow, Range};
 
 use crate::{
-    AttributeData, Document, ExpandedNameIndexed, NamespaceIdx, Namespaces, NodeData, NodeId,
-    NodeKind, ShortRange, StringStorage, TextPos, NS_XMLNS_URI, NS_XML_PREFIX, NS_XML_URI, PI,
-    XMLNS,
+    AttributeData, Document, LineIndex, NamespaceIdx, Namespaces, NodeData, NodeId, NodeKind,
+    ShortRange, StringStorage, TextPos, NS_XMLNS_URI, NS_XML_PREFIX, NS_XML_URI, PI, XMLNS,
 };
 
-use crate::tokenizer::{self, Reference, StrSpan, Stream};
+use crate::tokenizer::{self, Reference, StrSpan, Stream, XmlByteExt};
 
 type Result<T> = core::result::Result<T, Error>;
 
@@ -63,7 +64,8 @@ pub enum Error {
 
     /// A possible entity reference loop.
     ///
-    /// The current depth limit is 10. The max number of references per reference is 255.
+    /// Limits are controlled by [`ParsingOptions::max_entity_depth`]
+    /// and [`ParsingOptions::max_entity_references`].
     EntityReferenceLoop(TextPos),
 
     /// Attribute value cannot have a `<` character.
@@ -76,7 +78,14 @@ pub enum Error {
     /// ```xml
     /// <e xmlns:n1='http://www.w3.org' xmlns:n2='http://www.w3.org' n1:a='b1' n2:a='b2'/>
     /// ```
-    DuplicatedAttribute(String, TextPos),
+    ///
+    /// Contains positions of the duplicated attribute and of the original one.
+    ///
+    /// Both positions are available even without the `positions` build feature,
+    /// since they are taken from the attributes of the element being parsed
+    /// and not from the stored ones. So there is no single-position variant for such builds,
+    /// which would also make the feature change the public API, while features must be additive.
+    DuplicatedAttribute(String, TextPos, TextPos),
 
     /// The XML document must have at least one element.
     NoRootNode,
@@ -96,6 +105,12 @@ pub enum Error {
     /// Indicates that the [`ParsingOptions::nodes_limit`] was reached.
     NodesLimitReached,
 
+    /// Indicates that the [`ParsingOptions::memory_limit`] was reached.
+    MemoryLimitReached,
+
+    /// Indicates that the [`ParsingOptions::max_depth`] was reached.
+    DepthLimitReached(TextPos),
+
     /// Indicates that too many attributes were parsed.
     AttributesLimitReached,
 
@@ -143,6 +158,56 @@ pub enum Error {
     ///
     /// Should only appear on invalid input data.
     UnexpectedEndOfStream,
+
+    /// The input is not a valid UTF-8 string.
+    ///
+    /// Contains an offset of the first invalid byte.
+    ///
+    /// This error will be emitted only by [`Document::parse_bytes`].
+    InvalidUtf8(usize),
+
+    /// The XML declaration or a byte order mark specifies an encoding other than UTF-8.
+    ///
+    /// UTF-16 and UTF-32 byte order marks are reported at the start of the input.
+    ///
+    /// This error will be emitted only by [`Document::parse_bytes`].
+    UnsupportedEncoding(String, TextPos),
+
+    /// Parsing was cancelled by a progress callback.
+    ///
+    /// This error will be emitted only by [`Document::parse_with_callback`].
+    Cancelled,
+}
+
+/// A coarse error category.
+///
+/// Returned by [`Error::kind`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ErrorKind {
+    /// The document structure is invalid, like mismatched tags or a missing root element.
+    Structure,
+
+    /// The input contains invalid characters or malformed markup.
+    Syntax,
+
+    /// Namespaces are used or declared incorrectly.
+    Namespace,
+
+    /// An entity reference is unknown or malformed.
+    Entity,
+
+    /// One of the parsing limits was reached.
+    ///
+    /// Includes [`Error::EntityReferenceLoop`], since it's detected using limits.
+    /// Parsing may succeed with less restrictive [`ParsingOptions`].
+    Limit,
+
+    /// The input is valid, but was rejected by [`ParsingOptions`], like DTD,
+    /// or parsing was cancelled by the caller.
+    Policy,
+
+    /// The input is not valid UTF-8 or uses an unsupported encoding.
+    Io,
 }
 
 impl Error {
@@ -161,12 +226,14 @@ impl Error {
             Error::MalformedEntityReference(pos) => pos,
             Error::EntityReferenceLoop(pos) => pos,
             Error::InvalidAttributeValue(pos) => pos,
-            Error::DuplicatedAttribute(_, pos) => pos,
+            Error::DuplicatedAttribute(_, pos, _) => pos,
             Error::NoRootNode => TextPos::new(1, 1),
             Error::UnclosedRootNode => TextPos::new(1, 1),
             Error::UnexpectedDeclaration(pos) => pos,
             Error::DtdDetected => TextPos::new(1, 1),
             Error::NodesLimitReached => TextPos::new(1, 1),
+            Error::MemoryLimitReached => TextPos::new(1, 1),
+            Error::DepthLimitReached(pos) => pos,
             Error::AttributesLimitReached => TextPos::new(1, 1),
             Error::NamespacesLimitReached => TextPos::new(1, 1),
             Error::InvalidName(pos) => pos,
@@ -179,10 +246,117 @@ impl Error {
             Error::InvalidCharacterData(pos) => pos,
             Error::UnknownToken(pos) => pos,
             Error::UnexpectedEndOfStream => TextPos::new(1, 1),
+            Error::InvalidUtf8(_) => TextPos::new(1, 1),
+            Error::UnsupportedEncoding(_, pos) => pos,
+            Error::Cancelled => TextPos::new(1, 1),
+        }
+    }
+
+    /// Returns the error category.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::{Document, ErrorKind};
+    ///
+    /// let error = Document::parse("<!DOCTYPE e []><e/>").unwrap_err();
+    /// assert_eq!(error.kind(), ErrorKind::Policy);
+    ///
+    /// let error = Document::parse("<e></d>").unwrap_err();
+    /// assert_eq!(error.kind(), ErrorKind::Structure);
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        match *self {
+            Error::InvalidXmlPrefixUri(_) => ErrorKind::Namespace,
+            Error::UnexpectedXmlUri(_) => ErrorKind::Namespace,
+            Error::UnexpectedXmlnsUri(_) => ErrorKind::Namespace,
+            Error::InvalidElementNamePrefix(_) => ErrorKind::Namespace,
+            Error::DuplicatedNamespace(_, _) => ErrorKind::Namespace,
+            Error::UnknownNamespace(_, _) => ErrorKind::Namespace,
+            Error::UnexpectedCloseTag(_, _, _) => ErrorKind::Structure,
+            Error::UnexpectedEntityCloseTag(_) => ErrorKind::Structure,
+            Error::UnknownEntityReference(_, _) => ErrorKind::Entity,
+            Error::MalformedEntityReference(_) => ErrorKind::Entity,
+            Error::EntityReferenceLoop(_) => ErrorKind::Limit,
+            Error::InvalidAttributeValue(_) => ErrorKind::Syntax,
+            Error::DuplicatedAttribute(_, _, _) => ErrorKind::Structure,
+            Error::NoRootNode => ErrorKind::Structure,
+            Error::UnclosedRootNode => ErrorKind::Structure,
+            Error::UnexpectedDeclaration(_) => ErrorKind::Structure,
+            Error::DtdDetected => ErrorKind::Policy,
+            Error::NodesLimitReached => ErrorKind::Limit,
+            Error::MemoryLimitReached => ErrorKind::Limit,
+            Error::DepthLimitReached(_) => ErrorKind::Limit,
+            Error::AttributesLimitReached => ErrorKind::Limit,
+            Error::NamespacesLimitReached => ErrorKind::Limit,
+            Error::InvalidName(_) => ErrorKind::Syntax,
+            Error::NonXmlChar(_, _) => ErrorKind::Syntax,
+            Error::InvalidChar(_, _, _) => ErrorKind::Syntax,
+            Error::InvalidChar2(_, _, _) => ErrorKind::Syntax,
+            Error::InvalidString(_, _) => ErrorKind::Syntax,
+            Error::InvalidExternalID(_) => ErrorKind::Syntax,
+            Error::InvalidComment(_) => ErrorKind::Syntax,
+            Error::InvalidCharacterData(_) => ErrorKind::Syntax,
+            Error::UnknownToken(_) => ErrorKind::Syntax,
+            Error::UnexpectedEndOfStream => ErrorKind::Structure,
+            Error::InvalidUtf8(_) => ErrorKind::Io,
+            Error::UnsupportedEncoding(_, _) => ErrorKind::Io,
+            Error::Cancelled => ErrorKind::Policy,
+        }
+    }
+
+    /// Returns the error position in bytes in the specified input text.
+    ///
+    /// Errors store only a [`TextPos`], which is converted back into a byte offset.
+    /// This operation is expensive, just like calculating a `TextPos`.
+    ///
+    /// The `text` must be the same text that was passed to the parser.
+    /// Returns `None` for errors without a position or when the position
+    /// is outside of the text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let text = "<e>\n<b></c></e>";
+    /// let error = roxmltree::Document::parse(text).unwrap_err();
+    /// assert_eq!(error.byte_offset(text), Some(7));
+    /// assert_eq!(&text[7..], "</c></e>");
+    /// ```
+    pub fn byte_offset(&self, text: &str) -> Option<usize> {
+        match *self {
+            Error::NoRootNode
+            | Error::UnclosedRootNode
+            | Error::DtdDetected
+            | Error::NodesLimitReached
+            | Error::MemoryLimitReached
+            | Error::AttributesLimitReached
+            | Error::NamespacesLimitReached
+            | Error::Cancelled => None,
+            Error::UnexpectedEndOfStream => Some(text.len()),
+            Error::InvalidUtf8(offset) => Some(offset),
+            _ => text_pos_to_byte_offset(text, self.pos()),
         }
     }
 }
 
+fn text_pos_to_byte_offset(text: &str, pos: TextPos) -> Option<usize> {
+    let mut line_start = 0;
+    for _ in 1..pos.row {
+        line_start += text[line_start..].find('\n')? + 1;
+    }
+
+    let line = &text[line_start..];
+    let line = &line[..line.find('\n').unwrap_or(line.len())];
+    let col = pos.col.checked_sub(1)? as usize;
+    let offset = match line.char_indices().nth(col) {
+        Some((offset, _)) => offset,
+        None if line.chars().count() == col => line.len(),
+        None => return None,
+    };
+
+    Some(line_start + offset)
+}
+
 impl core::fmt::Display for Error {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         match *self {
@@ -238,8 +412,12 @@ impl core::fmt::Display for Error {
             Error::InvalidAttributeValue(pos) => {
                 write!(f, "unescaped '<' found at {}", pos)
             }
-            Error::DuplicatedAttribute(ref name, pos) => {
-                write!(f, "attribute '{}' at {} is already defined", name, pos)
+            Error::DuplicatedAttribute(ref name, pos, prev_pos) => {
+                write!(
+                    f,
+                    "attribute '{}' at {} is already defined at {}",
+                    name, pos, prev_pos
+                )
             }
             Error::NoRootNode => {
                 write!(f, "the document does not have a root node")
@@ -256,6 +434,12 @@ impl core::fmt::Display for Error {
             Error::NodesLimitReached => {
                 write!(f, "nodes limit reached")
             }
+            Error::MemoryLimitReached => {
+                write!(f, "memory limit reached")
+            }
+            Error::DepthLimitReached(pos) => {
+                write!(f, "elements nesting depth limit reached at {}", pos)
+            }
             Error::AttributesLimitReached => {
                 write!(f, "more than 2^32 attributes were parsed")
             }
@@ -300,17 +484,80 @@ impl core::fmt::Display for Error {
             Error::UnexpectedEndOfStream => {
                 write!(f, "unexpected end of stream")
             }
+            Error::InvalidUtf8(offset) => {
+                write!(f, "invalid UTF-8 sequence at byte {}", offset)
+            }
+            Error::UnsupportedEncoding(ref name, pos) => {
+                write!(f, "unsupported encoding '{}' at {}", name, pos)
+            }
+            Error::Cancelled => {
+                write!(f, "parsing was cancelled")
+            }
         }
     }
 }
 
-#[cfg(feature = "std")]
-impl std::error::Error for Error {
-    fn description(&self) -> &str {
-        "an XML parsing error"
+// `std::error::Error` is a re-export of `core::error::Error`,
+// so this covers `std` builds as well.
+impl core::error::Error for Error {
+    #[inline]
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        None
+    }
+}
+
+/// A suspicious, but well-formed construct.
+///
+/// Reported by [`Document::parse_with_diagnostics`].
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Diagnostic {
+    code: DiagnosticCode,
+    message: String,
+    pos: TextPos,
+}
+
+impl Diagnostic {
+    /// Returns the diagnostic code.
+    #[inline]
+    pub fn code(&self) -> DiagnosticCode {
+        self.code
+    }
+
+    /// Returns a human-readable description.
+    #[inline]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the position of the construct.
+    #[inline]
+    pub fn pos(&self) -> TextPos {
+        self.pos
+    }
+}
+
+impl core::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{} at {}", self.message, self.pos)
     }
 }
 
+/// A list of all possible diagnostics.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DiagnosticCode {
+    /// A namespace is redeclared with the same URI as in the parent element.
+    RedundantNamespace,
+
+    /// An attribute has an empty value.
+    EmptyAttributeValue,
+
+    /// A processing instruction target starts with `xml`.
+    ///
+    /// Such targets are reserved by the XML specification.
+    /// `xml-stylesheet` and `xml-model` are not reported.
+    ReservedPITarget,
+}
+
 /// Parsing options.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub struct ParsingOptions {
@@ -335,6 +582,219 @@ pub struct ParsingOptions {
     ///
     /// Default: u32::MAX (no limit)
     pub nodes_limit: u32,
+
+    /// Sets the approximate maximum number of bytes the document can use.
+    ///
+    /// The estimate includes nodes, attributes and allocated strings,
+    /// but not the input text itself, which is borrowed.
+    ///
+    /// Unlike [`ParsingOptions::nodes_limit`], takes attributes and allocated strings into account.
+    ///
+    /// Default: None (no limit)
+    pub memory_limit: Option<usize>,
+
+    /// Sets the maximum elements nesting depth.
+    ///
+    /// The root element has a depth of 1.
+    ///
+    /// Useful when dealing with random input to limit the recursion depth
+    /// of the code that processes the document.
+    ///
+    /// Default: u32::MAX (no limit)
+    pub max_depth: u32,
+
+    /// Sets the maximum entity references nesting depth.
+    ///
+    /// Protects against the billion laughs attack, along with [`max_entity_references`].
+    /// Exceeding it leads to [`Error::EntityReferenceLoop`].
+    ///
+    /// When set to 0, references to any custom entities will lead to an error.
+    ///
+    /// Default: 10
+    ///
+    /// [`max_entity_references`]: ParsingOptions::max_entity_references
+    pub max_entity_depth: u32,
+
+    /// Sets the maximum number of nested entity references resolved
+    /// by a single top-level reference.
+    ///
+    /// References outside of entities are not limited.
+    /// Exceeding it leads to [`Error::EntityReferenceLoop`].
+    ///
+    /// When set to 0, custom entities cannot reference other entities.
+    ///
+    /// Default: 255
+    pub max_entity_references: u32,
+
+    /// Removes insignificant whitespace-only text nodes.
+    ///
+    /// A whitespace-only text node is considered insignificant when it has
+    /// at least one element sibling and none of its siblings is a text node
+    /// with non-whitespace characters.
+    /// In other words, whitespaces inside mixed content are preserved.
+    /// Whitespaces inside an element with `xml:space="preserve"` are preserved as well,
+    /// unless a nested element resets it via `xml:space="default"`.
+    ///
+    /// ```xml
+    /// <a>
+    ///     <b> text </b>
+    /// </a>
+    /// ```
+    ///
+    /// Here, the whitespaces around `b` will be removed,
+    /// but the `b` text will be preserved as is.
+    ///
+    /// Default: false
+    pub trim_whitespace: bool,
+
+    /// Expands references to entities declared in the DTD.
+    ///
+    /// When set to `false`, references to known custom entities will be kept in text
+    /// and attribute values as is, like `&name;`. This is useful when the original
+    /// entities usage must be preserved.
+    /// Character references and predefined entities, like `&lt;`, are still resolved,
+    /// except the ones producing `&`, which are kept as `&amp;`.
+    /// The same applies to `&` inside CDATA sections.
+    /// This way, `&amp;name;` and `&name;` produce different strings,
+    /// and any `&` in the produced string starts either `&amp;` or an unexpanded reference.
+    ///
+    /// Unknown entity references are still an error.
+    ///
+    /// Default: true
+    pub expand_entities: bool,
+
+    /// Specifies how references to undeclared entities are handled.
+    ///
+    /// Applies to both text and attribute values.
+    /// Malformed references are still an error.
+    ///
+    /// Default: `UnknownEntityPolicy::Error`
+    pub unknown_entity_policy: UnknownEntityPolicy,
+
+    /// Merges text nodes separated only by comments.
+    ///
+    /// Comments are still preserved, but will be placed after the merged text node.
+    ///
+    /// ```xml
+    /// <p>Hello<!-- comment -->World</p>
+    /// ```
+    ///
+    /// Here, `p` will have a `HelloWorld` text node followed by a comment.
+    ///
+    /// Default: false
+    pub merge_text_across_comments: bool,
+
+    /// Replaces tabs and line breaks in attribute values with spaces.
+    ///
+    /// This is an [attribute-value normalization](https://www.w3.org/TR/xml/#AVNormalize)
+    /// required by the XML spec.
+    ///
+    /// When set to `false`, literal whitespace inside attribute values is preserved.
+    /// Line endings are still normalized to `\n`
+    /// and references are still expanded as usual.
+    ///
+    /// Default: true
+    pub normalize_attribute_whitespace: bool,
+
+    /// Allows `]]>` inside text.
+    ///
+    /// According to the spec, `]]>` must not appear inside text outside of a CDATA section,
+    /// which leads to [`Error::InvalidCharacterData`].
+    /// When set to `true`, such text will be accepted as is.
+    /// Use only for known malformed documents.
+    ///
+    /// Default: false
+    pub allow_cdata_end_in_text: bool,
+
+    /// Applies default attribute values declared via `<!ATTLIST>` inside a DTD.
+    ///
+    /// When enabled, elements that lack an attribute with a declared default value
+    /// will get this attribute with the default value.
+    /// Defaults are appended after element's own attributes, in declaration order.
+    ///
+    /// Only a subset of DTD is supported: `CDATA` attributes with a literal default,
+    /// optionally `#FIXED`, like `<!ATTLIST elem attr CDATA "value">`.
+    /// Enumerated and tokenized attribute types are parsed, but their defaults are ignored.
+    /// Elements and attributes are matched by their qualified names, just like in a DTD.
+    ///
+    /// When the `positions` feature is enabled, the range of a default attribute
+    /// points to its declaration inside the DTD.
+    ///
+    /// Has no effect when `allow_dtd` is `false`.
+    ///
+    /// Default: false
+    pub apply_attlist_defaults: bool,
+
+    /// Stops parsing at unknown content after the root element instead of returning
+    /// an [`Error::UnknownToken`].
+    ///
+    /// Comments and processing instructions after the root element are still parsed,
+    /// until the first unknown content, which is then ignored.
+    /// Its position is available via [`Document::trailing_content_offset`].
+    ///
+    /// Useful for files with some garbage, like logs, appended after the document.
+    ///
+    /// Default: false
+    pub allow_trailing_content: bool,
+
+    /// Allows whitespace before the XML declaration.
+    ///
+    /// The XML declaration must be at the very start of the document,
+    /// but leading whitespace is a common deviation. When set,
+    /// the whitespace is skipped and the declaration is validated as usual.
+    ///
+    /// Default: false
+    pub allow_leading_whitespace_before_declaration: bool,
+
+    /// Parses `<!ELEMENT>` declarations to detect ignorable whitespace.
+    ///
+    /// Only distinguishes element content from mixed content,
+    /// which is enough for [`Node::is_ignorable_whitespace`].
+    /// Content models are not validated.
+    ///
+    /// Has no effect when `allow_dtd` is `false`.
+    ///
+    /// Default: false
+    ///
+    /// [`Node::is_ignorable_whitespace`]: crate::Node::is_ignorable_whitespace
+    pub detect_ignorable_whitespace: bool,
+
+    /// Sets the initial nodes storage capacity.
+    ///
+    /// By default, the capacity is guessed by counting `<` in the input,
+    /// which can overshoot a lot for documents with many comments or CDATA sections.
+    /// The storage will still grow when needed.
+    ///
+    /// Affects only the peak memory usage, since the storage is shrunk to fit after parsing.
+    ///
+    /// Default: None
+    pub reserve_nodes: Option<usize>,
+
+    /// Sets the initial attributes storage capacity.
+    ///
+    /// By default, the capacity is guessed by counting `=` in the input.
+    /// The storage will still grow when needed.
+    ///
+    /// Affects only the peak memory usage, since the storage is shrunk to fit after parsing.
+    ///
+    /// Default: None
+    pub reserve_attributes: Option<usize>,
+}
+
+/// Specifies how references to undeclared entities are handled.
+///
+/// Used by [`ParsingOptions::unknown_entity_policy`].
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum UnknownEntityPolicy {
+    /// Returns an [`Error::UnknownEntityReference`].
+    #[default]
+    Error,
+
+    /// Keeps a reference as is, like `&nbsp;`.
+    Passthrough,
+
+    /// Replaces a reference with a specified character, like `U+FFFD`.
+    Replace(char),
 }
 
 // Explicit for readability.
@@ -343,30 +803,108 @@ impl Default for ParsingOptions {
     fn default() -> Self {
         ParsingOptions {
             allow_dtd: false,
-            nodes_limit: core::u32::MAX,
+            nodes_limit: u32::MAX,
+            memory_limit: None,
+            max_depth: u32::MAX,
+            max_entity_depth: 10,
+            max_entity_references: 255,
+            trim_whitespace: false,
+            expand_entities: true,
+            unknown_entity_policy: UnknownEntityPolicy::Error,
+            merge_text_across_comments: false,
+            normalize_attribute_whitespace: true,
+            allow_cdata_end_in_text: false,
+            apply_attlist_defaults: false,
+            allow_trailing_content: false,
+            allow_leading_whitespace_before_declaration: false,
+            detect_ignorable_whitespace: false,
+            reserve_nodes: None,
+            reserve_attributes: None,
         }
     }
 }
 
+impl ParsingOptions {
+    /// Returns strict parsing options.
+    ///
+    /// Same as [`ParsingOptions::default`].
+    #[inline]
+    pub fn strict() -> Self {
+        Self::default()
+    }
+
+    /// Returns parsing options tolerant to common real-world deviations from the spec.
+    ///
+    /// Differs from the default options by:
+    ///
+    /// - `allow_dtd` set to `true`
+    /// - `unknown_entity_policy` set to [`UnknownEntityPolicy::Passthrough`]
+    /// - `allow_cdata_end_in_text` set to `true`
+    /// - `allow_trailing_content` set to `true`
+    /// - `allow_leading_whitespace_before_declaration` set to `true`
+    pub fn lenient() -> Self {
+        ParsingOptions {
+            allow_dtd: true,
+            unknown_entity_policy: UnknownEntityPolicy::Passthrough,
+            allow_cdata_end_in_text: true,
+            allow_trailing_content: true,
+            allow_leading_whitespace_before_declaration: true,
+            ..Self::default()
+        }
+    }
+
+    /// Returns parsing options suitable for untrusted input.
+    ///
+    /// Differs from the default options by:
+    ///
+    /// - `nodes_limit` set to 1 000 000
+    /// - `memory_limit` set to 64 MiB
+    /// - `max_depth` set to 256
+    ///
+    /// DTD is not allowed, just like by default.
+    pub fn untrusted() -> Self {
+        ParsingOptions {
+            allow_dtd: false,
+            nodes_limit: 1_000_000,
+            memory_limit: Some(64 * 1024 * 1024),
+            max_depth: 256,
+            ..Self::default()
+        }
+    }
+}
+
+struct AttributeDefault<'input> {
+    prefix: &'input str,
+    local: &'input str,
+    value: StrSpan<'input>,
+    range: Range<usize>,
+    qname_len: u32,
+    eq_len: u32,
+}
+
 struct TempAttributeData<'input> {
     prefix: &'input str,
     local: &'input str,
     value: StringStorage<'input>,
     range: Range<usize>,
     #[allow(unused)] // only used for feature "positions"
-    qname_len: u16,
+    qname_len: u32,
     #[allow(unused)] // only used for feature "positions"
-    eq_len: u8,
+    eq_len: u32,
 }
 
 impl<'input> Document<'input> {
     /// Parses the input XML string.
     ///
-    /// We do not support `&[u8]` or `Reader` because the input must be an already allocated
-    /// UTF-8 string.
+    /// We do not support `Reader` because the input must be an already allocated string.
+    /// Use [`Document::parse_bytes`] to parse UTF-8 bytes.
     ///
     /// This is a shorthand for `Document::parse_with_options(data, ParsingOptions::default())`.
     ///
+    /// A `&String` or a `&Cow<str>` can be passed as is. Either way, the document borrows
+    /// the text, so an owned one must outlive the document.
+    /// Use [`OwnedDocument`](crate::OwnedDocument) when the text has to be owned.
+    ///
     /// # Examples
     ///
     /// ```
@@ -374,14 +912,14 @@ impl<'input> Document<'input> {
     /// assert_eq!(doc.descendants().count(), 2); // root node + `e` element node
     /// ```
     #[inline]
-    pub fn parse(text: &str) -> Result<Document> {
+    pub fn parse(text: &str) -> Result<Document<'_>> {
         Self::parse_with_options(text, ParsingOptions::default())
     }
 
     /// Parses the input XML string using to selected options.
     ///
-    /// We do not support `&[u8]` or `Reader` because the input must be an already allocated
-    /// UTF-8 string.
+    /// We do not support `Reader` because the input must be an already allocated string.
+    /// Use [`Document::parse_bytes`] to parse UTF-8 bytes.
     ///
     /// # Examples
     ///
@@ -391,11 +929,207 @@ impl<'input> Document<'input> {
     /// assert_eq!(doc.descendants().count(), 2); // root node + `e` element node
     /// ```
     #[inline]
-    pub fn parse_with_options(text: &str, opt: ParsingOptions) -> Result<Document> {
-        parse(text, opt)
+    pub fn parse_with_options(text: &str, opt: ParsingOptions) -> Result<Document<'_>> {
+        parse(text, opt, Mode::Document)
+    }
+
+    /// Parses the input XML bytes using to selected options.
+    ///
+    /// Only UTF-8 is supported. So unlike [`Document::parse_with_options`],
+    /// checks that the input is a valid UTF-8 string and that the XML declaration
+    /// doesn't specify a different encoding. `US-ASCII` is allowed as well,
+    /// since it's a subset of UTF-8. Input starting with a UTF-16 or UTF-32
+    /// byte order mark is rejected with [`Error::UnsupportedEncoding`].
+    ///
+    /// The input is not copied, so the document still borrows it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opt = roxmltree::ParsingOptions::default();
+    /// let data = b"<?xml version='1.0' encoding='UTF-8'?><e/>";
+    /// let doc = roxmltree::Document::parse_bytes(data, opt).unwrap();
+    /// assert_eq!(doc.descendants().count(), 2);
+    ///
+    /// let data = b"<?xml version='1.0' encoding='ISO-8859-1'?><e/>";
+    /// assert!(roxmltree::Document::parse_bytes(data, opt).is_err());
+    /// ```
+    pub fn parse_bytes(data: &[u8], opt: ParsingOptions) -> Result<Document<'_>> {
+        // UTF-16 and UTF-32 input is not a valid UTF-8 anyway,
+        // but a decoding error would not explain what went wrong.
+        if let Some(name) = detect_non_utf8_bom(data) {
+            return Err(Error::UnsupportedEncoding(
+                name.to_string(),
+                TextPos::new(1, 1),
+            ));
+        }
+
+        let text = core::str::from_utf8(data).map_err(|e| Error::InvalidUtf8(e.valid_up_to()))?;
+
+        let leading_whitespace = opt.allow_leading_whitespace_before_declaration;
+        if let Some(encoding) = tokenizer::parse_encoding(text, leading_whitespace)? {
+            let name = encoding.as_str();
+            if !name.eq_ignore_ascii_case("UTF-8") && !name.eq_ignore_ascii_case("US-ASCII") {
+                let pos = Stream::new(text).gen_text_pos_from(encoding.range().start);
+                return Err(Error::UnsupportedEncoding(name.to_string(), pos));
+            }
+        }
+
+        parse(text, opt, Mode::Document)
+    }
+
+    /// Parses the input XML string, recovering from errors when possible.
+    ///
+    /// Unlike [`Document::parse_with_options`], returns a partial document
+    /// along with all the occurred errors.
+    ///
+    /// Only mismatched close tags are recovered from: such a tag simply closes
+    /// the current element. On any other error, parsing stops and the document
+    /// will contain everything parsed before the error.
+    ///
+    /// Still returns an error when the document doesn't have any elements:
+    /// either the one that stopped parsing or [`Error::NoRootNode`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opt = roxmltree::ParsingOptions::default();
+    /// let (doc, errors) = roxmltree::Document::parse_lossy("<a><b></c></a>", opt).unwrap();
+    /// assert_eq!(doc.root_element().descendants().count(), 2);
+    /// assert_eq!(errors.len(), 1);
+    /// ```
+    pub fn parse_lossy(text: &str, opt: ParsingOptions) -> Result<(Document<'_>, Vec<Error>)> {
+        let (doc, errors, _) = parse_impl(text, opt, Mode::Lossy, None)?;
+        Ok((doc, errors))
+    }
+
+    /// Parses the input XML string and reports suspicious, but well-formed constructs.
+    ///
+    /// Succeeds and fails exactly like [`Document::parse_with_options`].
+    /// See [`DiagnosticCode`] for the list of reported constructs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::{DiagnosticCode, Document, ParsingOptions, TextPos};
+    ///
+    /// let opt = ParsingOptions::default();
+    /// let text = "<e xmlns:n='http://n'><n:a xmlns:n='http://n' b=''/></e>";
+    /// let (_, diagnostics) = Document::parse_with_diagnostics(text, opt).unwrap();
+    /// assert_eq!(diagnostics.len(), 2);
+    /// assert_eq!(diagnostics[0].code(), DiagnosticCode::RedundantNamespace);
+    /// assert_eq!(diagnostics[0].pos(), TextPos::new(1, 28));
+    /// assert_eq!(diagnostics[1].code(), DiagnosticCode::EmptyAttributeValue);
+    /// ```
+    pub fn parse_with_diagnostics(
+        text: &str,
+        opt: ParsingOptions,
+    ) -> Result<(Document<'_>, Vec<Diagnostic>)> {
+        let (doc, errors, diagnostics) = parse_impl(text, opt, Mode::Diagnostics, None)?;
+        debug_assert!(errors.is_empty());
+        Ok((doc, diagnostics))
+    }
+
+    /// Parses the input XML fragment using to selected options.
+    ///
+    /// Unlike [`Document::parse_with_options`], allows multiple top-level elements.
+    /// The document still must have at least one element.
+    ///
+    /// All top-level elements are children of the root node
+    /// and can be accessed via [`Document::root_elements`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let opt = roxmltree::ParsingOptions::default();
+    /// let doc = roxmltree::Document::parse_fragment("<li>a</li><li>b</li>", opt).unwrap();
+    /// assert_eq!(doc.root_elements().count(), 2);
+    /// assert_eq!(doc.root_element().text(), Some("a"));
+    /// ```
+    #[inline]
+    pub fn parse_fragment(text: &str, opt: ParsingOptions) -> Result<Document<'_>> {
+        parse(text, opt, Mode::Fragment)
+    }
+
+    /// Parses the input XML string using to selected options and reports the progress.
+    ///
+    /// The callback is invoked periodically, every few thousand nodes,
+    /// with the current position in bytes in the input text.
+    /// Returning [`ControlFlow::Break`] cancels parsing with [`Error::Cancelled`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use core::ops::ControlFlow;
+    ///
+    /// let text = format!("<e>{}</e>", "<a/>".repeat(10_000));
+    /// let opt = roxmltree::ParsingOptions::default();
+    ///
+    /// let mut last_pos = 0;
+    /// let doc = roxmltree::Document::parse_with_callback(&text, opt, |pos| {
+    ///     last_pos = pos;
+    ///     ControlFlow::Continue(())
+    /// });
+    /// assert!(doc.is_ok());
+    /// assert!(last_pos > 0 && last_pos < text.len());
+    ///
+    /// let doc = roxmltree::Document::parse_with_callback(&text, opt, |_| ControlFlow::Break(()));
+    /// assert_eq!(doc.unwrap_err(), roxmltree::Error::Cancelled);
+    /// ```
+    pub fn parse_with_callback<F>(
+        text: &str,
+        opt: ParsingOptions,
+        mut callback: F,
+    ) -> Result<Document<'_>>
+    where
+        F: FnMut(usize) -> ControlFlow<()>,
+    {
+        let (doc, errors, _) = parse_impl(text, opt, Mode::Document, Some(&mut callback))?;
+        debug_assert!(errors.is_empty());
+        Ok(doc)
+    }
+}
+
+/// Returns the encoding name of a UTF-16 or UTF-32 byte order mark.
+fn detect_non_utf8_bom(data: &[u8]) -> Option<&'static str> {
+    // UTF-32LE must be checked first, since its BOM starts with the UTF-16LE one.
+    if data.starts_with(&[0xFF, 0xFE, 0x00, 0x00]) {
+        Some("UTF-32LE")
+    } else if data.starts_with(&[0x00, 0x00, 0xFE, 0xFF]) {
+        Some("UTF-32BE")
+    } else if data.starts_with(&[0xFF, 0xFE]) {
+        Some("UTF-16LE")
+    } else if data.starts_with(&[0xFE, 0xFF]) {
+        Some("UTF-16BE")
+    } else {
+        None
     }
 }
 
+/// Checks that the input XML string is well-formed, without building a tree.
+///
+/// Performs the same checks as [`Document::parse_with_options`],
+/// like namespaces resolving, duplicated attributes detection and close tags matching,
+/// but drops nodes as soon as they are no longer needed.
+/// Which makes it faster and way more memory efficient.
+///
+/// Dropped nodes, attributes and namespaces are still counted by the limits,
+/// like [`ParsingOptions::nodes_limit`] and [`ParsingOptions::memory_limit`],
+/// so an input is rejected by `validate` only when it's rejected by the parser.
+///
+/// # Examples
+///
+/// ```
+/// let opt = roxmltree::ParsingOptions::default();
+/// assert!(roxmltree::validate("<e><a/></e>", opt).is_ok());
+/// assert!(roxmltree::validate("<e a='1' a='2'/>", opt).is_err());
+/// ```
+pub fn validate(text: &str, opt: ParsingOptions) -> Result<()> {
+    let (_, errors, _) = parse_impl(text, opt, Mode::Validate, None)?;
+    debug_assert!(errors.is_empty());
+    Ok(())
+}
+
 struct Entity<'input> {
     name: &'input str,
     value: StrSpan<'input>,
@@ -424,11 +1158,12 @@ impl<'input> TagNameSpan<'input> {
 /// An entity loop detector.
 ///
 /// Limits:
-/// - Entities depth is 10.
-/// - Maximum number of entity references per entity reference is 255.
+/// - Entities depth is `ParsingOptions::max_entity_depth`, 10 by default.
+/// - Maximum number of entity references per entity reference is
+///   `ParsingOptions::max_entity_references`, 255 by default.
 ///
 /// Basically, if a text or an attribute has an entity reference and this reference
-/// has more than 10 nested references - this is an error.
+/// has more than `max_entity_depth` nested references - this is an error.
 ///
 /// This is useful for simple loops like:
 ///
@@ -438,7 +1173,7 @@ impl<'input> TagNameSpan<'input> {
 /// ```
 ///
 /// And, if a text or an attribute has an entity reference and it references more
-/// than 255 references - this is an error.
+/// than `max_entity_references` references - this is an error.
 ///
 /// This is useful for cases like billion laughs attack, where depth can be pretty small,
 /// but the number of references is exponentially increasing:
@@ -450,18 +1185,28 @@ impl<'input> TagNameSpan<'input> {
 /// <!ENTITY lol3 "&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;&lol2;">
 /// <!ENTITY lol4 "&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;&lol3;">
 /// ```
-#[derive(Default)]
 struct LoopDetector {
     /// References depth.
-    depth: u8,
+    depth: u32,
     /// Number of references resolved by the root reference.
-    references: u8,
+    references: u32,
+    max_depth: u32,
+    max_references: u32,
 }
 
 impl LoopDetector {
+    fn new(opt: &ParsingOptions) -> Self {
+        LoopDetector {
+            depth: 0,
+            references: 0,
+            max_depth: opt.max_entity_depth,
+            max_references: opt.max_entity_references,
+        }
+    }
+
     #[inline]
     fn inc_depth(&mut self, stream: &Stream) -> Result<()> {
-        if self.depth < 10 {
+        if self.depth < self.max_depth {
             self.depth += 1;
             Ok(())
         } else {
@@ -487,7 +1232,7 @@ impl LoopDetector {
             // Allow infinite amount of references at zero depth.
             Ok(())
         } else {
-            if self.references == core::u8::MAX {
+            if self.references >= self.max_references {
                 return Err(Error::EntityReferenceLoop(stream.gen_text_pos()));
             }
 
@@ -504,61 +1249,225 @@ struct Context<'input> {
     awaiting_subtree: Vec<NodeId>,
     parent_prefixes: Vec<&'input str>,
     entities: Vec<Entity<'input>>,
+    // Names of general entities already added to the document.
+    general_entities: BTreeSet<&'input str>,
+    // Elements already declared in the DTD, as prefix and local name pairs.
+    declared_elements: BTreeSet<(&'input str, &'input str)>,
+    // Attribute defaults grouped by element's prefix and local name.
+    attribute_defaults: BTreeMap<(&'input str, &'input str), Vec<AttributeDefault<'input>>>,
+    // The number of allocated string bytes. Used by the memory limit.
+    owned_bytes: usize,
+    // Recoverable errors. Set only in the lossy mode.
+    errors: Option<Vec<Error>>,
+    // Set only by `parse_with_diagnostics`.
+    // Positions are stored as byte offsets and resolved once parsing is done.
+    diagnostics: Option<Vec<(DiagnosticCode, String, usize)>>,
+    // Drop nodes as soon as they are no longer needed. Used by `validate`.
+    discard: bool,
+    // The number of dropped nodes, attributes and namespace references.
+    // Still counted by the limits, so `validate` fails on the same input as parsing.
+    discarded_nodes: usize,
+    discarded_attributes: usize,
+    discarded_namespaces: usize,
     after_text: bool,
+    // The range of the text with the entity reference that is currently being expanded.
+    // Nodes produced by entities use it instead of the entity definition range,
+    // so node ranges are always in the document order.
+    entity_text_range: Option<Range<usize>>,
     parent_id: NodeId,
     tag_name: TagNameSpan<'input>,
     loop_detector: LoopDetector,
     doc: Document<'input>,
 }
 
-impl<'input> Context<'input> {
-    fn append_node(&mut self, kind: NodeKind<'input>, range: Range<usize>) -> Result<NodeId> {
-        if self.doc.nodes.len() >= self.opt.nodes_limit as usize {
-            return Err(Error::NodesLimitReached);
-        }
-
+impl<'input> Document<'input> {
+    /// Appends a new node as the last child of `parent_id`.
+    ///
+    /// `awaiting_subtree` contains nodes which `next_subtree` is not known yet.
+    /// Shared by the parser and the `DocumentBuilder`.
+    pub(crate) fn append_node(
+        &mut self,
+        parent_id: NodeId,
+        kind: NodeKind<'input>,
+        range: Range<usize>,
+        awaiting_subtree: &mut Vec<NodeId>,
+    ) -> NodeId {
         #[cfg(not(feature = "positions"))]
         let _ = range;
 
-        let new_child_id = NodeId::from(self.doc.nodes.len());
+        let new_child_id = NodeId::from(self.nodes.len());
 
         let appending_element = matches!(kind, NodeKind::Element { .. });
-        self.doc.nodes.push(NodeData {
-            parent: Some(self.parent_id),
+        self.nodes.push(NodeData {
+            parent: Some(parent_id),
             prev_sibling: None,
             next_subtree: None,
             last_child: None,
             kind,
             #[cfg(feature = "positions")]
             range,
+            #[cfg(feature = "line_positions")]
+            text_pos: TextPos::new(1, 1),
         });
 
-        let last_child_id = self.doc.nodes[self.parent_id.get_usize()].last_child;
-        self.doc.nodes[new_child_id.get_usize()].prev_sibling = last_child_id;
-        self.doc.nodes[self.parent_id.get_usize()].last_child = Some(new_child_id);
+        let last_child_id = self.nodes[parent_id.get_usize()].last_child;
+        self.nodes[new_child_id.get_usize()].prev_sibling = last_child_id;
+        self.nodes[parent_id.get_usize()].last_child = Some(new_child_id);
+
+        for id in awaiting_subtree.iter() {
+            self.nodes[id.get_usize()].next_subtree = Some(new_child_id);
+        }
+        awaiting_subtree.clear();
+
+        if !appending_element {
+            awaiting_subtree.push(NodeId::from(self.nodes.len() - 1));
+        }
+
+        new_child_id
+    }
+
+    /// Resolves namespaces of a new child of `parent_id`.
+    ///
+    /// Namespaces declared on the new element are stored starting from `namespace_start_idx`.
+    /// Parent's namespaces that were not redeclared will be appended after them.
+    pub(crate) fn resolve_namespaces(
+        &mut self,
+        parent_id: NodeId,
+        namespace_start_idx: usize,
+    ) -> Result<ShortRange> {
+        if let NodeKind::Element { ref namespaces, .. } = self.nodes[parent_id.get_usize()].kind {
+            let parent_ns = *namespaces;
+            if namespace_start_idx == self.namespaces.tree_order.len() {
+                return Ok(parent_ns);
+            }
+
+            for i in parent_ns.to_urange() {
+                if !self.namespaces.exists(
+                    namespace_start_idx,
+                    self.namespaces.get(self.namespaces.tree_order[i]).name,
+                ) {
+                    self.namespaces.push_ref(i);
+                }
+            }
+        }
 
-        for id in &self.awaiting_subtree {
-            self.doc.nodes[id.get_usize()].next_subtree = Some(new_child_id);
+        check_namespaces_limit(self.namespaces.tree_order.len())?;
+
+        Ok((namespace_start_idx..self.namespaces.tree_order.len()).into())
+    }
+}
+
+impl<'input> Context<'input> {
+    fn append_node(&mut self, kind: NodeKind<'input>, range: Range<usize>) -> Result<NodeId> {
+        check_nodes_limit(
+            self.doc.nodes.len(),
+            self.discarded_nodes,
+            self.opt.nodes_limit,
+        )?;
+
+        if let NodeKind::Text(ref text, _) | NodeKind::Comment(ref text) = kind {
+            self.add_owned_bytes(text);
+        }
+
+        // Leaf nodes are never referenced, so there is no need to store them.
+        if self.discard && !matches!(kind, NodeKind::Element { .. }) {
+            self.discarded_nodes += 1;
+            self.check_memory_limit()?;
+            return Ok(self.parent_id);
+        }
+
+        let range = self.entity_text_range.clone().unwrap_or(range);
+        let id = self
+            .doc
+            .append_node(self.parent_id, kind, range, &mut self.awaiting_subtree);
+        self.check_memory_limit()?;
+        Ok(id)
+    }
+
+    #[inline]
+    fn add_owned_bytes(&mut self, text: &StringStorage) {
+        if let StringStorage::Owned(ref text) = text {
+            self.owned_bytes += text.len();
         }
-        self.awaiting_subtree.clear();
+    }
 
-        if !appending_element {
-            self.awaiting_subtree
-                .push(NodeId::from(self.doc.nodes.len() - 1));
+    fn check_memory_limit(&self) -> Result<()> {
+        if let Some(limit) = self.opt.memory_limit {
+            // Saturates on overflow, since the limit is reached anyway.
+            let nodes = self.doc.nodes.len().saturating_add(self.discarded_nodes);
+            let attributes = self
+                .doc
+                .attributes
+                .len()
+                .saturating_add(self.discarded_attributes);
+            let used = nodes
+                .saturating_mul(core::mem::size_of::<NodeData>())
+                .saturating_add(attributes.saturating_mul(core::mem::size_of::<AttributeData>()))
+                .saturating_add(self.owned_bytes);
+            if used > limit {
+                return Err(Error::MemoryLimitReached);
+            }
         }
 
-        Ok(new_child_id)
+        Ok(())
     }
 
     fn err_pos_at(&self, pos: usize) -> TextPos {
         self.doc.text_pos_at(pos)
     }
+
+    /// Reports a diagnostic, when requested.
+    ///
+    /// The message is created lazily, since diagnostics are disabled by default.
+    fn diagnose(&mut self, code: DiagnosticCode, pos: usize, message: impl FnOnce() -> String) {
+        if let Some(ref mut diagnostics) = self.diagnostics {
+            diagnostics.push((code, message(), pos));
+        }
+    }
 }
 
-fn parse(text: &str, opt: ParsingOptions) -> Result<Document> {
-    // Trying to guess rough nodes and attributes amount.
-    let nodes_capacity = text.bytes().filter(|c| *c == b'<').count();
-    let attributes_capacity = text.bytes().filter(|c| *c == b'=').count();
+fn parse(text: &str, opt: ParsingOptions, mode: Mode) -> Result<Document<'_>> {
+    let (doc, errors, _) = parse_impl(text, opt, mode, None)?;
+    debug_assert!(errors.is_empty());
+    Ok(doc)
+}
+
+/// Parsing modes that are not a part of `ParsingOptions`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// A single root element is required.
+    Document,
+    /// Multiple root elements are allowed.
+    Fragment,
+    /// Recoverable errors are collected instead of being returned.
+    Lossy,
+    /// Nodes are dropped as soon as they are no longer needed.
+    Validate,
+    /// Suspicious, but well-formed constructs are reported.
+    Diagnostics,
+}
+
+fn parse_impl<'input>(
+    text: &'input str,
+    opt: ParsingOptions,
+    mode: Mode,
+    progress: Option<&mut dyn FnMut(usize) -> ControlFlow<()>>,
+) -> Result<(Document<'input>, Vec<Error>, Vec<Diagnostic>)> {
+    let lossy = mode == Mode::Lossy;
+    let discard = mode == Mode::Validate;
+
+    // Trying to guess rough nodes and attributes amount, unless set explicitly.
+    // Not needed when nodes are dropped anyway.
+    let (nodes_capacity, attributes_capacity) = if discard {
+        (0, 0)
+    } else {
+        (
+            opt.reserve_nodes
+                .unwrap_or_else(|| text.bytes().filter(|c| *c == b'<').count()),
+            opt.reserve_attributes
+                .unwrap_or_else(|| text.bytes().filter(|c| *c == b'=').count()),
+        )
+    };
 
     // Init document.
     let mut doc = Document {
@@ -566,6 +1475,13 @@ fn parse(text: &str, opt: ParsingOptions) -> Result<Document> {
         nodes: Vec::with_capacity(nodes_capacity),
         attributes: Vec::with_capacity(attributes_capacity),
         namespaces: Namespaces::default(),
+        #[cfg(feature = "intern_names")]
+        names: Default::default(),
+        entities: Vec::new(),
+        element_content: BTreeSet::new(),
+        standalone: None,
+        external_subset: false,
+        trailing_content: None,
     };
 
     // Add a root node.
@@ -577,6 +1493,8 @@ fn parse(text: &str, opt: ParsingOptions) -> Result<Document> {
         kind: NodeKind::Root,
         #[cfg(feature = "positions")]
         range: 0..text.len(),
+        #[cfg(feature = "line_positions")]
+        text_pos: TextPos::new(1, 1),
     });
 
     doc.namespaces
@@ -587,51 +1505,329 @@ fn parse(text: &str, opt: ParsingOptions) -> Result<Document> {
         namespace_start_idx: 1,
         current_attributes: Vec::with_capacity(16),
         entities: Vec::new(),
+        general_entities: BTreeSet::new(),
+        declared_elements: BTreeSet::new(),
+        attribute_defaults: BTreeMap::new(),
+        owned_bytes: 0,
+        errors: if lossy { Some(Vec::new()) } else { None },
+        diagnostics: if mode == Mode::Diagnostics {
+            Some(Vec::new())
+        } else {
+            None
+        },
+        discard,
+        discarded_nodes: 0,
+        discarded_attributes: 0,
+        discarded_namespaces: 0,
         awaiting_subtree: Vec::new(),
         parent_prefixes: Vec::new(),
         after_text: false,
+        entity_text_range: None,
         parent_id: NodeId::new(0),
         tag_name: TagNameSpan::new_null(),
-        loop_detector: LoopDetector::default(),
+        loop_detector: LoopDetector::new(&opt),
         doc,
     };
     ctx.parent_prefixes.push("");
 
-    tokenizer::parse(text, opt.allow_dtd, &mut ctx)?;
+    let fragment = mode == Mode::Fragment;
+    let res = match progress {
+        Some(callback) => {
+            let mut events = ProgressEvents {
+                ctx: &mut ctx,
+                callback,
+                next_report: PROGRESS_INTERVAL,
+            };
+            tokenizer::parse(text, opt.allow_dtd, fragment, &mut events)
+        }
+        None => tokenizer::parse(text, opt.allow_dtd, fragment, &mut ctx),
+    };
+
+    let mut errors = ctx.errors.unwrap_or_default();
+    let diagnostics = resolve_diagnostics(text, ctx.diagnostics.unwrap_or_default());
+    let stopped = res.is_err();
+    let trailing_content = match res {
+        Ok(pos) => pos,
+        Err(error) => {
+            if !lossy {
+                return Err(error);
+            }
+
+            // Stop on a non-recoverable error. Open elements will simply stay open,
+            // which is fine, since there are no nodes after them.
+            errors.push(error);
+            None
+        }
+    };
 
     let mut doc = ctx.doc;
+    doc.trailing_content = trailing_content;
     if !doc.root().children().any(|n| n.is_element()) {
-        return Err(Error::NoRootNode);
+        // The error that stopped parsing is more useful than a missing root.
+        let error = if stopped { errors.pop() } else { None };
+        return Err(error.unwrap_or(Error::NoRootNode));
     }
 
     if ctx.parent_prefixes.len() > 1 {
-        return Err(Error::UnclosedRootNode);
+        if !lossy {
+            return Err(Error::UnclosedRootNode);
+        }
+
+        // Elements are always unclosed after a non-recoverable error.
+        if !stopped {
+            errors.push(Error::UnclosedRootNode);
+        }
+    }
+
+    if opt.trim_whitespace {
+        trim_whitespace(&mut doc);
     }
 
+    #[cfg(feature = "line_positions")]
+    calc_text_positions(&mut doc);
+
     doc.nodes.shrink_to_fit();
     doc.attributes.shrink_to_fit();
     doc.namespaces.shrink_to_fit();
+    #[cfg(feature = "intern_names")]
+    doc.names.shrink_to_fit();
+    doc.entities.shrink_to_fit();
 
-    Ok(doc)
+    Ok((doc, errors, diagnostics))
+}
+
+// A line index is built only once, since calculating each position from scratch is O(n).
+fn resolve_diagnostics(
+    text: &str,
+    diagnostics: Vec<(DiagnosticCode, String, usize)>,
+) -> Vec<Diagnostic> {
+    if diagnostics.is_empty() {
+        return Vec::new();
+    }
+
+    let index = LineIndex::new(text);
+    diagnostics
+        .into_iter()
+        .map(|(code, message, pos)| Diagnostic {
+            code,
+            message,
+            pos: index.pos_at(pos),
+        })
+        .collect()
+}
+
+/// Calculates text positions of all nodes in a single pass.
+#[cfg(feature = "line_positions")]
+fn calc_text_positions(doc: &mut Document) {
+    let text = doc.text;
+    let mut pos = 0;
+    let mut row = 1;
+    let mut col = 1;
+    for node in &mut doc.nodes {
+        let start = node.range.start;
+        // Nodes are stored in the document order, so this should never happen.
+        // But in case it does, simply start from the beginning.
+        if start < pos {
+            pos = 0;
+            row = 1;
+            col = 1;
+        }
+
+        for c in text[pos..start].chars() {
+            if c == '\n' {
+                row += 1;
+                col = 1;
+            } else {
+                col += 1;
+            }
+        }
+        pos = start;
+
+        node.text_pos = TextPos::new(row, col);
+    }
+}
+
+/// Removes whitespace-only text nodes that are not a part of a mixed content.
+///
+/// Nodes are stored in a flat array, so we have to remap all the links afterwards.
+fn trim_whitespace(doc: &mut Document) {
+    let is_whitespace = |text: &str| text.bytes().all(|c| c.is_xml_space());
+
+    let len = doc.nodes.len();
+    let mut has_elements = vec![false; len];
+    let mut has_text = vec![false; len];
+    for node in &doc.nodes {
+        if let Some(parent) = node.parent {
+            match node.kind {
+                NodeKind::Element { .. } => has_elements[parent.get_usize()] = true,
+                NodeKind::Text(ref text, _) if !is_whitespace(text) => {
+                    has_text[parent.get_usize()] = true
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Parents always precede their children, so `xml:space` can be inherited in one pass.
+    let mut preserve = vec![false; len];
+    for idx in 0..len {
+        let node = doc.get_node(NodeId::new(idx as u32)).unwrap();
+        preserve[idx] = match node.attribute((NS_XML_URI, "space")) {
+            Some("preserve") => true,
+            Some("default") => false,
+            _ => node.d.parent.is_some_and(|p| preserve[p.get_usize()]),
+        };
+    }
+
+    let removed: Vec<bool> = doc
+        .nodes
+        .iter()
+        .map(|node| match (&node.kind, node.parent) {
+            (NodeKind::Text(ref text, _), Some(parent)) => {
+                let parent = parent.get_usize();
+                has_elements[parent]
+                    && !has_text[parent]
+                    && !preserve[parent]
+                    && is_whitespace(text)
+            }
+            _ => false,
+        })
+        .collect();
+
+    if !removed.contains(&true) {
+        return;
+    }
+
+    // Links to removed nodes are replaced with links to the nearest kept node.
+    let is_removed = |id: NodeId| removed[id.get_usize()];
+
+    let mut prev_sibling: Vec<Option<NodeId>> = Vec::with_capacity(len);
+    for node in &doc.nodes {
+        // A previous sibling always precedes the node.
+        let id = match node.prev_sibling {
+            Some(id) if is_removed(id) => prev_sibling[id.get_usize()],
+            id => id,
+        };
+        prev_sibling.push(id);
+    }
+
+    let mut next_subtree: Vec<Option<NodeId>> = vec![None; len];
+    for (idx, node) in doc.nodes.iter().enumerate().rev() {
+        // A next subtree always follows the node.
+        next_subtree[idx] = match node.next_subtree {
+            Some(id) if is_removed(id) => next_subtree[id.get_usize()],
+            id => id,
+        };
+    }
+
+    let mut new_ids = Vec::with_capacity(len);
+    let mut count = 0;
+    for is_removed in &removed {
+        new_ids.push(NodeId::from(count));
+        if !is_removed {
+            count += 1;
+        }
+    }
+
+    let map = |id: Option<NodeId>| id.map(|id| new_ids[id.get_usize()]);
+
+    let nodes = core::mem::take(&mut doc.nodes);
+    doc.nodes.reserve_exact(count);
+    for (idx, mut node) in nodes.into_iter().enumerate() {
+        if removed[idx] {
+            continue;
+        }
+
+        node.last_child = match node.last_child {
+            Some(id) if is_removed(id) => prev_sibling[id.get_usize()],
+            id => id,
+        };
+
+        node.parent = map(node.parent);
+        node.prev_sibling = map(prev_sibling[idx]);
+        node.next_subtree = map(next_subtree[idx]);
+        node.last_child = map(node.last_child);
+        doc.nodes.push(node);
+    }
 }
 
 impl<'input> tokenizer::XmlEvents<'input> for Context<'input> {
     fn token(&mut self, token: tokenizer::Token<'input>) -> Result<()> {
         match token {
             tokenizer::Token::ProcessingInstruction(target, value, range) => {
+                if self.diagnostics.is_some()
+                    && target
+                        .as_bytes()
+                        .get(..3)
+                        .is_some_and(|p| p.eq_ignore_ascii_case(b"xml"))
+                    && target != "xml-stylesheet"
+                    && target != "xml-model"
+                {
+                    self.diagnose(DiagnosticCode::ReservedPITarget, range.start, || {
+                        alloc::format!("processing instruction target '{}' is reserved", target)
+                    });
+                }
+
                 let pi = NodeKind::PI(PI { target, value });
                 self.append_node(pi, range)?;
                 self.after_text = false;
             }
             tokenizer::Token::Comment(text, range) => {
                 self.append_node(NodeKind::Comment(StringStorage::Borrowed(text)), range)?;
-                self.after_text = false;
+                self.after_text &= self.opt.merge_text_across_comments;
             }
-            tokenizer::Token::EntityDeclaration(name, definition) => {
+            tokenizer::Token::EntityDeclaration(name, definition, is_general) => {
                 self.entities.push(Entity {
                     name,
                     value: definition,
                 });
+
+                // Only the first declaration is binding.
+                if is_general && self.general_entities.insert(name) {
+                    self.doc.entities.push((name, Some(definition.as_str())));
+                }
+            }
+            tokenizer::Token::ExternalEntityDeclaration(name, _) => {
+                if self.general_entities.insert(name) {
+                    self.doc.entities.push((name, None));
+                }
+            }
+            tokenizer::Token::AttributeDefault(
+                element,
+                range,
+                qname_len,
+                eq_len,
+                prefix,
+                local,
+                value,
+            ) => {
+                // Only the first declaration is binding.
+                let defaults = self.attribute_defaults.entry(element).or_default();
+                if !defaults
+                    .iter()
+                    .any(|d| d.prefix == prefix && d.local == local)
+                {
+                    defaults.push(AttributeDefault {
+                        prefix,
+                        local,
+                        value,
+                        range,
+                        qname_len,
+                        eq_len,
+                    });
+                }
+            }
+            tokenizer::Token::Declaration(standalone, _) => {
+                self.doc.standalone = standalone;
+            }
+            tokenizer::Token::DoctypeStart(has_external_subset, _) => {
+                self.doc.external_subset = has_external_subset;
+            }
+            tokenizer::Token::ElementDeclaration(prefix, local, is_element_content, _) => {
+                // Only the first declaration is binding.
+                if self.declared_elements.insert((prefix, local)) && is_element_content {
+                    self.doc.element_content.insert((prefix, local));
+                }
             }
             tokenizer::Token::ElementStart(prefix, local, start) => {
                 if prefix == XMLNS {
@@ -665,19 +1861,111 @@ impl<'input> tokenizer::XmlEvents<'input> for Context<'input> {
 
         Ok(())
     }
+
+    #[inline]
+    fn allow_cdata_end_in_text(&self) -> bool {
+        self.opt.allow_cdata_end_in_text
+    }
+
+    #[inline]
+    fn parse_attribute_defaults(&self) -> bool {
+        self.opt.apply_attlist_defaults
+    }
+
+    #[inline]
+    fn parse_element_declarations(&self) -> bool {
+        self.opt.detect_ignorable_whitespace
+    }
+
+    #[inline]
+    fn allow_trailing_content(&self) -> bool {
+        self.opt.allow_trailing_content
+    }
+
+    #[inline]
+    fn allow_leading_whitespace_before_declaration(&self) -> bool {
+        self.opt.allow_leading_whitespace_before_declaration
+    }
+}
+
+/// How many nodes should be parsed between progress callback calls.
+const PROGRESS_INTERVAL: usize = 4096;
+
+/// Forwards tokens to the parser, invoking the progress callback along the way.
+struct ProgressEvents<'a, 'input> {
+    ctx: &'a mut Context<'input>,
+    callback: &'a mut dyn FnMut(usize) -> ControlFlow<()>,
+    next_report: usize,
+}
+
+impl<'input> tokenizer::XmlEvents<'input> for ProgressEvents<'_, 'input> {
+    fn token(&mut self, token: tokenizer::Token<'input>) -> Result<()> {
+        let pos = match token {
+            tokenizer::Token::ProcessingInstruction(_, _, ref range)
+            | tokenizer::Token::Comment(_, ref range)
+            | tokenizer::Token::AttributeDefault(_, ref range, ..)
+            | tokenizer::Token::Attribute(ref range, ..)
+            | tokenizer::Token::ElementEnd(_, ref range)
+            | tokenizer::Token::Text(_, ref range)
+            | tokenizer::Token::Cdata(_, ref range) => range.start,
+            tokenizer::Token::EntityDeclaration(_, ref span, _) => span.range().start,
+            tokenizer::Token::Declaration(_, start)
+            | tokenizer::Token::DoctypeStart(_, start)
+            | tokenizer::Token::ExternalEntityDeclaration(_, start)
+            | tokenizer::Token::ElementDeclaration(_, _, _, start)
+            | tokenizer::Token::ElementStart(_, _, start) => start,
+        };
+
+        self.ctx.token(token)?;
+
+        if self.ctx.doc.nodes.len() >= self.next_report {
+            self.next_report = self.ctx.doc.nodes.len() + PROGRESS_INTERVAL;
+            if (self.callback)(pos).is_break() {
+                return Err(Error::Cancelled);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn allow_cdata_end_in_text(&self) -> bool {
+        self.ctx.allow_cdata_end_in_text()
+    }
+
+    #[inline]
+    fn parse_attribute_defaults(&self) -> bool {
+        self.ctx.parse_attribute_defaults()
+    }
+
+    #[inline]
+    fn parse_element_declarations(&self) -> bool {
+        self.ctx.parse_element_declarations()
+    }
+
+    #[inline]
+    fn allow_trailing_content(&self) -> bool {
+        self.ctx.allow_trailing_content()
+    }
+
+    #[inline]
+    fn allow_leading_whitespace_before_declaration(&self) -> bool {
+        self.ctx.allow_leading_whitespace_before_declaration()
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
 fn process_attribute<'input>(
     range: Range<usize>,
-    qname_len: u16,
-    eq_len: u8,
+    qname_len: u32,
+    eq_len: u32,
     prefix: &'input str,
     local: &'input str,
     value: StrSpan<'input>,
     ctx: &mut Context<'input>,
 ) -> Result<()> {
     let value = normalize_attribute(value, ctx)?;
+    ctx.add_owned_bytes(&value);
 
     if prefix == XMLNS {
         // The xmlns namespace MUST NOT be declared as the default namespace.
@@ -717,6 +2005,7 @@ fn process_attribute<'input>(
         // Xml namespace should not be added to the namespaces.
         if !is_xml_ns_uri {
             ctx.doc.namespaces.push_ns(Some(local), value)?;
+            check_redundant_namespace(Some(local), range.start, ctx);
         }
     } else if local == XMLNS {
         // The xml namespace MUST NOT be declared as the default namespace.
@@ -732,7 +2021,14 @@ fn process_attribute<'input>(
         }
 
         ctx.doc.namespaces.push_ns(None, value)?;
+        check_redundant_namespace(None, range.start, ctx);
     } else {
+        if value.as_str().is_empty() {
+            ctx.diagnose(DiagnosticCode::EmptyAttributeValue, range.start, || {
+                alloc::format!("attribute '{}' has an empty value", local)
+            });
+        }
+
         ctx.current_attributes.push(TempAttributeData {
             prefix,
             local,
@@ -746,6 +2042,33 @@ fn process_attribute<'input>(
     Ok(())
 }
 
+/// Checks that the just declared namespace is not already declared by the parent
+/// with the same URI.
+fn check_redundant_namespace(prefix: Option<&str>, pos: usize, ctx: &mut Context) {
+    if ctx.diagnostics.is_none() {
+        return;
+    }
+
+    // Namespaces are deduplicated by prefix and URI,
+    // so the same declaration always has the same index.
+    let idx = *ctx.doc.namespaces.tree_order.last().unwrap();
+    let redundant = match ctx.doc.nodes[ctx.parent_id.get_usize()].kind {
+        NodeKind::Element { namespaces, .. } => {
+            ctx.doc.namespaces.tree_order[namespaces.to_urange()].contains(&idx)
+        }
+        _ => false,
+    };
+
+    if redundant {
+        ctx.diagnose(DiagnosticCode::RedundantNamespace, pos, || match prefix {
+            Some(prefix) => {
+                alloc::format!("namespace '{}' is already declared by the parent", prefix)
+            }
+            None => "default namespace is already declared by the parent".to_string(),
+        });
+    }
+}
+
 fn process_element<'input>(
     end_token: tokenizer::ElementEnd<'input>,
     token_range: Range<usize>,
@@ -765,8 +2088,24 @@ fn process_element<'input>(
         }
     }
 
-    let namespaces = ctx.resolve_namespaces();
+    if !matches!(end_token, tokenizer::ElementEnd::Close(..))
+        && ctx.parent_prefixes.len() > ctx.opt.max_depth as usize
+    {
+        return Err(Error::DepthLimitReached(ctx.err_pos_at(ctx.tag_name.pos)));
+    }
+
+    if !matches!(end_token, tokenizer::ElementEnd::Close(..)) && !ctx.attribute_defaults.is_empty()
+    {
+        apply_attribute_defaults(ctx)?;
+    }
+
+    let namespaces = ctx
+        .doc
+        .resolve_namespaces(ctx.parent_id, ctx.namespace_start_idx)?;
     ctx.namespace_start_idx = ctx.doc.namespaces.tree_order.len();
+    if ctx.discarded_namespaces != 0 {
+        check_namespaces_limit(ctx.namespace_start_idx + ctx.discarded_namespaces)?;
+    }
 
     let attributes = resolve_attributes(namespaces, ctx)?;
 
@@ -778,41 +2117,58 @@ fn process_element<'input>(
                 ctx.tag_name.prefix,
                 ctx,
             )?;
+            let tag_name = ctx.doc.new_name(tag_ns_idx, ctx.tag_name.name);
             let new_element_id = ctx.append_node(
                 NodeKind::Element {
-                    tag_name: ExpandedNameIndexed {
-                        namespace_idx: tag_ns_idx,
-                        local_name: ctx.tag_name.name,
-                    },
+                    tag_name,
                     attributes,
                     namespaces,
+                    #[cfg(feature = "intern_names")]
+                    local_name_offset: ctx.doc.text_offset(ctx.tag_name.name),
                 },
                 ctx.tag_name.pos..token_range.end,
             )?;
-            ctx.awaiting_subtree.push(new_element_id);
+
+            if ctx.discard {
+                discard_element(new_element_id, ctx);
+            } else {
+                ctx.awaiting_subtree.push(new_element_id);
+            }
         }
         tokenizer::ElementEnd::Close(prefix, local) => {
-            let parent_node = &mut ctx.doc.nodes[ctx.parent_id.get_usize()];
             // should never panic as we start with the single prefix of the
             // root node and always push another one when changing the parent
             let parent_prefix = *ctx.parent_prefixes.last().unwrap();
 
             #[cfg(feature = "positions")]
             {
-                parent_node.range.end = token_range.end;
+                let range = ctx.entity_text_range.as_ref().unwrap_or(&token_range);
+                ctx.doc.nodes[ctx.parent_id.get_usize()].range.end = range.end;
             }
 
-            if let NodeKind::Element { ref tag_name, .. } = parent_node.kind {
-                if prefix != parent_prefix || local != tag_name.local_name {
-                    return Err(Error::UnexpectedCloseTag(
-                        gen_qname_string(parent_prefix, tag_name.local_name),
+            if let NodeKind::Element { tag_name, .. } =
+                ctx.doc.nodes[ctx.parent_id.get_usize()].kind
+            {
+                let parent_local = tag_name.local_name(&ctx.doc);
+                if prefix != parent_prefix || local != parent_local {
+                    let error = Error::UnexpectedCloseTag(
+                        gen_qname_string(parent_prefix, parent_local),
                         gen_qname_string(prefix, local),
                         ctx.err_pos_at(token_range.start),
-                    ));
+                    );
+
+                    // In the lossy mode, a mismatched close tag simply closes the current element,
+                    // just like the tokenizer does.
+                    match ctx.errors {
+                        Some(ref mut errors) => errors.push(error),
+                        None => return Err(error),
+                    }
                 }
             }
             ctx.awaiting_subtree.push(ctx.parent_id);
 
+            let closed_id = ctx.parent_id;
+            let parent_node = &ctx.doc.nodes[ctx.parent_id.get_usize()];
             if let Some(id) = parent_node.parent {
                 ctx.parent_id = id;
                 ctx.parent_prefixes.pop();
@@ -820,6 +2176,10 @@ fn process_element<'input>(
             } else {
                 unreachable!("should be already checked by the tokenizer");
             }
+
+            if ctx.discard {
+                discard_element(closed_id, ctx);
+            }
         }
         tokenizer::ElementEnd::Open => {
             let tag_ns_idx = get_ns_idx_by_prefix(
@@ -828,14 +2188,14 @@ fn process_element<'input>(
                 ctx.tag_name.prefix,
                 ctx,
             )?;
+            let tag_name = ctx.doc.new_name(tag_ns_idx, ctx.tag_name.name);
             ctx.parent_id = ctx.append_node(
                 NodeKind::Element {
-                    tag_name: ExpandedNameIndexed {
-                        namespace_idx: tag_ns_idx,
-                        local_name: ctx.tag_name.name,
-                    },
+                    tag_name,
                     attributes,
                     namespaces,
+                    #[cfg(feature = "intern_names")]
+                    local_name_offset: ctx.doc.text_offset(ctx.tag_name.name),
                 },
                 ctx.tag_name.pos..token_range.end,
             )?;
@@ -846,31 +2206,116 @@ fn process_element<'input>(
     Ok(())
 }
 
-impl Context<'_> {
-    fn resolve_namespaces(&mut self) -> ShortRange {
-        if let NodeKind::Element { ref namespaces, .. } =
-            self.doc.nodes[self.parent_id.get_usize()].kind
+/// Removes a closed element along with its attributes and namespaces.
+///
+/// Its descendants were already removed, so it's always the last node.
+/// Top-level elements are preserved, since the document must have at least one.
+fn discard_element(id: NodeId, ctx: &mut Context) {
+    let node = &ctx.doc.nodes[id.get_usize()];
+    let parent_id = match node.parent {
+        Some(parent_id) if parent_id.get_usize() != 0 => parent_id,
+        _ => return,
+    };
+
+    if let NodeKind::Element {
+        attributes,
+        namespaces,
+        ..
+    } = node.kind
+    {
+        if attributes.len() != 0 {
+            ctx.doc.attributes.truncate(attributes.start as usize);
+            ctx.discarded_attributes += attributes.len();
+        }
+
+        if let NodeKind::Element {
+            namespaces: parent_namespaces,
+            ..
+        } = ctx.doc.nodes[parent_id.get_usize()].kind
         {
-            let parent_ns = *namespaces;
-            if self.namespace_start_idx == self.doc.namespaces.tree_order.len() {
-                return parent_ns;
+            // Element's own namespaces are stored after the parent ones.
+            if namespaces.to_urange() != parent_namespaces.to_urange() {
+                let tree_order = &mut ctx.doc.namespaces.tree_order;
+                ctx.discarded_namespaces += tree_order.len() - namespaces.start as usize;
+                tree_order.truncate(namespaces.start as usize);
             }
+        }
+    }
 
-            for i in parent_ns.to_urange() {
-                if !self.doc.namespaces.exists(
-                    self.namespace_start_idx,
-                    self.doc
-                        .namespaces
-                        .get(self.doc.namespaces.tree_order[i])
-                        .name,
-                ) {
-                    self.doc.namespaces.push_ref(i);
-                }
-            }
+    ctx.doc.nodes.truncate(id.get_usize());
+    ctx.discarded_nodes += 1;
+    ctx.awaiting_subtree.clear();
+    ctx.namespace_start_idx = ctx.doc.namespaces.tree_order.len();
+}
+
+/// Adds declared default attributes missing on the current element.
+///
+/// Must be called before namespaces resolving, since defaults can declare namespaces as well.
+fn apply_attribute_defaults(ctx: &mut Context) -> Result<()> {
+    let element = (ctx.tag_name.prefix, ctx.tag_name.name);
+    let count = match ctx.attribute_defaults.get(&element) {
+        Some(defaults) => defaults.len(),
+        None => return Ok(()),
+    };
+
+    for i in 0..count {
+        let d = &ctx.attribute_defaults[&element][i];
+        let is_set = if d.prefix == XMLNS {
+            let local = d.local;
+            ctx.doc
+                .namespaces
+                .exists(ctx.namespace_start_idx, Some(local))
+        } else if d.local == XMLNS {
+            ctx.doc.namespaces.exists(ctx.namespace_start_idx, None)
+        } else {
+            ctx.current_attributes
+                .iter()
+                .any(|a| a.prefix == d.prefix && a.local == d.local)
+        };
+
+        if !is_set {
+            let (range, qname_len, eq_len) = (d.range.clone(), d.qname_len, d.eq_len);
+            let (prefix, local, value) = (d.prefix, d.local, d.value);
+            process_attribute(range, qname_len, eq_len, prefix, local, value, ctx)?;
         }
+    }
+
+    Ok(())
+}
+
+/// Checks that attributes can still be indexed by `u32` after adding new ones.
+///
+/// Discarded attributes are counted as well.
+/// Uses a checked addition, since `usize` can be 32 bits wide.
+pub(crate) fn check_attributes_limit(
+    len: usize,
+    discarded: usize,
+    additional: usize,
+) -> Result<()> {
+    let total = len
+        .checked_add(discarded)
+        .and_then(|n| n.checked_add(additional));
+    match total {
+        Some(total) if total < u32::MAX as usize => Ok(()),
+        _ => Err(Error::AttributesLimitReached),
+    }
+}
+
+/// Checks that one more node can be added, counting the discarded ones as well.
+pub(crate) fn check_nodes_limit(len: usize, discarded: usize, limit: u32) -> Result<()> {
+    match len.checked_add(discarded) {
+        Some(total) if total < limit as usize => Ok(()),
+        _ => Err(Error::NodesLimitReached),
+    }
+}
 
-        (self.namespace_start_idx..self.doc.namespaces.tree_order.len()).into()
+/// Checks that namespace ranges can be stored as `u32`.
+pub(crate) fn check_namespaces_limit(len: usize) -> Result<()> {
+    if len > u32::MAX as usize {
+        return Err(Error::NamespacesLimitReached);
     }
+
+    Ok(())
 }
 
 fn resolve_attributes(namespaces: ShortRange, ctx: &mut Context) -> Result<ShortRange> {
@@ -878,14 +2323,21 @@ fn resolve_attributes(namespaces: ShortRange, ctx: &mut Context) -> Result<Short
         return Ok(ShortRange::new(0, 0));
     }
 
-    if ctx.doc.attributes.len() + ctx.current_attributes.len() >= core::u32::MAX as usize {
-        return Err(Error::AttributesLimitReached);
-    }
+    check_attributes_limit(
+        ctx.doc.attributes.len(),
+        ctx.discarded_attributes,
+        ctx.current_attributes.len(),
+    )?;
 
     let start_idx = ctx.doc.attributes.len();
 
-    let current_attributes = core::mem::take(&mut ctx.current_attributes);
-    for attr in current_attributes {
+    let mut current_attributes = core::mem::take(&mut ctx.current_attributes);
+    for i in 0..current_attributes.len() {
+        // Previous attributes are still needed for error reporting,
+        // so we cannot consume them.
+        let value = core::mem::replace(&mut current_attributes[i].value, "".into());
+        let attr = &current_attributes[i];
+
         let namespace_idx = if attr.prefix == NS_XML_PREFIX {
             // The prefix 'xml' is by definition bound to the namespace name
             // http://www.w3.org/XML/1998/namespace. This namespace is added
@@ -899,24 +2351,26 @@ fn resolve_attributes(namespaces: ShortRange, ctx: &mut Context) -> Result<Short
             get_ns_idx_by_prefix(namespaces, attr.range.start, attr.prefix, ctx)?
         };
 
-        let attr_name = ExpandedNameIndexed {
-            namespace_idx,
-            local_name: attr.local,
-        };
+        let attr_name = ctx.doc.new_name(namespace_idx, attr.local);
 
         // Check for duplicated attributes.
-        if ctx.doc.attributes[start_idx..].iter().any(|attr| {
+        if let Some(prev_idx) = ctx.doc.attributes[start_idx..].iter().position(|attr| {
             attr.name.as_expanded_name(&ctx.doc) == attr_name.as_expanded_name(&ctx.doc)
         }) {
             let pos = ctx.err_pos_at(attr.range.start);
-            return Err(Error::DuplicatedAttribute(attr.local.to_string(), pos));
+            let prev_pos = ctx.err_pos_at(current_attributes[prev_idx].range.start);
+            return Err(Error::DuplicatedAttribute(
+                attr.local.to_string(),
+                pos,
+                prev_pos,
+            ));
         }
 
         ctx.doc.attributes.push(AttributeData {
             name: attr_name,
-            value: attr.value,
+            value,
             #[cfg(feature = "positions")]
-            range: attr.range,
+            range: attr.range.clone(),
             #[cfg(feature = "positions")]
             qname_len: attr.qname_len,
             #[cfg(feature = "positions")]
@@ -924,6 +2378,8 @@ fn resolve_attributes(namespaces: ShortRange, ctx: &mut Context) -> Result<Short
         });
     }
 
+    ctx.check_memory_limit()?;
+
     Ok((start_idx..ctx.doc.attributes.len()).into())
 }
 
@@ -939,11 +2395,12 @@ fn process_text<'input>(
         return Ok(());
     }
 
+    let prev_entity_text_range = ctx.entity_text_range.clone();
     let mut text_buffer = TextBuffer::new();
     let mut is_as_is = false; // TODO: explain
     let mut stream = Stream::from_substr(ctx.doc.text, range.clone());
     while !stream.at_end() {
-        match parse_next_chunk(&mut stream, &ctx.entities)? {
+        match parse_next_chunk(&mut stream, &ctx.entities, &ctx.opt)? {
             NextChunk::Byte(c) => {
                 if is_as_is {
                     text_buffer.push_raw(c);
@@ -952,6 +2409,13 @@ fn process_text<'input>(
                     text_buffer.push_from_text(c, stream.at_end());
                 }
             }
+            NextChunk::Char('&') if !ctx.opt.expand_entities => {
+                // Keep it escaped, so it cannot be confused with an unexpanded reference.
+                is_as_is = false;
+                for b in "&amp;".bytes() {
+                    text_buffer.push_raw(b);
+                }
+            }
             NextChunk::Char(c) => {
                 for b in CharToBytes::new(c) {
                     if ctx.loop_detector.depth > 0 {
@@ -964,9 +2428,19 @@ fn process_text<'input>(
                     }
                 }
             }
+            NextChunk::Reference(reference) => {
+                is_as_is = false;
+                for b in reference.bytes() {
+                    text_buffer.push_raw(b);
+                }
+            }
             NextChunk::Text(fragment) => {
                 is_as_is = false;
 
+                // All nodes produced by an entity, as well as the text around the reference,
+                // use the range of the text with the outermost reference.
+                ctx.entity_text_range.get_or_insert_with(|| range.clone());
+
                 if !text_buffer.is_empty() {
                     let storage = StringStorage::new_owned(text_buffer.to_str());
                     append_text(storage, range.clone(), ctx)?;
@@ -994,18 +2468,24 @@ fn process_text<'input>(
         ctx.after_text = true;
     }
 
+    ctx.entity_text_range = prev_entity_text_range;
+
     Ok(())
 }
 
 // While the whole purpose of CDATA is to indicate to an XML library that this text
 // has to be stored as is, carriage return (`\r`) is still has to be replaced with `\n`.
+// And `&` has to be escaped when entities are not expanded, see `expand_entities`.
 fn process_cdata<'input>(
     text: &'input str,
     range: Range<usize>,
     ctx: &mut Context<'input>,
 ) -> Result<()> {
+    let escape_amp = !ctx.opt.expand_entities;
+    let is_special = |b: u8| b == b'\r' || (b == b'&' && escape_amp);
+
     // Add text as is if it has only valid characters.
-    if !text.as_bytes().contains(&b'\r') {
+    if !text.bytes().any(is_special) {
         append_text(StringStorage::Borrowed(text), range, ctx)?;
         ctx.after_text = true;
         return Ok(());
@@ -1014,6 +2494,13 @@ fn process_cdata<'input>(
     let mut text_buffer = TextBuffer::new();
     let count = text.chars().count();
     for (i, c) in text.chars().enumerate() {
+        if c == '&' && escape_amp {
+            for b in "&amp;".bytes() {
+                text_buffer.push_raw(b);
+            }
+            continue;
+        }
+
         for b in CharToBytes::new(c) {
             text_buffer.push_from_text(b, i + 1 == count);
         }
@@ -1032,10 +2519,40 @@ fn append_text<'input>(
     range: Range<usize>,
     ctx: &mut Context<'input>,
 ) -> Result<()> {
+    let from_entity = ctx.entity_text_range.is_some();
+    let range = ctx.entity_text_range.clone().unwrap_or(range);
+
     if ctx.after_text {
+        // The previous text node was dropped, but merging still counts toward the memory limit.
+        if ctx.discard {
+            ctx.owned_bytes += text.as_str().len();
+            return ctx.check_memory_limit();
+        }
+
+        // Skip comments between text nodes. Text and comments are always leaf nodes,
+        // so all trailing comments are siblings of the previous text node.
+        let mut idx = ctx.doc.nodes.len() - 1;
+        if ctx.opt.merge_text_across_comments {
+            while idx > 0 && matches!(ctx.doc.nodes[idx].kind, NodeKind::Comment(_)) {
+                idx -= 1;
+            }
+        }
+
         // Prepend to a previous text node.
-        if let Some(node) = ctx.doc.nodes.last_mut() {
-            if let NodeKind::Text(ref mut prev_text) = node.kind {
+        if let Some(node) = ctx.doc.nodes.get_mut(idx) {
+            #[cfg(feature = "positions")]
+            {
+                // Cover all merged parts.
+                if matches!(node.kind, NodeKind::Text(..)) && range.end > node.range.end {
+                    node.range.end = range.end;
+                }
+            }
+            #[cfg(not(feature = "positions"))]
+            let _ = range;
+
+            if let NodeKind::Text(ref mut prev_text, ref mut is_merged) = node.kind {
+                *is_merged = true;
+
                 let text_str = text.as_str();
                 let prev_text_str = prev_text.as_str();
 
@@ -1043,10 +2560,15 @@ fn append_text<'input>(
                 concat_text.push_str(prev_text_str);
                 concat_text.push_str(text_str);
                 *prev_text = StringStorage::new_owned(concat_text);
+
+                // An approximation, since the previous text will be deallocated
+                // or was borrowed.
+                ctx.owned_bytes += text_str.len();
+                ctx.check_memory_limit()?;
             }
         }
     } else {
-        ctx.append_node(NodeKind::Text(text), range)?;
+        ctx.append_node(NodeKind::Text(text, from_entity), range)?;
     }
 
     Ok(())
@@ -1056,9 +2578,15 @@ enum NextChunk<'a> {
     Byte(u8),
     Char(char),
     Text(StrSpan<'a>),
+    // An unexpanded or an unknown entity reference.
+    Reference(&'a str),
 }
 
-fn parse_next_chunk<'a>(stream: &mut Stream<'a>, entities: &[Entity<'a>]) -> Result<NextChunk<'a>> {
+fn parse_next_chunk<'a>(
+    stream: &mut Stream<'a>,
+    entities: &[Entity<'a>],
+    opt: &ParsingOptions,
+) -> Result<NextChunk<'a>> {
     debug_assert!(!stream.at_end());
 
     // Safe, because we already checked that stream is not at the end.
@@ -1070,14 +2598,20 @@ fn parse_next_chunk<'a>(stream: &mut Stream<'a>, entities: &[Entity<'a>]) -> Res
         let start = stream.pos();
         match stream.try_consume_reference() {
             Some(Reference::Char(ch)) => Ok(NextChunk::Char(ch)),
-            Some(Reference::Entity(name)) => entities
-                .iter()
-                .find(|e| e.name == name)
-                .map(|e| NextChunk::Text(e.value))
-                .ok_or_else(|| {
-                    let pos = stream.gen_text_pos_from(start);
-                    Error::UnknownEntityReference(name.into(), pos)
-                }),
+            Some(Reference::Entity(name)) => match entities.iter().find(|e| e.name == name) {
+                Some(entity) if opt.expand_entities => Ok(NextChunk::Text(entity.value)),
+                Some(_) => Ok(NextChunk::Reference(stream.slice_back(start))),
+                None => match opt.unknown_entity_policy {
+                    UnknownEntityPolicy::Error => {
+                        let pos = stream.gen_text_pos_from(start);
+                        Err(Error::UnknownEntityReference(name.into(), pos))
+                    }
+                    UnknownEntityPolicy::Passthrough => {
+                        Ok(NextChunk::Reference(stream.slice_back(start)))
+                    }
+                    UnknownEntityPolicy::Replace(c) => Ok(NextChunk::Char(c)),
+                },
+            },
             None => {
                 let pos = stream.gen_text_pos_from(start);
                 Err(Error::MalformedEntityReference(pos))
@@ -1094,7 +2628,7 @@ fn normalize_attribute<'input>(
     text: StrSpan<'input>,
     ctx: &mut Context<'input>,
 ) -> Result<StringStorage<'input>> {
-    if is_normalization_required(&text) {
+    if is_normalization_required(&text, ctx.opt.normalize_attribute_whitespace) {
         let mut text_buffer = TextBuffer::new();
         _normalize_attribute(text, &mut text_buffer, ctx)?;
         Ok(StringStorage::new_owned(text_buffer.finish()))
@@ -1104,15 +2638,17 @@ fn normalize_attribute<'input>(
 }
 
 #[inline]
-fn is_normalization_required(text: &StrSpan) -> bool {
+fn is_normalization_required(text: &StrSpan, normalize_whitespace: bool) -> bool {
     // We assume that `&` indicates an entity or a character reference.
     // But in rare cases it can be just an another character.
 
-    fn check(c: u8) -> bool {
-        matches!(c, b'&' | b'\t' | b'\n' | b'\r')
+    if normalize_whitespace {
+        text.as_str()
+            .bytes()
+            .any(|c| matches!(c, b'&' | b'\t' | b'\n' | b'\r'))
+    } else {
+        text.as_str().bytes().any(|c| matches!(c, b'&' | b'\r'))
     }
-
-    text.as_str().bytes().any(check)
 }
 
 fn _normalize_attribute(text: StrSpan, buffer: &mut TextBuffer, ctx: &mut Context) -> Result<()> {
@@ -1123,13 +2659,24 @@ fn _normalize_attribute(text: StrSpan, buffer: &mut TextBuffer, ctx: &mut Contex
 
         if c != b'&' {
             stream.advance(1);
-            buffer.push_from_attr(c, stream.curr_byte().ok());
+            let next = stream.curr_byte().ok();
+            if ctx.opt.normalize_attribute_whitespace {
+                buffer.push_from_attr(c, next);
+            } else {
+                buffer.push_from_attr_preserved(c, next);
+            }
             continue;
         }
 
         // Check for character/entity references.
         let start = stream.pos();
         match stream.try_consume_reference() {
+            Some(Reference::Char('&')) if !ctx.opt.expand_entities => {
+                // Keep it escaped, so it cannot be confused with an unexpanded reference.
+                for b in "&amp;".bytes() {
+                    buffer.push_raw(b);
+                }
+            }
             Some(Reference::Char(ch)) => {
                 for b in CharToBytes::new(ch) {
                     if ctx.loop_detector.depth > 0 {
@@ -1141,7 +2688,11 @@ fn _normalize_attribute(text: StrSpan, buffer: &mut TextBuffer, ctx: &mut Contex
                             ));
                         }
 
-                        buffer.push_from_attr(b, None);
+                        if ctx.opt.normalize_attribute_whitespace {
+                            buffer.push_from_attr(b, None);
+                        } else {
+                            buffer.push_raw(b);
+                        }
                     } else {
                         // Characters not from entity should be added as is.
                         // Not sure why... At least `lxml` produces the same results.
@@ -1150,16 +2701,33 @@ fn _normalize_attribute(text: StrSpan, buffer: &mut TextBuffer, ctx: &mut Contex
                 }
             }
             Some(Reference::Entity(name)) => match ctx.entities.iter().find(|e| e.name == name) {
+                Some(_) if !ctx.opt.expand_entities => {
+                    for b in stream.slice_back(start).bytes() {
+                        buffer.push_raw(b);
+                    }
+                }
                 Some(entity) => {
                     ctx.loop_detector.inc_references(&stream)?;
                     ctx.loop_detector.inc_depth(&stream)?;
                     _normalize_attribute(entity.value, buffer, ctx)?;
                     ctx.loop_detector.dec_depth();
                 }
-                None => {
-                    let pos = stream.gen_text_pos_from(start);
-                    return Err(Error::UnknownEntityReference(name.into(), pos));
-                }
+                None => match ctx.opt.unknown_entity_policy {
+                    UnknownEntityPolicy::Error => {
+                        let pos = stream.gen_text_pos_from(start);
+                        return Err(Error::UnknownEntityReference(name.into(), pos));
+                    }
+                    UnknownEntityPolicy::Passthrough => {
+                        for b in stream.slice_back(start).bytes() {
+                            buffer.push_raw(b);
+                        }
+                    }
+                    UnknownEntityPolicy::Replace(c) => {
+                        for b in CharToBytes::new(c) {
+                            buffer.push_raw(b);
+                        }
+                    }
+                },
             },
             None => {
                 let pos = stream.gen_text_pos_from(start);
@@ -1290,6 +2858,16 @@ impl TextBuffer {
         self.buffer.push(current);
     }
 
+    // Like `push_from_attr`, but only translates line endings.
+    fn push_from_attr_preserved(&mut self, current: u8, next: Option<u8>) {
+        match current {
+            // \r in \r\n should be ignored.
+            b'\r' if next == Some(b'\n') => {}
+            b'\r' => self.buffer.push(b'\n'),
+            _ => self.buffer.push(current),
+        }
+    }
+
     // Translate \r\n and any \r that is not followed by \n into a single \n character.
     //
     // https://www.w3.org/TR/xml/#sec-line-ends
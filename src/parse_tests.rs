@@ -0,0 +1,58 @@
+use crate::parse::{check_attributes_limit, check_namespaces_limit, check_nodes_limit};
+use crate::Error;
+
+// Documents large enough to reach these limits are impractical in tests,
+// so the checks are tested directly.
+
+#[test]
+fn attributes_limit_1() {
+    assert_eq!(check_attributes_limit(0, 0, 16), Ok(()));
+    assert_eq!(check_attributes_limit(u32::MAX as usize - 2, 0, 1), Ok(()));
+    assert_eq!(
+        check_attributes_limit(u32::MAX as usize - 1, 0, 1),
+        Err(Error::AttributesLimitReached)
+    );
+    assert_eq!(
+        check_attributes_limit(u32::MAX as usize - 2, 1, 1),
+        Err(Error::AttributesLimitReached)
+    );
+}
+
+#[test]
+fn attributes_limit_2() {
+    // Must not overflow.
+    assert_eq!(
+        check_attributes_limit(usize::MAX, 0, 1),
+        Err(Error::AttributesLimitReached)
+    );
+    assert_eq!(
+        check_attributes_limit(1, usize::MAX, 0),
+        Err(Error::AttributesLimitReached)
+    );
+}
+
+#[test]
+fn nodes_limit_1() {
+    assert_eq!(check_nodes_limit(1, 1, 3), Ok(()));
+    assert_eq!(check_nodes_limit(2, 1, 3), Err(Error::NodesLimitReached));
+    // Must not overflow.
+    assert_eq!(
+        check_nodes_limit(usize::MAX, 1, u32::MAX),
+        Err(Error::NodesLimitReached)
+    );
+}
+
+#[test]
+fn namespaces_limit_1() {
+    assert_eq!(check_namespaces_limit(1), Ok(()));
+    assert_eq!(check_namespaces_limit(u32::MAX as usize), Ok(()));
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn namespaces_limit_2() {
+    assert_eq!(
+        check_namespaces_limit(u32::MAX as usize + 1),
+        Err(Error::NamespacesLimitReached)
+    );
+}
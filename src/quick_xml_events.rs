@@ -0,0 +1,130 @@
+use alloc::borrow::Cow;
+
+use quick_xml::events::{BytesEnd, BytesPI, BytesStart, BytesText, Event};
+
+use crate::visitor::{Edge, Traverse};
+use crate::{Node, NodeType};
+
+impl<'a, 'input: 'a> Node<'a, 'input> {
+    /// Returns an iterator over [`quick_xml`] events of the node subtree.
+    ///
+    /// Elements are always represented by a `Start` and `End` pair,
+    /// even when they are empty. Namespace declarations are reconstructed:
+    /// the first element declares all the namespaces in scope
+    /// and descendants declare only the ones that differ from their parents.
+    ///
+    /// Since the document is already parsed, entities are expanded
+    /// and CDATA sections are converted to text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use quick_xml::events::Event;
+    ///
+    /// let doc = roxmltree::Document::parse("<e xmlns='http://a'><c>t</c></e>").unwrap();
+    /// let c = doc.root_element().first_child().unwrap();
+    ///
+    /// let mut events = c.quick_xml_events();
+    /// match events.next() {
+    ///     Some(Event::Start(start)) => assert_eq!(&*start, b"c xmlns=\"http://a\""),
+    ///     _ => unreachable!(),
+    /// }
+    /// assert!(matches!(events.next(), Some(Event::Text(_))));
+    /// assert!(matches!(events.next(), Some(Event::End(_))));
+    /// assert!(events.next().is_none());
+    /// ```
+    pub fn quick_xml_events(&self) -> QuickXmlEvents<'a, 'input> {
+        QuickXmlEvents {
+            apex: *self,
+            traverse: self.traverse(),
+        }
+    }
+}
+
+/// An iterator over [`quick_xml`] events.
+///
+/// Created by [`Node::quick_xml_events`].
+#[derive(Clone, Debug)]
+pub struct QuickXmlEvents<'a, 'input: 'a> {
+    apex: Node<'a, 'input>,
+    traverse: Traverse<'a, 'input>,
+}
+
+impl<'a, 'input: 'a> Iterator for QuickXmlEvents<'a, 'input> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let node = match self.traverse.next()? {
+                Edge::Open(node) => node,
+                Edge::Close(node) => {
+                    if node.is_element() {
+                        let name = qname(node.prefix(), node.tag_name().name());
+                        return Some(Event::End(BytesEnd::new(name)));
+                    }
+
+                    continue;
+                }
+            };
+
+            match node.node_type() {
+                NodeType::Root => {}
+                NodeType::Element => {
+                    return Some(Event::Start(self.start_element(node)));
+                }
+                NodeType::Text => {
+                    return Some(Event::Text(BytesText::new(node.text().unwrap_or(""))));
+                }
+                NodeType::Comment => {
+                    let text = node.comment_text().unwrap_or("");
+                    return Some(Event::Comment(BytesText::from_escaped(text)));
+                }
+                NodeType::PI => {
+                    let pi = node.pi()?;
+                    let content = match pi.value {
+                        Some(value) => Cow::Owned(alloc::format!("{} {}", pi.target, value)),
+                        None => Cow::Borrowed(pi.target),
+                    };
+                    return Some(Event::PI(BytesPI::new(content)));
+                }
+            }
+        }
+    }
+}
+
+impl<'a, 'input: 'a> QuickXmlEvents<'a, 'input> {
+    fn start_element(&self, node: Node<'a, 'input>) -> BytesStart<'a> {
+        let mut start = BytesStart::new(qname(node.prefix(), node.tag_name().name()));
+
+        let parent = node.parent_element().filter(|_| node != self.apex);
+        for ns in node.namespaces() {
+            let declared = match parent {
+                Some(parent) => parent.lookup_namespace_uri(ns.name()) != Some(ns.uri()),
+                // An empty default namespace is the same as an absent one.
+                None => ns.name().is_some() || !ns.uri().is_empty(),
+            };
+
+            if declared {
+                let name = match ns.name() {
+                    Some(prefix) => Cow::Owned(alloc::format!("xmlns:{}", prefix)),
+                    None => Cow::Borrowed("xmlns"),
+                };
+                start.push_attribute((name.as_ref(), ns.uri()));
+            }
+        }
+
+        for attr in node.attributes() {
+            let name = qname(attr.prefix(), attr.name());
+            start.push_attribute((name.as_ref(), attr.value()));
+        }
+
+        start
+    }
+}
+
+fn qname<'a>(prefix: Option<&str>, name: &'a str) -> Cow<'a, str> {
+    match prefix {
+        Some(prefix) => Cow::Owned(alloc::format!("{}:{}", prefix, name)),
+        None => Cow::Borrowed(name),
+    }
+}
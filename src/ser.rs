@@ -0,0 +1,140 @@
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+
+use crate::{Attributes, Document, ExpandedName, Node, NodeKind};
+
+/// Specifies how element and attribute names are serialized.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum NameStyle {
+    /// A name is serialized using the Clark notation, like `{http://www.w3.org}e`.
+    ///
+    /// Names without a namespace are serialized as is.
+    #[default]
+    Clark,
+
+    /// Namespaces are ignored and only local names are serialized.
+    ///
+    /// Attributes of the same element can have the same local name, like `a:x` and `b:x`.
+    /// Such attributes are serialized using the Clark notation instead, to keep map keys unique.
+    Local,
+}
+
+/// A node wrapper that implements `Serialize` using the specified [`NameStyle`].
+///
+/// Can be created via [`Node::serializable`].
+#[derive(Clone, Copy, Debug)]
+pub struct SerializableNode<'a, 'input: 'a> {
+    node: Node<'a, 'input>,
+    style: NameStyle,
+}
+
+impl<'a, 'input: 'a> Node<'a, 'input> {
+    /// Returns a node wrapper that implements `Serialize` using the specified name style.
+    ///
+    /// The `Serialize` implementation of the `Node` itself uses the [`NameStyle::Clark`].
+    ///
+    /// The produced shape is:
+    ///
+    /// - an element is a map with `tag`, `attributes` and `children` keys
+    /// - a text node is a string
+    /// - a comment is a map with a `comment` key
+    /// - a processing instruction is a map with `target` and `value` keys
+    /// - the root node is a map with a `children` key
+    ///
+    /// This is a one-way projection. Round-tripping is not supported.
+    #[inline]
+    pub fn serializable(&self, style: NameStyle) -> SerializableNode<'a, 'input> {
+        SerializableNode { node: *self, style }
+    }
+}
+
+impl Serialize for SerializableNode<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.node.d.kind {
+            NodeKind::Root => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("children", &SerializableChildren(*self))?;
+                map.end()
+            }
+            NodeKind::Element { .. } => {
+                let mut map = serializer.serialize_map(Some(3))?;
+                map.serialize_entry("tag", &SerializableName(self.node.tag_name(), self.style))?;
+                map.serialize_entry(
+                    "attributes",
+                    &SerializableAttributes(self.node.attributes(), self.style),
+                )?;
+                map.serialize_entry("children", &SerializableChildren(*self))?;
+                map.end()
+            }
+            NodeKind::PI(pi) => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("target", pi.target)?;
+                map.serialize_entry("value", &pi.value)?;
+                map.end()
+            }
+            NodeKind::Comment(ref text) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("comment", text.as_str())?;
+                map.end()
+            }
+            NodeKind::Text(ref text, _) => serializer.serialize_str(text),
+        }
+    }
+}
+
+impl Serialize for Node<'_, '_> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.serializable(NameStyle::default())
+            .serialize(serializer)
+    }
+}
+
+impl Serialize for Document<'_> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.root().serialize(serializer)
+    }
+}
+
+struct SerializableName<'a, 'b>(ExpandedName<'a, 'b>, NameStyle);
+
+impl Serialize for SerializableName<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match (self.1, self.0.namespace()) {
+            (NameStyle::Clark, Some(_)) => serializer.collect_str(&self.0),
+            _ => serializer.serialize_str(self.0.name()),
+        }
+    }
+}
+
+struct SerializableAttributes<'a, 'input>(Attributes<'a, 'input>, NameStyle);
+
+impl Serialize for SerializableAttributes<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for attr in self.0.clone() {
+            let is_collision = self.1 == NameStyle::Local
+                && self.0.clone().filter(|a| a.name() == attr.name()).count() > 1;
+            let style = if is_collision {
+                NameStyle::Clark
+            } else {
+                self.1
+            };
+            let name = SerializableName(attr.data.name.as_expanded_name(attr.doc), style);
+            map.serialize_entry(&name, attr.value())?;
+        }
+        map.end()
+    }
+}
+
+struct SerializableChildren<'a, 'input>(SerializableNode<'a, 'input>);
+
+impl Serialize for SerializableChildren<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(None)?;
+        for child in self.0.node.children() {
+            seq.serialize_element(&child.serializable(self.0.style))?;
+        }
+        seq.end()
+    }
+}
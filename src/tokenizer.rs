@@ -5,6 +5,18 @@ use crate::{Error, TextPos};
 
 type Result<T> = core::result::Result<T, Error>;
 
+/// Checks that the string is a [NCName](https://www.w3.org/TR/xml-names/#NT-NCName),
+/// i.e. a name without a colon.
+pub(crate) fn is_ncname(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c != ':' && c.is_xml_name_start() => {}
+        _ => return false,
+    }
+
+    chars.all(|c| c != ':' && c.is_xml_name())
+}
+
 /// Extension methods for XML-subset only operations.
 trait XmlCharExt {
     /// Checks if the value is within the
@@ -79,7 +91,7 @@ impl XmlCharExt for char {
     }
 }
 
-trait XmlByteExt {
+pub trait XmlByteExt {
     /// Checks if byte is a space.
     ///
     /// `[ \r\n\t]`
@@ -122,7 +134,7 @@ impl<'input> From<&'input str> for StrSpan<'input> {
 
 impl<'input> StrSpan<'input> {
     #[inline]
-    pub fn from_substr(text: &str, start: usize, end: usize) -> StrSpan {
+    pub fn from_substr(text: &str, start: usize, end: usize) -> StrSpan<'_> {
         debug_assert!(start <= end);
         StrSpan {
             text: &text[start..end],
@@ -153,14 +165,43 @@ pub enum Token<'input> {
     // <!-- text -->
     Comment(&'input str, Range<usize>),
 
+    // <?xml version="1.0" standalone="yes"?>
+    // Only the `standalone` value and the declaration position are reported.
+    Declaration(Option<bool>, usize),
+
+    // <!DOCTYPE svg SYSTEM "svg.dtd" [
+    // Only the external subset presence and the DTD position are reported.
+    DoctypeStart(bool, usize),
+
     // <!ENTITY ns_extend "http://test.com">
-    EntityDeclaration(&'input str, StrSpan<'input>),
+    // The last value indicates a general entity, as opposed to a parameter one.
+    EntityDeclaration(&'input str, StrSpan<'input>, bool),
+
+    // <!ENTITY ns_extend SYSTEM "ns_extend.xml">
+    // Only general entities are reported.
+    ExternalEntityDeclaration(&'input str, usize),
+
+    // <!ATTLIST ns:elem ns:attr CDATA "value">
+    // Element's prefix and local name followed by the same data as in `Attribute`.
+    AttributeDefault(
+        (&'input str, &'input str),
+        Range<usize>,
+        u32,
+        u32,
+        &'input str,
+        &'input str,
+        StrSpan<'input>,
+    ),
+
+    // <!ELEMENT ns:elem (ns:child)>
+    // Element's prefix, local name, whether it has element content and position.
+    ElementDeclaration(&'input str, &'input str, bool, usize),
 
     // <ns:elem
     ElementStart(&'input str, &'input str, usize),
 
     // ns:attr="value"
-    Attribute(Range<usize>, u16, u8, &'input str, &'input str, StrSpan<'input>),
+    Attribute(Range<usize>, u32, u32, &'input str, &'input str, StrSpan<'input>),
 
     ElementEnd(ElementEnd<'input>, Range<usize>),
 
@@ -186,23 +227,56 @@ pub enum ElementEnd<'input> {
 
 pub trait XmlEvents<'input> {
     fn token(&mut self, token: Token<'input>) -> Result<()>;
+
+    /// Checks that `]]>` is allowed inside text.
+    fn allow_cdata_end_in_text(&self) -> bool {
+        false
+    }
+
+    /// Checks that `<!ATTLIST>` declarations should be parsed instead of being skipped.
+    fn parse_attribute_defaults(&self) -> bool {
+        false
+    }
+
+    /// Checks that `<!ELEMENT>` declarations should be parsed instead of being skipped.
+    fn parse_element_declarations(&self) -> bool {
+        false
+    }
+
+    /// Checks that parsing should stop at unknown content after the root element.
+    fn allow_trailing_content(&self) -> bool {
+        false
+    }
+
+    /// Checks that whitespace is allowed before the XML declaration.
+    fn allow_leading_whitespace_before_declaration(&self) -> bool {
+        false
+    }
 }
 
 // document ::= prolog element Misc*
+//
+// When `fragment` is set, multiple elements are allowed instead of a single one.
+//
+// Returns the trailing content position, when it's allowed.
 pub fn parse<'input>(
     text: &'input str,
     allow_dtd: bool,
+    fragment: bool,
     events: &mut dyn XmlEvents<'input>,
-) -> Result<()> {
+) -> Result<Option<usize>> {
     let s = &mut Stream::new(text);
 
-    // Skip UTF-8 BOM.
-    if s.starts_with(&[0xEF, 0xBB, 0xBF]) {
-        s.advance(3);
+    skip_bom(s);
+    // Spaces are skipped by `parse_misc` anyway.
+    if events.allow_leading_whitespace_before_declaration() {
+        s.skip_spaces();
     }
 
     if s.starts_with(b"<?xml ") {
-        parse_declaration(s)?;
+        let start = s.pos();
+        let (_, standalone) = parse_declaration(s)?;
+        events.token(Token::Declaration(standalone, start))?;
     }
 
     parse_misc(s, events)?;
@@ -218,17 +292,59 @@ pub fn parse<'input>(
     }
 
     s.skip_spaces();
-    if s.curr_byte().ok() == Some(b'<') {
+    let has_root = s.curr_byte().ok() == Some(b'<');
+    if has_root {
         parse_element(s, events)?;
     }
 
+    let allow_trailing_content = has_root && events.allow_trailing_content();
+    let parse_misc = if allow_trailing_content {
+        parse_trailing_misc
+    } else {
+        parse_misc
+    };
+
     parse_misc(s, events)?;
 
+    if fragment {
+        while s.curr_byte().ok() == Some(b'<') {
+            parse_element(s, events)?;
+            parse_misc(s, events)?;
+        }
+    }
+
     if !s.at_end() {
+        if allow_trailing_content {
+            return Ok(Some(s.pos()));
+        }
+
         return Err(Error::UnknownToken(s.gen_text_pos()));
     }
 
-    Ok(())
+    Ok(None)
+}
+
+/// Returns the `encoding` value of the XML declaration, if any.
+pub fn parse_encoding(text: &str, leading_whitespace: bool) -> Result<Option<StrSpan<'_>>> {
+    let s = &mut Stream::new(text);
+
+    skip_bom(s);
+    if leading_whitespace {
+        s.skip_spaces();
+    }
+
+    if s.starts_with(b"<?xml ") {
+        parse_declaration(s).map(|(encoding, _)| encoding)
+    } else {
+        Ok(None)
+    }
+}
+
+fn skip_bom(s: &mut Stream) {
+    // Skip UTF-8 BOM.
+    if s.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        s.advance(3);
+    }
 }
 
 // Misc ::= Comment | PI | S
@@ -247,10 +363,36 @@ fn parse_misc<'input>(s: &mut Stream<'input>, events: &mut dyn XmlEvents<'input>
     Ok(())
 }
 
+// Same as `parse_misc`, but stops at a malformed comment or processing instruction,
+// so it can be reported as trailing content instead.
+fn parse_trailing_misc<'input>(
+    s: &mut Stream<'input>,
+    events: &mut dyn XmlEvents<'input>,
+) -> Result<()> {
+    while !s.at_end() {
+        s.skip_spaces();
+        if s.starts_with(b"<!--") && is_parsable(s, parse_comment) {
+            parse_comment(s, events)?;
+        } else if s.starts_with(b"<?") && is_parsable(s, parse_pi) {
+            parse_pi(s, events)?;
+        } else {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
 // XMLDecl ::= '<?xml' VersionInfo EncodingDecl? SDDecl? S? '?>'
 //
 // We don't actually return a token for the XML declaration and only validate it.
-fn parse_declaration(s: &mut Stream) -> Result<()> {
+// Only the encoding and the `standalone` value are returned, since the encoding
+// is needed to validate a bytes input.
+//
+// A `standalone` value other than `yes` or `no` is ignored.
+fn parse_declaration<'input>(
+    s: &mut Stream<'input>,
+) -> Result<(Option<StrSpan<'input>>, Option<bool>)> {
     fn consume_spaces(s: &mut Stream) -> Result<()> {
         if s.starts_with_space() {
             s.skip_spaces();
@@ -271,24 +413,32 @@ fn parse_declaration(s: &mut Stream) -> Result<()> {
     // The `version` "attribute" is mandatory.
     if !s.starts_with(b"version") {
         // Will trigger the InvalidString error, which is what we want.
-        return s.skip_string(b"version");
+        s.skip_string(b"version")?;
     }
     let _ = parse_attribute(s)?;
     consume_spaces(s)?;
 
+    let mut encoding = None;
     if s.starts_with(b"encoding") {
-        let _ = parse_attribute(s)?;
+        let (_, _, value) = parse_attribute(s)?;
+        encoding = Some(value);
         consume_spaces(s)?;
     }
 
+    let mut standalone = None;
     if s.starts_with(b"standalone") {
-        let _ = parse_attribute(s)?;
+        let (_, _, value) = parse_attribute(s)?;
+        standalone = match value.as_str() {
+            "yes" => Some(true),
+            "no" => Some(false),
+            _ => None,
+        };
     }
 
     s.skip_spaces();
     s.skip_string(b"?>")?;
 
-    Ok(())
+    Ok((encoding, standalone))
 }
 
 // '<!--' ((Char - '-') | ('-' (Char - '-')))* '-->'
@@ -339,7 +489,8 @@ fn parse_pi<'input>(s: &mut Stream<'input>, events: &mut dyn XmlEvents<'input>)
 
 fn parse_doctype<'input>(s: &mut Stream<'input>, events: &mut dyn XmlEvents<'input>) -> Result<()> {
     let start = s.pos();
-    parse_doctype_start(s)?;
+    let has_external_subset = parse_doctype_start(s)?;
+    events.token(Token::DoctypeStart(has_external_subset, start))?;
     s.skip_spaces();
 
     if s.curr_byte() == Ok(b'>') {
@@ -372,6 +523,16 @@ fn parse_doctype<'input>(s: &mut Stream<'input>, events: &mut dyn XmlEvents<'inp
                     return Err(Error::UnexpectedEndOfStream);
                 }
             }
+        } else if s.starts_with(b"<!ATTLIST")
+            && events.parse_attribute_defaults()
+            && is_parsable(s, parse_attlist_decl)
+        {
+            parse_attlist_decl(s, events)?;
+        } else if s.starts_with(b"<!ELEMENT")
+            && events.parse_element_declarations()
+            && is_parsable(s, parse_element_decl)
+        {
+            parse_element_decl(s, events)?;
         } else if s.starts_with(b"<!ELEMENT")
             || s.starts_with(b"<!ATTLIST")
             || s.starts_with(b"<!NOTATION")
@@ -389,14 +550,16 @@ fn parse_doctype<'input>(s: &mut Stream<'input>, events: &mut dyn XmlEvents<'inp
 }
 
 // doctypedecl ::= '<!DOCTYPE' S Name (S ExternalID)? S? ('[' intSubset ']' S?)? '>'
-fn parse_doctype_start(s: &mut Stream) -> Result<()> {
+//
+// Returns `true` when an external subset is referenced.
+fn parse_doctype_start(s: &mut Stream) -> Result<bool> {
     s.advance(9);
 
     s.consume_spaces()?;
     s.skip_name()?;
     s.skip_spaces();
 
-    let _ = parse_external_id(s)?;
+    let has_external_subset = parse_external_id(s)?;
     s.skip_spaces();
 
     let c = s.curr_byte()?;
@@ -404,7 +567,7 @@ fn parse_doctype_start(s: &mut Stream) -> Result<()> {
         return Err(Error::InvalidChar2("'[' or '>'", c, s.gen_text_pos()));
     }
 
-    Ok(())
+    Ok(has_external_subset)
 }
 
 // ExternalID ::= 'SYSTEM' S SystemLiteral | 'PUBLIC' S PubidLiteral S SystemLiteral
@@ -443,6 +606,7 @@ fn parse_entity_decl<'input>(
     s: &mut Stream<'input>,
     events: &mut dyn XmlEvents<'input>,
 ) -> Result<()> {
+    let start = s.pos();
     s.advance(8);
     s.consume_spaces()?;
 
@@ -456,7 +620,9 @@ fn parse_entity_decl<'input>(
     let name = s.consume_name()?;
     s.consume_spaces()?;
     if let Some(definition) = parse_entity_def(s, is_ge)? {
-        events.token(Token::EntityDeclaration(name, definition))?;
+        events.token(Token::EntityDeclaration(name, definition, is_ge))?;
+    } else if is_ge {
+        events.token(Token::ExternalEntityDeclaration(name, start))?;
     }
     s.skip_spaces();
     s.consume_byte(b'>')?;
@@ -508,6 +674,143 @@ fn parse_entity_def<'input>(
     }
 }
 
+/// Checks that an item can be parsed, without reporting any tokens.
+///
+/// Declarations with parameter entity references cannot be,
+/// so they are skipped instead, just like when they are not requested at all.
+fn is_parsable<'input>(
+    s: &Stream<'input>,
+    parse: fn(&mut Stream<'input>, &mut dyn XmlEvents<'input>) -> Result<()>,
+) -> bool {
+    struct NoEvents;
+
+    impl<'input> XmlEvents<'input> for NoEvents {
+        fn token(&mut self, _: Token<'input>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    parse(&mut s.clone(), &mut NoEvents).is_ok()
+}
+
+// AttlistDecl ::= '<!ATTLIST' S Name AttDef* S? '>'
+// AttDef      ::= S Name S AttType S DefaultDecl
+// DefaultDecl ::= '#REQUIRED' | '#IMPLIED' | (('#FIXED' S)? AttValue)
+//
+// Only CDATA attributes with a default value will be reported.
+fn parse_attlist_decl<'input>(
+    s: &mut Stream<'input>,
+    events: &mut dyn XmlEvents<'input>,
+) -> Result<()> {
+    s.advance(9);
+    s.consume_spaces()?;
+    let element = s.consume_qname()?;
+
+    loop {
+        s.skip_spaces();
+        if s.try_consume_byte(b'>') {
+            break;
+        }
+
+        let start = s.pos();
+        let (prefix, local) = s.consume_qname()?;
+        let qname_end = s.pos();
+        s.consume_spaces()?;
+
+        // AttType ::= StringType | TokenizedType | EnumeratedType
+        let is_cdata = if s.starts_with(b"(") {
+            consume_enumeration(s)?;
+            false
+        } else {
+            let att_type = s.consume_name()?;
+            if att_type == "NOTATION" {
+                s.consume_spaces()?;
+                consume_enumeration(s)?;
+            }
+
+            att_type == "CDATA"
+        };
+        s.consume_spaces()?;
+
+        if s.starts_with(b"#REQUIRED") {
+            s.advance(9);
+            continue;
+        } else if s.starts_with(b"#IMPLIED") {
+            s.advance(8);
+            continue;
+        } else if s.starts_with(b"#FIXED") {
+            s.advance(6);
+            s.consume_spaces()?;
+        }
+
+        let eq_len = part_len(s, qname_end, s.pos())?;
+        let quote = s.consume_quote()?;
+        let quote_c = quote as char;
+        // The attribute value must not contain the < character.
+        let value_start = s.pos();
+        s.skip_chars(|_, c| c != quote_c && c != '<')?;
+        let value = s.slice_back_span(value_start);
+        s.consume_byte(quote)?;
+
+        if is_cdata {
+            let qname_len = part_len(s, start, qname_end)?;
+            let range = s.range_from(start);
+            events.token(Token::AttributeDefault(
+                element, range, qname_len, eq_len, prefix, local, value,
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+// elementdecl ::= '<!ELEMENT' S Name S contentspec S? '>'
+// contentspec ::= 'EMPTY' | 'ANY' | Mixed | children
+// Mixed       ::= '(' S? '#PCDATA' (S? '|' S? Name)* S? ')*' | '(' S? '#PCDATA' S? ')'
+//
+// Only whether an element has element content, aka `children`, is reported.
+// The content model itself is skipped.
+fn parse_element_decl<'input>(
+    s: &mut Stream<'input>,
+    events: &mut dyn XmlEvents<'input>,
+) -> Result<()> {
+    let start = s.pos();
+    s.advance(9);
+    s.consume_spaces()?;
+    let (prefix, local) = s.consume_qname()?;
+    s.consume_spaces()?;
+
+    let is_element_content = if s.try_consume_byte(b'(') {
+        s.skip_spaces();
+        !s.starts_with(b"#PCDATA")
+    } else {
+        false
+    };
+
+    consume_decl(s)?;
+
+    events.token(Token::ElementDeclaration(
+        prefix,
+        local,
+        is_element_content,
+        start,
+    ))
+}
+
+// Enumeration ::= '(' S? Nmtoken (S? '|' S? Nmtoken)* S? ')'
+fn consume_enumeration(s: &mut Stream) -> Result<()> {
+    s.consume_byte(b'(')?;
+    s.skip_bytes(|c| c != b')');
+    s.consume_byte(b')')?;
+    Ok(())
+}
+
+// Attribute positions store lengths as `u32` to keep attributes small.
+// Longer parts are not realistic, but must not be silently truncated.
+fn part_len(s: &Stream, start: usize, end: usize) -> Result<u32> {
+    u32::try_from(end - start).map_err(|_| Error::UnknownToken(s.gen_text_pos_from(start)))
+}
+
 fn consume_decl(s: &mut Stream) -> Result<()> {
     s.skip_bytes(|c| c != b'>');
     s.consume_byte(b'>')?;
@@ -554,9 +857,9 @@ fn parse_element<'input>(s: &mut Stream<'input>, events: &mut dyn XmlEvents<'inp
                 // because it will blow up the binary size.
                 let (prefix, local) = s.consume_qname()?;
                 let qname_end = s.pos();
-                let qname_len = u16::try_from(qname_end - start).unwrap_or(u16::MAX);
+                let qname_len = part_len(s, start, qname_end)?;
                 s.consume_eq()?;
-                let eq_len = u8::try_from(s.pos() - qname_end).unwrap_or(u8::MAX);
+                let eq_len = part_len(s, qname_end, s.pos())?;
                 let quote = s.consume_quote()?;
                 let quote_c = quote as char;
                 // The attribute value must not contain the < character.
@@ -668,7 +971,7 @@ fn parse_text<'input>(s: &mut Stream<'input>, events: &mut dyn XmlEvents<'input>
     // https://www.w3.org/TR/xml/#syntax
     //
     // Search for `>` first, since it's a bit faster than looking for `]]>`.
-    if text.contains('>') && text.contains("]]>") {
+    if text.contains('>') && text.contains("]]>") && !events.allow_cdata_end_in_text() {
         return Err(Error::InvalidCharacterData(s.gen_text_pos()));
     }
 
@@ -843,7 +1146,7 @@ impl<'input> Stream<'input> {
     }
 
     #[inline]
-    fn slice_back(&self, pos: usize) -> &'input str {
+    pub fn slice_back(&self, pos: usize) -> &'input str {
         self.span.slice_region(pos, self.pos)
     }
 
@@ -909,8 +1212,7 @@ impl<'input> Stream<'input> {
 
         let reference = if self.try_consume_byte(b'#') {
             let (value, radix) = if self.try_consume_byte(b'x') {
-                let value =
-                    self.consume_bytes(|c| matches!(c, b'0'..=b'9' | b'A'..=b'F' | b'a'..=b'f'));
+                let value = self.consume_bytes(|c| c.is_ascii_hexdigit());
                 (value, 16)
             } else {
                 let value = self.consume_bytes(|c| c.is_ascii_digit());
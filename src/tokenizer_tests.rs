@@ -42,6 +42,9 @@ pub enum Token<'a> {
     PI(&'a str, Option<&'a str>, Range),
     Comment(&'a str, Range),
     EntityDecl(&'a str, &'a str),
+    ExternalEntityDecl(&'a str),
+    AttributeDefault(&'a str, &'a str, &'a str),
+    ElementDecl(&'a str, &'a str),
     ElementStart(&'a str, &'a str, usize),
     Attribute(&'a str, &'a str, &'a str),
     ElementEnd(ElementEnd<'a>, Range),
@@ -57,7 +60,6 @@ pub enum ElementEnd<'a> {
     Empty,
 }
 
-#[macro_export]
 macro_rules! test {
     ($name:ident, $text:expr, $($token:expr),*) => (
         #[test]
@@ -84,9 +86,20 @@ impl<'a> xml::XmlEvents<'a> for EventsCollector<'a> {
                 Token::PI(target, content, range)
             }
             xml::Token::Comment(text, range) => Token::Comment(text, range),
-            xml::Token::EntityDeclaration(name, definition) => {
+            // Not interesting for the tokenizer tests and tested via the public API instead.
+            xml::Token::Declaration(..) | xml::Token::DoctypeStart(..) => return Ok(()),
+            xml::Token::EntityDeclaration(name, definition, _) => {
                 Token::EntityDecl(name, definition.as_str())
             }
+            xml::Token::ExternalEntityDeclaration(name, _) => Token::ExternalEntityDecl(name),
+            xml::Token::AttributeDefault((_, element), _, _, _, _, local, value) => {
+                Token::AttributeDefault(element, local, value.as_str())
+            }
+            // Only declarations with element content affect the document.
+            xml::Token::ElementDeclaration(_, _, false, _) => return Ok(()),
+            xml::Token::ElementDeclaration(prefix, local, true, _) => {
+                Token::ElementDecl(prefix, local)
+            }
             xml::Token::ElementStart(prefix, local, start) => {
                 Token::ElementStart(prefix, local, start)
             }
@@ -107,12 +120,20 @@ impl<'a> xml::XmlEvents<'a> for EventsCollector<'a> {
         self.tokens.push(t);
         Ok(())
     }
+
+    fn parse_attribute_defaults(&self) -> bool {
+        true
+    }
+
+    fn parse_element_declarations(&self) -> bool {
+        true
+    }
 }
 
 #[inline(never)]
-pub fn collect_tokens(text: &str) -> Vec<Token> {
+pub fn collect_tokens(text: &str) -> Vec<Token<'_>> {
     let mut collector = EventsCollector { tokens: Vec::new() };
-    if let Err(e) = xml::parse(text, true, &mut collector) {
+    if let Err(e) = xml::parse(text, true, false, &mut collector) {
         collector.tokens.push(Token::Error(e.to_string()));
     }
     collector.tokens
@@ -235,7 +256,7 @@ macro_rules! test_err {
         #[test]
         fn $name() {
             let mut collector = EventsCollector { tokens: Vec::new() };
-            assert!(xml::parse($text, true, &mut collector).is_err());
+            assert!(xml::parse($text, true, false, &mut collector).is_err());
         }
     };
 }
@@ -337,6 +358,7 @@ test!(
     "<!DOCTYPE svg [
     <!ENTITY open-hatch SYSTEM \"http://www.textuality.com/boilerplate/OpenHatch.xml\">
 ]>",
+    Token::ExternalEntityDecl("open-hatch")
 );
 
 test!(
@@ -346,6 +368,7 @@ test!(
              PUBLIC \"-//Textuality//TEXT Standard open-hatch boilerplate//EN\"
              \"http://www.textuality.com/boilerplate/OpenHatch.xml\">
 ]>",
+    Token::ExternalEntityDecl("open-hatch")
 );
 
 // TODO: NDATA will be ignored
@@ -354,6 +377,7 @@ test!(
     "<!DOCTYPE svg [
     <!ENTITY hatch-pic SYSTEM \"../grafix/OpenHatch.gif\" NDATA gif >
 ]>",
+    Token::ExternalEntityDecl("hatch-pic")
 );
 
 // TODO: unsupported data will be ignored
@@ -368,6 +392,44 @@ test!(
     Token::EntityDecl("ns_extend", "http://ns.adobe.com/Extensibility/1.0/")
 );
 
+test!(
+    dtd_attlist_01,
+    "<!DOCTYPE svg [
+    <!ATTLIST svg
+        a CDATA 'default'
+        b CDATA #REQUIRED
+        c (x|y) 'x'
+        d NOTATION (n) #IMPLIED
+        e CDATA #FIXED \"fixed\">
+]>",
+    Token::AttributeDefault("svg", "a", "default"),
+    Token::AttributeDefault("svg", "e", "fixed")
+);
+
+test!(
+    dtd_element_01,
+    "<!DOCTYPE svg [
+    <!ELEMENT svg (g|rect)+>
+    <!ELEMENT g ( #PCDATA | rect )*>
+    <!ELEMENT rect EMPTY>
+    <!ELEMENT n:text ANY>
+    <!ELEMENT n:tspan
+        ( n:text , g? )>
+]>",
+    Token::ElementDecl("", "svg"),
+    Token::ElementDecl("n", "tspan")
+);
+
+// Declarations that cannot be parsed, like the ones with parameter entity references,
+// are skipped.
+test!(
+    dtd_element_err_01,
+    "<!DOCTYPE svg [
+    <!ELEMENT (g|rect)+>
+    <!ELEMENT %name; (g)>
+]>",
+);
+
 // We do not support !ELEMENT DTD token and it will be skipped.
 // Previously, we were calling `Tokenizer::next` after the skip,
 // which is recursive and could cause a stack overflow when there are too many sequential
@@ -382,7 +444,7 @@ fn dtd_entity_07() {
     text.push_str("]>\n");
 
     let mut collector = EventsCollector { tokens: Vec::new() };
-    xml::parse(&text, true, &mut collector).unwrap();
+    xml::parse(&text, true, false, &mut collector).unwrap();
 }
 
 test!(
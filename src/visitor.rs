@@ -0,0 +1,146 @@
+use crate::{Node, NodeType};
+
+/// A depth-first tree visitor.
+///
+/// All methods do nothing by default.
+///
+/// Used by [`Node::accept`].
+pub trait Visitor<'a, 'input> {
+    /// Called when an element is entered, before its children.
+    fn enter_element(&mut self, node: Node<'a, 'input>) {
+        let _ = node;
+    }
+
+    /// Called when an element is left, after its children.
+    fn leave_element(&mut self, node: Node<'a, 'input>) {
+        let _ = node;
+    }
+
+    /// Called for each text node.
+    fn visit_text(&mut self, node: Node<'a, 'input>) {
+        let _ = node;
+    }
+
+    /// Called for each comment node.
+    fn visit_comment(&mut self, node: Node<'a, 'input>) {
+        let _ = node;
+    }
+
+    /// Called for each processing instruction node.
+    fn visit_pi(&mut self, node: Node<'a, 'input>) {
+        let _ = node;
+    }
+}
+
+impl<'a, 'input: 'a> Node<'a, 'input> {
+    /// Walks this node's subtree in the document order using the specified visitor.
+    ///
+    /// Includes the current node.
+    /// Unlike [`Node::descendants`], reports when an element is left.
+    ///
+    /// The walk is not recursive, so it's safe to use on deeply nested documents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use roxmltree::{Node, Visitor};
+    ///
+    /// #[derive(Default)]
+    /// struct Paths {
+    ///     stack: Vec<String>,
+    ///     paths: Vec<String>,
+    /// }
+    ///
+    /// impl<'a, 'input> Visitor<'a, 'input> for Paths {
+    ///     fn enter_element(&mut self, node: Node<'a, 'input>) {
+    ///         self.stack.push(node.tag_name().name().to_string());
+    ///     }
+    ///
+    ///     fn leave_element(&mut self, _: Node<'a, 'input>) {
+    ///         self.stack.pop();
+    ///     }
+    ///
+    ///     fn visit_text(&mut self, _: Node<'a, 'input>) {
+    ///         self.paths.push(self.stack.join("/"));
+    ///     }
+    /// }
+    ///
+    /// let doc = roxmltree::Document::parse("<a><b>1</b><c><d>2</d></c>3</a>").unwrap();
+    ///
+    /// let mut visitor = Paths::default();
+    /// doc.root().accept(&mut visitor);
+    /// assert_eq!(visitor.paths, ["a/b", "a/c/d", "a"]);
+    /// ```
+    pub fn accept<V: Visitor<'a, 'input>>(&self, visitor: &mut V) {
+        for edge in self.traverse() {
+            match edge {
+                Edge::Open(node) => match node.node_type() {
+                    NodeType::Root => {}
+                    NodeType::Element => visitor.enter_element(node),
+                    NodeType::Text => visitor.visit_text(node),
+                    NodeType::Comment => visitor.visit_comment(node),
+                    NodeType::PI => visitor.visit_pi(node),
+                },
+                Edge::Close(node) => {
+                    if node.is_element() {
+                        visitor.leave_element(node);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns an iterator over this node's subtree, including the current node,
+    /// that reports both opening and closing of each node.
+    ///
+    /// Doesn't allocate, since the tree is walked via parent and sibling links.
+    #[inline]
+    pub(crate) fn traverse(&self) -> Traverse<'a, 'input> {
+        Traverse {
+            root: *self,
+            next: Some(Edge::Open(*self)),
+        }
+    }
+}
+
+/// A node opening or closing during a subtree traversal.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Edge<'a, 'input: 'a> {
+    Open(Node<'a, 'input>),
+    Close(Node<'a, 'input>),
+}
+
+/// A depth-first subtree traversal.
+///
+/// Every node is opened and closed, even when it cannot have children.
+/// Shared by the visitor, dumping, subtree copying and `quick_xml` events.
+#[derive(Clone, Debug)]
+pub(crate) struct Traverse<'a, 'input: 'a> {
+    root: Node<'a, 'input>,
+    next: Option<Edge<'a, 'input>>,
+}
+
+impl<'a, 'input: 'a> Iterator for Traverse<'a, 'input> {
+    type Item = Edge<'a, 'input>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let edge = self.next.take()?;
+        self.next = match edge {
+            Edge::Open(node) => match node.first_child() {
+                Some(child) => Some(Edge::Open(child)),
+                None => Some(Edge::Close(node)),
+            },
+            Edge::Close(node) => {
+                if node == self.root {
+                    None
+                } else if let Some(sibling) = node.next_sibling() {
+                    Some(Edge::Open(sibling))
+                } else {
+                    node.parent().map(Edge::Close)
+                }
+            }
+        };
+
+        Some(edge)
+    }
+}
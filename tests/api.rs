@@ -264,6 +264,26 @@ fn text_pos_06() {
     }
 }
 
+#[cfg(feature = "positions")]
+#[test]
+fn attribute_quote_char_01() {
+    let data = "<e a='\"' b = \"'\" c=''/>";
+    let doc = Document::parse(data).unwrap();
+
+    let quotes: Vec<_> = doc
+        .root_element()
+        .attributes()
+        .map(|a| a.quote_char())
+        .collect();
+    assert_eq!(quotes, [b'\'', b'"', b'\'']);
+
+    let mut builder = DocumentBuilder::new();
+    builder.element("e").attribute("a", "1").end();
+    let doc = builder.finish().unwrap();
+    let attr = doc.root_element().attributes().next().unwrap();
+    assert_eq!(attr.quote_char(), b'"');
+}
+
 #[test]
 fn next_sibling_element_01() {
     let data = "<root><a/><b/><c/></root>";
@@ -282,6 +302,111 @@ fn next_sibling_element_01() {
     assert_eq!(c.tag_name().name(), "c");
 }
 
+#[test]
+fn sibling_index_01() {
+    let data = "<root><a/>text<!-- comment --><b/><c/></root>";
+
+    let doc = roxmltree::Document::parse(data).unwrap();
+
+    let root = doc.root_element();
+    assert_eq!(root.sibling_index(), 0);
+    assert_eq!(root.element_sibling_index(), Some(0));
+
+    let indices: Vec<_> = root
+        .children()
+        .map(|n| (n.sibling_index(), n.element_sibling_index()))
+        .collect();
+    assert_eq!(
+        indices,
+        [
+            (0, Some(0)),
+            (1, None),
+            (2, None),
+            (3, Some(1)),
+            (4, Some(2))
+        ]
+    );
+}
+
+#[test]
+fn select_01() {
+    let data = "\
+<svg xmlns='http://www.w3.org/2000/svg'>
+    <g id='g1'>
+        <rect id='r1'/>
+        <g id='g2'><rect id='r2'/></g>
+    </g>
+    <rect id='r3'/>
+    text
+</svg>
+";
+
+    fn ids<'a>(node: Node<'a, '_>, path: &str) -> Vec<&'a str> {
+        node.select(path)
+            .map(|n| n.attribute("id").unwrap_or(""))
+            .collect()
+    }
+
+    let doc = Document::parse(data).unwrap();
+
+    let root = doc.root();
+    assert_eq!(ids(root, "svg/g/rect"), ["r1"]);
+    assert_eq!(ids(root, "svg/rect"), ["r3"]);
+    assert_eq!(ids(root, "svg/*"), ["g1", "r3"]);
+    assert_eq!(ids(root, "//rect"), ["r1", "r2", "r3"]);
+    assert_eq!(ids(root, "//g/rect"), ["r1", "r2"]);
+    assert_eq!(ids(root, "//g//rect"), ["r1", "r2"]);
+    assert_eq!(ids(root, "svg/g/*/rect"), ["r2"]);
+    assert!(ids(root, "svg/text").is_empty());
+    assert!(ids(root, "svg/circle").is_empty());
+
+    let svg = doc.root_element();
+    assert_eq!(ids(svg, "g/g/rect"), ["r2"]);
+}
+
+#[test]
+fn find_local_01() {
+    let data = "<svg xmlns='http://www.w3.org/2000/svg' xmlns:n='http://www.w3.org'>\
+<rect n:x='1' y='2'/>rect<!--rect--></svg>";
+
+    let doc = Document::parse(data).unwrap();
+    let rect = doc.root_element().first_child().unwrap();
+    let attrs = rect.attributes();
+    assert_eq!(attrs.find_local("x").unwrap().value(), "1");
+    assert_eq!(attrs.find_local("y").unwrap().value(), "2");
+    assert_eq!(rect.attribute("x"), None);
+}
+
+#[test]
+fn tag_name_is_one_of_01() {
+    let data = "<svg xmlns='http://www.w3.org/2000/svg' xmlns:n='http://www.w3.org'>\
+<rect/><n:circle/><path/>text</svg>";
+
+    let doc = Document::parse(data).unwrap();
+    let svg = doc.root_element();
+
+    let count = svg
+        .descendants()
+        .filter(|n| n.tag_name_is_one_of(&["rect", "circle"]))
+        .count();
+    assert_eq!(count, 2);
+
+    let names = [
+        ExpandedName::from(("http://www.w3.org/2000/svg", "rect")),
+        ExpandedName::from(("http://www.w3.org/2000/svg", "circle")),
+        ExpandedName::from("path"),
+    ];
+    let local_names: Vec<_> = svg
+        .descendants()
+        .filter(|n| n.has_tag_name_in(&names))
+        .map(|n| n.tag_name().name())
+        .collect();
+    assert_eq!(local_names, ["rect", "path"]);
+
+    assert!(!svg.last_child().unwrap().tag_name_is_one_of(&[""]));
+    assert!(!svg.has_tag_name_in(&[]));
+}
+
 #[test]
 fn next_prev_element_01() {
     let data = "<root><a/><b/><c/></root>";
@@ -310,7 +435,7 @@ fn nodes_document_order() {
     let b = a.next_sibling_element().unwrap();
     let c = b.next_sibling_element().unwrap();
 
-    let mut elems = vec![&b, &c, &a];
+    let mut elems = [&b, &c, &a];
     elems.sort();
     assert!(elems[0] == &a);
     assert!(elems[1] == &b);
@@ -340,6 +465,7 @@ fn lifetimes() {
     let _ = f(&doc, |d| d.root().attribute_node("a"));
     let _ = f(&doc, |d| d.root().attributes());
     let _ = f(&doc, |d| d.root().namespaces());
+    let _ = f(&doc, |d| d.root().namespace_map());
     let _ = f(&doc, |d| d.root().text());
     let _ = f(&doc, |d| d.root().tail());
     let _ = f(&doc, |d| d.root().pi());
@@ -356,3 +482,2155 @@ fn tag_name_lifetime() {
     let root = doc.root_element();
     assert_eq!(get_tag_name(&root), "e");
 }
+
+#[test]
+fn trim_whitespace_01() {
+    let data = "\
+<a>
+    <b> text </b>
+    <!-- comment -->
+    <c/>
+</a>
+";
+
+    let opt = ParsingOptions {
+        trim_whitespace: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    let root = doc.root_element();
+
+    assert_eq!(root.children().count(), 3);
+    assert_eq!(root.descendants().count(), 5);
+
+    let b = root.first_child().unwrap();
+    assert!(b.has_tag_name("b"));
+    assert_eq!(b.text(), Some(" text "));
+    assert_eq!(b.tail(), None);
+    assert!(b.next_sibling().unwrap().is_comment());
+
+    let c = root.last_child().unwrap();
+    assert!(c.has_tag_name("c"));
+    assert_eq!(c.prev_sibling_element(), Some(b));
+    assert_eq!(c.next_sibling(), None);
+    assert_eq!(c.parent(), Some(root));
+}
+
+#[test]
+fn trim_whitespace_02() {
+    let data = "<p>Hello <b>x</b> <i>y</i></p>";
+
+    let opt = ParsingOptions {
+        trim_whitespace: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    let root = doc.root_element();
+
+    assert_eq!(root.children().count(), 4);
+    assert_eq!(root.first_element_child().unwrap().tail(), Some(" "));
+}
+
+#[test]
+fn trim_whitespace_03() {
+    let data = "\
+<a>
+    <b xml:space='preserve'>
+        <c/>
+        <d xml:space='default'>
+            <e/>
+        </d>
+    </b>
+</a>
+";
+
+    let opt = ParsingOptions {
+        trim_whitespace: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    let root = doc.root_element();
+    assert_eq!(root.children().count(), 1);
+
+    let b = root.first_child().unwrap();
+    assert_eq!(b.children().count(), 5);
+    assert!(b.first_child().unwrap().is_text());
+
+    let d = b.children().find(|n| n.has_tag_name("d")).unwrap();
+    assert_eq!(d.children().count(), 1);
+}
+
+#[test]
+fn descendants_of_type_01() {
+    let data = "<?pi?><e>text<!--c1--><a><!--c2-->text<b/></a><?pi?></e>";
+    let doc = Document::parse(data).unwrap();
+    let root = doc.root();
+
+    let count = |node: Node, node_type| node.descendants_of_type(node_type).count();
+    assert_eq!(count(root, NodeType::Root), 1);
+    assert_eq!(count(root, NodeType::Element), 3);
+    assert_eq!(count(root, NodeType::Text), 2);
+    assert_eq!(count(root, NodeType::Comment), 2);
+    assert_eq!(count(root, NodeType::PI), 2);
+
+    let a = doc.root_element().first_element_child().unwrap();
+    assert_eq!(count(a, NodeType::Root), 0);
+    assert_eq!(count(a, NodeType::Comment), 1);
+
+    let last = root.descendants_of_type(NodeType::Element).next_back();
+    assert!(last.unwrap().has_tag_name("b"));
+}
+
+#[test]
+fn descendant_elements_named_01() {
+    let data = "\
+<e xmlns='http://www.w3.org' xmlns:n='http://www.w3.org' xmlns:m='http://www.w4.org'>
+    <b/><n:b/><m:b/><c><b a='1'/></c>b
+</e>";
+
+    let doc = Document::parse(data).unwrap();
+    let root = doc.root();
+
+    assert_eq!(root.descendant_elements_named("b").count(), 4);
+    assert_eq!(
+        root.descendant_elements_named(("http://www.w3.org", "b"))
+            .count(),
+        3
+    );
+    assert_eq!(
+        root.descendant_elements_named(("http://www.w5.org", "b"))
+            .count(),
+        0
+    );
+    assert_eq!(root.descendant_elements_named("e").count(), 1);
+
+    let last = root.descendant_elements_named("b").next_back().unwrap();
+    assert_eq!(last.attribute("a"), Some("1"));
+
+    let c = root.descendant_elements_named("c").next().unwrap();
+    assert_eq!(c.descendant_elements_named("b").count(), 1);
+}
+
+#[test]
+fn stats_01() {
+    let data = "<e xmlns:n='http://www.w3.org' n:a='&lt;1' b='2'><!-- a&b -->&#x20;text</e>";
+    let doc = Document::parse(data).unwrap();
+    let stats = doc.stats();
+
+    assert_eq!(stats.nodes, 4);
+    assert!(stats.nodes_capacity >= stats.nodes);
+    assert_eq!(stats.attributes, 2);
+    assert!(stats.attributes_capacity >= stats.attributes);
+    assert_eq!(stats.namespaces, 2);
+    assert_eq!(stats.owned_string_bytes, "<1".len() + " text".len());
+}
+
+#[test]
+fn inner_text_storage_01() {
+    let data = "<e><a>text</a><b>&lt;</b><c/><d>1<!-- 2 --><x>3<?pi?></x>4</d></e>";
+    let doc = Document::parse(data).unwrap();
+    let mut iter = doc.root_element().children();
+
+    let a = iter.next().unwrap().inner_text_storage().unwrap();
+    assert!(matches!(a, StringStorage::Borrowed("text")));
+
+    let b = iter.next().unwrap().inner_text_storage().unwrap();
+    assert!(matches!(b, StringStorage::Owned(_)));
+    assert_eq!(b.as_str(), "<");
+
+    let c = iter.next().unwrap().inner_text_storage().unwrap();
+    assert_eq!(c.as_str(), "");
+
+    let d = iter.next().unwrap();
+    assert_eq!(d.inner_text_storage().unwrap().as_str(), "134");
+    assert_eq!(
+        doc.root().inner_text_storage().unwrap().as_str(),
+        "text<134"
+    );
+
+    let pi = d.last_element_child().unwrap().last_child().unwrap();
+    assert_eq!(pi.inner_text_storage(), None);
+}
+
+#[test]
+fn memory_limit_01() {
+    let data = "<e a='1' b='2'><c/>text</e>";
+
+    let opt = ParsingOptions {
+        memory_limit: Some(usize::MAX),
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+
+    let opt = ParsingOptions {
+        memory_limit: Some(doc.stats().nodes * 16),
+        ..ParsingOptions::default()
+    };
+    let error = Document::parse_with_options(data, opt).unwrap_err();
+    assert_eq!(error, Error::MemoryLimitReached);
+}
+
+#[test]
+fn memory_limit_02() {
+    // Allocated strings are taken into account as well.
+    let text = "&amp;".repeat(1000);
+    let data = format!("<e>{}</e>", text);
+
+    let opt = ParsingOptions {
+        memory_limit: Some(1000),
+        ..ParsingOptions::default()
+    };
+    let error = Document::parse_with_options(&data, opt).unwrap_err();
+    assert_eq!(error, Error::MemoryLimitReached);
+}
+
+#[test]
+fn max_depth_01() {
+    let data = "<a><b><c/></b><d/></a>";
+
+    let opt = ParsingOptions {
+        max_depth: 3,
+        ..ParsingOptions::default()
+    };
+    assert!(Document::parse_with_options(data, opt).is_ok());
+
+    let opt = ParsingOptions {
+        max_depth: 2,
+        ..ParsingOptions::default()
+    };
+    let error = Document::parse_with_options(data, opt).unwrap_err();
+    assert_eq!(error, Error::DepthLimitReached(TextPos::new(1, 7)));
+}
+
+#[test]
+fn namespaces_limit_01() {
+    // The `xml` namespace is always present, so the last declaration is above the limit.
+    let mut data = String::from("<e>");
+    for i in 0..=u16::MAX {
+        data.push_str(&format!("<n:e xmlns:n='{}'/>", i));
+    }
+    data.push_str("</e>");
+
+    let error = Document::parse(&data).unwrap_err();
+    assert_eq!(error, Error::NamespacesLimitReached);
+}
+
+#[test]
+fn into_owned_arc_01() {
+    let storage = StringStorage::new_owned("text");
+    let ptr = storage.as_str().as_ptr();
+    let text = storage.into_owned_arc();
+    assert_eq!(&*text, "text");
+    assert_eq!(text.as_ptr(), ptr);
+
+    let text = StringStorage::Borrowed("text").into_owned_arc();
+    assert_eq!(&*text, "text");
+}
+
+#[test]
+fn write_inner_text_01() {
+    let data = "<e>1<!-- 2 --><a>3<b>4</b></a><?pi 5?>6</e>";
+    let doc = Document::parse(data).unwrap();
+
+    let mut buf = String::from("0");
+    doc.root_element().write_inner_text(&mut buf).unwrap();
+    assert_eq!(buf, "01346");
+
+    buf.clear();
+    let a = doc.root_element().first_element_child().unwrap();
+    a.write_inner_text(&mut buf).unwrap();
+    assert_eq!(buf, "34");
+
+    buf.clear();
+    a.first_child().unwrap().write_inner_text(&mut buf).unwrap();
+    assert_eq!(buf, "3");
+}
+
+#[test]
+fn expand_entities_01() {
+    let data = "\
+<!DOCTYPE svg [
+    <!ENTITY ent 'value'>
+]>
+<e a='&ent;&amp;'>&ent;&#x20;&lt;<b/>&ent;</e>";
+
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        expand_entities: false,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    let root = doc.root_element();
+
+    assert_eq!(root.attribute("a"), Some("&ent;&amp;"));
+    assert_eq!(root.text(), Some("&ent; <"));
+    assert_eq!(root.last_child().unwrap().text(), Some("&ent;"));
+}
+
+#[test]
+fn expand_entities_02() {
+    let data = "<!DOCTYPE svg []><e>&ent;</e>";
+
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        expand_entities: false,
+        ..ParsingOptions::default()
+    };
+    let error = Document::parse_with_options(data, opt).unwrap_err();
+    assert_eq!(
+        error,
+        Error::UnknownEntityReference("ent".to_string(), TextPos::new(1, 21))
+    );
+}
+
+#[test]
+fn expand_entities_03() {
+    let data = "\
+<!DOCTYPE e [<!ENTITY ent 'value'>]>
+<e a='&amp;ent;' b='&ent;' c='&#38;ent;'><c>&amp;ent;</c><c>&ent;</c><c><![CDATA[&ent;]]></c></e>";
+
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        expand_entities: false,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    let root = doc.root_element();
+
+    // `&` produced by a reference or inside CDATA stays escaped.
+    assert_eq!(root.attribute("a"), Some("&amp;ent;"));
+    assert_eq!(root.attribute("b"), Some("&ent;"));
+    assert_eq!(root.attribute("c"), Some("&amp;ent;"));
+
+    let texts: Vec<_> = root.children().map(|n| n.text().unwrap()).collect();
+    assert_eq!(texts, ["&amp;ent;", "&ent;", "&amp;ent;"]);
+
+    // Nothing is escaped when entities are expanded.
+    let opt = ParsingOptions {
+        expand_entities: true,
+        ..opt
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    let root = doc.root_element();
+    assert_eq!(root.attribute("a"), Some("&ent;"));
+    let texts: Vec<_> = root.children().map(|n| n.text().unwrap()).collect();
+    assert_eq!(texts, ["&ent;", "value", "&ent;"]);
+}
+
+#[test]
+fn merge_text_across_comments_01() {
+    let data = "<p>Hello<!--x-->World<!--y--><!--z-->&amp;<b/>a<!--w--></p>";
+
+    let doc = Document::parse(data).unwrap();
+    assert_eq!(doc.root_element().text(), Some("Hello"));
+
+    let opt = ParsingOptions {
+        merge_text_across_comments: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    let p = doc.root_element();
+    assert_eq!(p.text(), Some("HelloWorld&"));
+
+    let types: Vec<_> = p.children().map(|n| n.node_type()).collect();
+    assert_eq!(
+        types,
+        [
+            NodeType::Text,
+            NodeType::Comment,
+            NodeType::Comment,
+            NodeType::Comment,
+            NodeType::Element,
+            NodeType::Text,
+            NodeType::Comment,
+        ]
+    );
+    assert_eq!(p.first_element_child().unwrap().tail(), Some("a"));
+}
+
+#[test]
+fn normalize_attribute_whitespace_01() {
+    let data = "<e a='1\t2\r\n3\r4&#x9;5'/>";
+
+    let doc = Document::parse(data).unwrap();
+    assert_eq!(doc.root_element().attribute("a"), Some("1 2 3 4\t5"));
+
+    let opt = ParsingOptions {
+        normalize_attribute_whitespace: false,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    assert_eq!(doc.root_element().attribute("a"), Some("1\t2\n3\n4\t5"));
+
+    let doc = Document::parse_with_options("<e a='1\t\n2'/>", opt).unwrap();
+    let attr = doc.root_element().attribute_node("a").unwrap();
+    assert!(matches!(
+        attr.value_storage(),
+        StringStorage::Borrowed("1\t\n2")
+    ));
+}
+
+#[test]
+fn allow_cdata_end_in_text_01() {
+    let data = "<p>a]]>b<b/>c]]></p>";
+
+    let error = Document::parse(data).unwrap_err();
+    assert_eq!(error, Error::InvalidCharacterData(TextPos::new(1, 9)));
+
+    let opt = ParsingOptions {
+        allow_cdata_end_in_text: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    assert_eq!(doc.root_element().text(), Some("a]]>b"));
+    let tail = doc.root_element().last_child().unwrap();
+    assert_eq!(tail.text(), Some("c]]>"));
+}
+
+#[test]
+fn apply_attlist_defaults_01() {
+    let data = "\
+<!DOCTYPE e [
+    <!ATTLIST e
+        a CDATA 'default'
+        b CDATA #IMPLIED
+        c (x|y) 'x'
+        d CDATA #FIXED \"&amp;fixed\"
+        xmlns:n CDATA 'http://www.w3.org'>
+    <!ATTLIST e a CDATA 'ignored'>
+    <!ATTLIST n:c a CDATA 'ns'>
+]>
+<e a='own'><e/><n:c/></e>";
+
+    // The `n` prefix is declared only via a default.
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let error = Document::parse_with_options(data, opt).unwrap_err();
+    assert_eq!(
+        error,
+        Error::UnknownNamespace("n".to_string(), TextPos::new(11, 17))
+    );
+
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        apply_attlist_defaults: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+
+    let root = doc.root_element();
+    let attrs: Vec<_> = root.attributes().map(|a| (a.name(), a.value())).collect();
+    assert_eq!(attrs, [("a", "own"), ("d", "&fixed")]);
+    assert_eq!(
+        root.lookup_namespace_uri(Some("n")),
+        Some("http://www.w3.org")
+    );
+
+    let child = root.first_element_child().unwrap();
+    let attrs: Vec<_> = child.attributes().map(|a| (a.name(), a.value())).collect();
+    assert_eq!(attrs, [("a", "default"), ("d", "&fixed")]);
+
+    let child = child.next_sibling_element().unwrap();
+    assert_eq!(child.tag_name().namespace(), Some("http://www.w3.org"));
+    assert_eq!(child.attribute("a"), Some("ns"));
+}
+
+#[test]
+fn entity_limits_01() {
+    let data = "\
+<!DOCTYPE e [
+    <!ENTITY a 'a'>
+    <!ENTITY b '&a;&a;&a;'>
+    <!ENTITY c '&b;&b;'>
+]>
+<e>&c;</e>";
+
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    assert_eq!(doc.root_element().text(), Some("aaaaaa"));
+
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        max_entity_depth: 2,
+        ..ParsingOptions::default()
+    };
+    let error = Document::parse_with_options(data, opt).unwrap_err();
+    assert!(matches!(error, Error::EntityReferenceLoop(_)));
+
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        max_entity_references: 7,
+        ..ParsingOptions::default()
+    };
+    let error = Document::parse_with_options(data, opt).unwrap_err();
+    assert!(matches!(error, Error::EntityReferenceLoop(_)));
+
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        max_entity_depth: 0,
+        ..ParsingOptions::default()
+    };
+    let error = Document::parse_with_options("<!DOCTYPE e [<!ENTITY a 'a'>]><e>&a;</e>", opt);
+    assert!(matches!(error, Err(Error::EntityReferenceLoop(_))));
+}
+
+#[test]
+fn unknown_entity_policy_01() {
+    let data = "<e a='1&nbsp;2'>3&nbsp;&amp;4</e>";
+
+    let error = Document::parse(data).unwrap_err();
+    assert_eq!(
+        error,
+        Error::UnknownEntityReference("nbsp".to_string(), TextPos::new(1, 8))
+    );
+
+    let opt = ParsingOptions {
+        unknown_entity_policy: UnknownEntityPolicy::Passthrough,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    assert_eq!(doc.root_element().attribute("a"), Some("1&nbsp;2"));
+    assert_eq!(doc.root_element().text(), Some("3&nbsp;&4"));
+
+    let opt = ParsingOptions {
+        unknown_entity_policy: UnknownEntityPolicy::Replace('\u{FFFD}'),
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    assert_eq!(doc.root_element().attribute("a"), Some("1\u{FFFD}2"));
+    assert_eq!(doc.root_element().text(), Some("3\u{FFFD}&4"));
+}
+
+#[test]
+fn unknown_entity_policy_02() {
+    let opt = ParsingOptions {
+        unknown_entity_policy: UnknownEntityPolicy::Passthrough,
+        ..ParsingOptions::default()
+    };
+    let error = Document::parse_with_options("<e>&nbsp</e>", opt).unwrap_err();
+    assert_eq!(error, Error::MalformedEntityReference(TextPos::new(1, 4)));
+}
+
+#[test]
+fn prologue_epilogue_nodes_01() {
+    let data = "<?pi1?><!--c1--><!DOCTYPE e []><?pi2?><e><!--c2--></e><!--c3--><?pi3?>";
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+
+    let prologue: Vec<_> = doc.prologue_nodes().map(|n| n.node_type()).collect();
+    assert_eq!(prologue, [NodeType::PI, NodeType::Comment, NodeType::PI]);
+
+    let epilogue: Vec<_> = doc.epilogue_nodes().map(|n| n.node_type()).collect();
+    assert_eq!(epilogue, [NodeType::Comment, NodeType::PI]);
+
+    let doc = Document::parse("<e/>").unwrap();
+    assert_eq!(doc.prologue_nodes().count(), 0);
+    assert_eq!(doc.epilogue_nodes().count(), 0);
+}
+
+#[test]
+fn parse_bytes_01() {
+    let opt = ParsingOptions::default();
+
+    let data = b"\xEF\xBB\xBF<?xml version='1.0' encoding='utf-8'?><e>\xD0\x96</e>";
+    let doc = Document::parse_bytes(data, opt).unwrap();
+    assert_eq!(doc.root_element().text(), Some("\u{0416}"));
+
+    let doc = Document::parse_bytes(b"<e/>", opt).unwrap();
+    assert!(doc.root_element().has_tag_name("e"));
+
+    let error = Document::parse_bytes(b"<e>\xD0</e>", opt).unwrap_err();
+    assert_eq!(error, Error::InvalidUtf8(3));
+    assert_eq!(error.to_string(), "invalid UTF-8 sequence at byte 3");
+
+    let data = b"<?xml version='1.0' encoding='windows-1251'?>\n<e/>";
+    let error = Document::parse_bytes(data, opt).unwrap_err();
+    assert_eq!(
+        error,
+        Error::UnsupportedEncoding("windows-1251".to_string(), TextPos::new(1, 31))
+    );
+}
+
+#[test]
+fn parse_bytes_02() {
+    let opt = ParsingOptions::default();
+
+    let check = |data: &[u8], name: &str| {
+        let error = Document::parse_bytes(data, opt).unwrap_err();
+        assert_eq!(
+            error,
+            Error::UnsupportedEncoding(name.to_string(), TextPos::new(1, 1))
+        );
+    };
+
+    check(b"\xFF\xFE<\0e\0/\0>\0", "UTF-16LE");
+    check(b"\xFE\xFF\0<\0e\0/\0>", "UTF-16BE");
+    check(b"\xFF\xFE\0\0<\0\0\0", "UTF-32LE");
+    check(b"\0\0\xFE\xFF\0\0\0<", "UTF-32BE");
+}
+
+#[test]
+fn parse_fragment_01() {
+    let data = "<!-- comment --><li>a</li>\n<li>b</li><?pi?>";
+
+    let doc = Document::parse_fragment(data, ParsingOptions::default()).unwrap();
+    assert_eq!(doc.root().children().count(), 4);
+    assert_eq!(doc.root_elements().count(), 2);
+    assert_eq!(doc.root_element().text(), Some("a"));
+
+    assert!(Document::parse(data).is_err());
+}
+
+#[test]
+fn parse_fragment_02() {
+    let data = "<!-- comment -->";
+    let error = Document::parse_fragment(data, ParsingOptions::default()).unwrap_err();
+    assert_eq!(error, Error::NoRootNode);
+}
+
+#[test]
+fn builder_01() {
+    let mut builder = DocumentBuilder::new();
+    builder
+        .comment("comment")
+        .element("root")
+        .namespace(None, "http://www.w3.org")
+        .namespace(Some("n"), "http://www.w4.org")
+        .attribute("a", "1")
+        .attribute("n:a", String::from("2"))
+        .attribute("xml:space", "preserve")
+        .text("text1")
+        .text(String::from("text2"))
+        .element("n:item")
+        .end()
+        .element("item")
+        .namespace(Some("n"), "http://www.w5.org")
+        .pi("target", Some("value"))
+        .end()
+        .end();
+    let doc: Document<'static> = builder.finish().unwrap();
+
+    let root = doc.root_element();
+    assert!(root.has_tag_name(("http://www.w3.org", "root")));
+    assert_eq!(root.attribute("a"), Some("1"));
+    assert_eq!(root.attribute(("http://www.w4.org", "a")), Some("2"));
+    assert_eq!(root.attribute((NS_XML_URI, "space")), Some("preserve"));
+    #[cfg(feature = "positions")]
+    assert_eq!(root.attribute_node("a").unwrap().raw_value(), "");
+    assert_eq!(root.text(), Some("text1text2"));
+    assert_eq!(root.children().count(), 3);
+
+    let item1 = root.child_element_at(0).unwrap();
+    assert!(item1.has_tag_name(("http://www.w4.org", "item")));
+    assert_eq!(item1.prefix(), Some("n"));
+
+    let item2 = item1.next_sibling_element().unwrap();
+    assert!(item2.has_tag_name(("http://www.w3.org", "item")));
+    assert_eq!(
+        item2.lookup_namespace_uri(Some("n")),
+        Some("http://www.w5.org")
+    );
+    assert!(item2.first_child().unwrap().is_pi());
+
+    assert_eq!(doc.descendants().count(), 7);
+}
+
+#[test]
+fn builder_02() {
+    let mut builder = DocumentBuilder::new();
+    builder.element("n:root").end();
+    assert_eq!(
+        builder.finish().unwrap_err(),
+        Error::UnknownNamespace("n".to_string(), TextPos::new(0, 0))
+    );
+
+    let mut builder = DocumentBuilder::new();
+    builder.element("root");
+    assert_eq!(builder.finish().unwrap_err(), Error::UnclosedRootNode);
+
+    let builder = DocumentBuilder::new();
+    assert_eq!(builder.finish().unwrap_err(), Error::NoRootNode);
+}
+
+#[cfg(feature = "positions")]
+#[test]
+fn source_text_01() {
+    let data = "<!-- c --><e>\r\n<b/>t<![CDATA[x]]>&#x20;<?pi v?></e>";
+    let doc = Document::parse(data).unwrap();
+
+    assert_eq!(doc.root().source_text(), data);
+
+    let mut iter = doc.root_element().children();
+    assert_eq!(iter.next().unwrap().source_text(), "\r\n");
+    assert_eq!(iter.next().unwrap().source_text(), "<b/>");
+    // All parts of a merged text.
+    assert_eq!(iter.next().unwrap().source_text(), "t<![CDATA[x]]>&#x20;");
+    assert_eq!(iter.next().unwrap().source_text(), "<?pi v?>");
+    assert_eq!(
+        doc.root().first_child().unwrap().source_text(),
+        "<!-- c -->"
+    );
+}
+
+#[test]
+fn parse_lossy_01() {
+    let data = "<a><b></c><d/></a>";
+    let (doc, errors) = Document::parse_lossy(data, ParsingOptions::default()).unwrap();
+
+    let names: Vec<_> = doc.descendants().map(|n| n.tag_name().name()).collect();
+    assert_eq!(names, ["", "a", "b", "d"]);
+    assert_eq!(
+        errors,
+        [Error::UnexpectedCloseTag(
+            "b".to_string(),
+            "c".to_string(),
+            TextPos::new(1, 7)
+        )]
+    );
+}
+
+#[test]
+fn parse_lossy_02() {
+    let data = "<a><b/>&unknown;<c/></a>";
+    let (doc, errors) = Document::parse_lossy(data, ParsingOptions::default()).unwrap();
+
+    let names: Vec<_> = doc.descendants().map(|n| n.tag_name().name()).collect();
+    assert_eq!(names, ["", "a", "b"]);
+    assert_eq!(
+        errors,
+        [Error::UnknownEntityReference(
+            "unknown".to_string(),
+            TextPos::new(1, 8)
+        )]
+    );
+
+    let res = Document::parse_lossy("<!-- c -->", ParsingOptions::default());
+    assert_eq!(res.unwrap_err(), Error::NoRootNode);
+}
+
+#[test]
+fn parse_lossy_03() {
+    // An error before the first element is returned as is.
+    let res = Document::parse_lossy("<a x='1' x='2'><b/></a>", ParsingOptions::default());
+    assert_eq!(
+        res.unwrap_err(),
+        Error::DuplicatedAttribute("x".to_string(), TextPos::new(1, 10), TextPos::new(1, 4))
+    );
+}
+
+#[test]
+fn expanded_name_hash() {
+    use std::collections::HashMap;
+
+    let doc1 = Document::parse("<e xmlns='http://www.w3.org'><a/><b/></e>").unwrap();
+    let doc2 = Document::parse("<n:e xmlns:n='http://www.w3.org'><a/></n:e>").unwrap();
+
+    let mut map: HashMap<ExpandedName, usize> = HashMap::new();
+    for node in doc1.descendants().chain(doc2.descendants()) {
+        if node.is_element() {
+            *map.entry(node.tag_name()).or_default() += 1;
+        }
+    }
+
+    assert_eq!(map[&ExpandedName::from(("http://www.w3.org", "e"))], 2);
+    assert_eq!(map[&ExpandedName::from(("http://www.w3.org", "a"))], 1);
+    assert_eq!(map[&ExpandedName::from("a")], 1);
+    assert_eq!(map.len(), 4);
+}
+
+#[test]
+fn descendants_pruned_01() {
+    let doc = Document::parse("<e><a><b/></a>text<c><d/><a/></c><a><f/></a></e>").unwrap();
+
+    let names: Vec<_> = doc
+        .root()
+        .descendants_pruned(|n| !n.has_tag_name("a"))
+        .filter(|n| n.is_element())
+        .map(|n| n.tag_name().name())
+        .collect();
+    assert_eq!(names, ["e", "c", "d"]);
+
+    let c = doc.root_element().children().nth(2).unwrap();
+    assert_eq!(c.descendants_pruned(|_| true).count(), 3);
+    assert_eq!(c.descendants_pruned(|_| false).count(), 0);
+}
+
+#[test]
+fn line_index_01() {
+    let data = "<e>\n  <b>тест</b>\r\n\n<c/></e>";
+    let doc = Document::parse(data).unwrap();
+
+    let index = doc.line_index();
+    assert_eq!(index.lines_count(), 4);
+    for (pos, _) in data.char_indices() {
+        assert_eq!(index.pos_at(pos), doc.text_pos_at(pos));
+    }
+    assert_eq!(index.pos_at(data.len()), doc.text_pos_at(data.len()));
+}
+
+#[test]
+fn line_index_02() {
+    // Long lines with multibyte characters crossing internal chunk boundaries.
+    let data = format!("<e>{}\n{}</e>", "тест".repeat(100), "a€".repeat(200));
+    let doc = Document::parse(&data).unwrap();
+
+    let index = doc.line_index();
+    assert_eq!(index.lines_count(), 2);
+    for (pos, _) in data.char_indices() {
+        assert_eq!(index.pos_at(pos), doc.text_pos_at(pos));
+    }
+    assert_eq!(index.pos_at(data.len()), doc.text_pos_at(data.len()));
+}
+
+#[test]
+fn xml_space_01() {
+    let data = "\
+<e xml:space='preserve'>
+    <a xml:space='default'><b/></a>
+    <c xml:space='invalid'><d/></c>
+</e>";
+    let doc = Document::parse(data).unwrap();
+
+    let space = |name: &str| {
+        doc.descendants()
+            .find(|n| n.has_tag_name(name))
+            .unwrap()
+            .xml_space()
+    };
+    assert_eq!(space("e"), XmlSpace::Preserve);
+    assert_eq!(space("b"), XmlSpace::Default);
+    assert_eq!(space("d"), XmlSpace::Preserve);
+    assert_eq!(doc.root().xml_space(), XmlSpace::Default);
+}
+
+#[test]
+fn owned_document_01() {
+    let doc: OwnedDocument = "<e a='b'/>".parse().unwrap();
+    assert_eq!(doc.document().root_element().attribute("a"), Some("b"));
+    assert_eq!(doc.into_text(), "<e a='b'/>");
+
+    let res = "<e>".parse::<OwnedDocument>();
+    assert_eq!(res.unwrap_err(), Error::UnclosedRootNode);
+}
+
+#[test]
+fn namespaces_rev_01() {
+    let data = "<e xmlns:a='http://a.org' xmlns:b='http://b.org' xmlns:c='http://c.org'/>";
+    let doc = Document::parse(data).unwrap();
+    let node = doc.root_element();
+
+    let mut forward: Vec<_> = node.namespaces().map(|ns| ns.uri()).collect();
+    forward.reverse();
+    let backward: Vec<_> = node.namespaces().rev().map(|ns| ns.uri()).collect();
+    assert_eq!(backward, forward);
+    assert_eq!(backward.first(), Some(&"http://c.org"));
+}
+
+#[cfg(feature = "line_positions")]
+#[test]
+fn node_text_pos_01() {
+    let data = "<!-- c -->\n<e a='b'>\r\n  <b>тест<c/></b>\n\n<![CDATA[x]]>&#x20;<?pi?></e>";
+    let doc = Document::parse(data).unwrap();
+
+    for node in doc.descendants() {
+        assert_eq!(node.text_pos(), doc.text_pos_at(node.range().start));
+    }
+}
+
+#[test]
+fn children_size_hint_01() {
+    let doc = Document::parse("<e><a><b/></a>text<c/></e>").unwrap();
+
+    let mut children = doc.root_element().children();
+    assert_eq!(children.size_hint(), (1, Some(4)));
+    children.next();
+    assert_eq!(children.size_hint(), (1, Some(2)));
+    children.next_back();
+    assert_eq!(children.size_hint(), (1, Some(1)));
+    children.next();
+    assert_eq!(children.size_hint(), (0, Some(0)));
+
+    let b = doc.descendants().find(|n| n.has_tag_name("b")).unwrap();
+    assert_eq!(b.children().size_hint(), (0, Some(0)));
+}
+
+#[test]
+fn to_document_01() {
+    let data = "\
+<root xmlns='http://a.org' xmlns:n='http://b.org' xml:space='preserve'>
+    <!-- comment -->
+    <n:item n:attr='1' attr='2'>
+        <sub xmlns='http://c.org' xmlns:m='http://d.org'><m:x/>text<?pi value?></sub>
+        <n:other/>
+    </n:item>
+</root>";
+    let doc = Document::parse(data).unwrap();
+
+    let item = doc.descendants().find(|n| n.has_tag_name("item")).unwrap();
+    let copy = item.to_document().unwrap();
+    let root = copy.root_element();
+    assert!(root.has_tag_name(("http://b.org", "item")));
+    assert_eq!(root.attribute(("http://b.org", "attr")), Some("1"));
+    assert_eq!(root.attribute("attr"), Some("2"));
+    assert_eq!(root.lookup_namespace_uri(None), Some("http://a.org"));
+    assert_eq!(root.lookup_prefix("http://b.org"), Some("n"));
+
+    let names: Vec<_> = copy
+        .descendants()
+        .filter(|n| n.is_element())
+        .map(|n| n.tag_name())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            ExpandedName::from(("http://b.org", "item")),
+            ExpandedName::from(("http://c.org", "sub")),
+            ExpandedName::from(("http://d.org", "x")),
+            ExpandedName::from(("http://b.org", "other")),
+        ]
+    );
+
+    let item_nodes: Vec<_> = item.descendants().map(|n| n.node_type()).collect();
+    let copy_nodes: Vec<_> = copy.descendants().skip(1).map(|n| n.node_type()).collect();
+    assert_eq!(item_nodes, copy_nodes);
+
+    let copy = doc.root().to_document().unwrap();
+    assert_eq!(copy.descendants().count(), doc.descendants().count());
+    assert_eq!(
+        copy.root_element().attribute((NS_XML_URI, "space")),
+        Some("preserve")
+    );
+
+    let text = doc.descendants().find(|n| n.is_text()).unwrap();
+    assert_eq!(text.to_document().unwrap_err(), Error::NoRootNode);
+}
+
+#[test]
+fn error_byte_offset_01() {
+    let texts = [
+        "<e>\r\n  <b>тест</c></e>",
+        "<e a='1' a='2'/>",
+        "<e>&unknown;</e>",
+        "<e>\n<!-- a -- b --></e>",
+        "<e>\n\n<1/></e>",
+        "<e/>\n<e/>",
+    ];
+
+    for text in texts {
+        let error = Document::parse(text).unwrap_err();
+        let offset = error.byte_offset(text).unwrap();
+        assert_eq!(doc_pos(text, offset), error.pos(), "{}", text);
+    }
+
+    fn doc_pos(text: &str, offset: usize) -> TextPos {
+        let before = &text[..offset];
+        let row = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let col = before[line_start..].chars().count() + 1;
+        TextPos::new(row as u32, col as u32)
+    }
+
+    assert_eq!(Error::NoRootNode.byte_offset(""), None);
+    assert_eq!(Error::UnexpectedEndOfStream.byte_offset("<e>"), Some(3));
+    // DocumentBuilder errors don't have a valid position.
+    assert_eq!(
+        Error::InvalidName(TextPos::new(0, 0)).byte_offset("<e/>"),
+        None
+    );
+}
+
+#[test]
+fn attributes_order_01() {
+    let data = "<e z='1' xmlns:n='http://www.w3.org' n:y='2' a='3' xml:b='4' m='5'/>";
+    let doc = Document::parse(data).unwrap();
+
+    let attrs = doc.root_element().attributes();
+    let names: Vec<_> = attrs.clone().map(|a| a.name()).collect();
+    assert_eq!(names, ["z", "y", "a", "b", "m"]);
+
+    for (i, name) in names.iter().enumerate() {
+        assert_eq!(attrs.get(i).unwrap().name(), *name);
+    }
+}
+
+#[test]
+fn parsing_options_presets_01() {
+    let default = ParsingOptions::default();
+    assert_eq!(ParsingOptions::strict(), default);
+
+    // Presets must differ from the defaults only by the documented fields.
+    let lenient = ParsingOptions::lenient();
+    assert_eq!(
+        ParsingOptions {
+            allow_dtd: default.allow_dtd,
+            unknown_entity_policy: default.unknown_entity_policy,
+            allow_cdata_end_in_text: default.allow_cdata_end_in_text,
+            allow_trailing_content: default.allow_trailing_content,
+            allow_leading_whitespace_before_declaration: default
+                .allow_leading_whitespace_before_declaration,
+            ..lenient
+        },
+        default
+    );
+
+    let untrusted = ParsingOptions::untrusted();
+    assert!(!untrusted.allow_dtd);
+    assert_eq!(
+        ParsingOptions {
+            nodes_limit: default.nodes_limit,
+            memory_limit: default.memory_limit,
+            max_depth: default.max_depth,
+            ..untrusted
+        },
+        default
+    );
+}
+
+#[test]
+fn parsing_options_presets_02() {
+    let data = "<!DOCTYPE e [<!ENTITY a 'a'>]><e>&a;&b;]]></e>";
+    let doc = Document::parse_with_options(data, ParsingOptions::lenient()).unwrap();
+    assert_eq!(doc.root_element().text(), Some("a&b;]]>"));
+
+    let decl = "\n <?xml version='1.0'?><e/>";
+    assert!(Document::parse(decl).is_err());
+    assert!(Document::parse_with_options(decl, ParsingOptions::lenient()).is_ok());
+
+    let error = Document::parse_with_options(data, ParsingOptions::untrusted()).unwrap_err();
+    assert_eq!(error, Error::DtdDetected);
+
+    let data = "<e>".repeat(300) + &"</e>".repeat(300);
+    let error = Document::parse_with_options(&data, ParsingOptions::untrusted()).unwrap_err();
+    assert!(matches!(error, Error::DepthLimitReached(_)));
+}
+
+#[test]
+fn visitor_01() {
+    struct Log(Vec<String>);
+
+    impl<'a, 'input> Visitor<'a, 'input> for Log {
+        fn enter_element(&mut self, node: Node<'a, 'input>) {
+            self.0.push(format!("<{}>", node.tag_name().name()));
+        }
+
+        fn leave_element(&mut self, node: Node<'a, 'input>) {
+            self.0.push(format!("</{}>", node.tag_name().name()));
+        }
+
+        fn visit_text(&mut self, node: Node<'a, 'input>) {
+            self.0.push(node.text().unwrap().to_string());
+        }
+
+        fn visit_comment(&mut self, _: Node<'a, 'input>) {
+            self.0.push("comment".to_string());
+        }
+
+        fn visit_pi(&mut self, node: Node<'a, 'input>) {
+            self.0.push(node.pi().unwrap().target.to_string());
+        }
+    }
+
+    let data = "<!--c--><a><b><c/></b>t<?pi?><d>u</d></a>";
+    let doc = Document::parse(data).unwrap();
+
+    let mut log = Log(Vec::new());
+    doc.root().accept(&mut log);
+    assert_eq!(
+        log.0,
+        ["comment", "<a>", "<b>", "<c>", "</c>", "</b>", "t", "pi", "<d>", "u", "</d>", "</a>"]
+    );
+
+    let mut log = Log(Vec::new());
+    doc.root_element().first_child().unwrap().accept(&mut log);
+    assert_eq!(log.0, ["<b>", "<c>", "</c>", "</b>"]);
+}
+
+#[test]
+fn error_kind_01() {
+    let kind = |data: &str| Document::parse(data).unwrap_err().kind();
+
+    assert_eq!(kind("<e></d>"), ErrorKind::Structure);
+    assert_eq!(kind("<e a='1' a='2'/>"), ErrorKind::Structure);
+    assert_eq!(kind("<e>&#x0;</e>"), ErrorKind::Entity);
+    assert_eq!(kind("<e><!-- -- --></e>"), ErrorKind::Syntax);
+    assert_eq!(kind("<n:e/>"), ErrorKind::Namespace);
+    assert_eq!(kind("<!DOCTYPE e><e/>"), ErrorKind::Policy);
+
+    let opt = ParsingOptions {
+        nodes_limit: 2,
+        ..ParsingOptions::default()
+    };
+    let error = Document::parse_with_options("<e><e/></e>", opt).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Limit);
+
+    let error = Document::parse_bytes(b"<e>\xFF</e>", ParsingOptions::default()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Io);
+}
+
+#[test]
+fn parse_with_callback_01() {
+    use std::ops::ControlFlow;
+
+    let text = "<e>".to_string() + &"<a/>text".repeat(10_000) + "</e>";
+
+    let mut positions = Vec::new();
+    let doc = Document::parse_with_callback(&text, ParsingOptions::default(), |pos| {
+        positions.push(pos);
+        ControlFlow::Continue(())
+    })
+    .unwrap();
+    assert_eq!(doc.descendants().count(), 20_002);
+    assert_eq!(positions.len(), 4);
+    assert!(positions.windows(2).all(|w| w[0] < w[1]));
+
+    let mut calls = 0;
+    let error = Document::parse_with_callback(&text, ParsingOptions::default(), |_| {
+        calls += 1;
+        if calls == 2 {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    })
+    .unwrap_err();
+    assert_eq!(error, Error::Cancelled);
+    assert_eq!(calls, 2);
+}
+
+#[test]
+fn structural_eq_01() {
+    let eq = |data1: &str, data2: &str, opt: CompareOptions| {
+        let doc1 = Document::parse(data1).unwrap();
+        let doc2 = Document::parse(data2).unwrap();
+        doc1.structural_eq(&doc2, opt)
+    };
+
+    let opt = CompareOptions::default();
+    assert!(eq("<e a='1' b='2'/>", "<e b='2' a='1'/>", opt));
+    assert!(eq("<e>text</e>", "<e>t&#101;xt</e>", opt));
+    assert!(eq(
+        "<a:e xmlns:a='http://www.w3.org'><a:b/></a:e>",
+        "<e xmlns='http://www.w3.org'><b/></e>",
+        opt
+    ));
+    assert!(!eq("<e a='1'/>", "<e a='2'/>", opt));
+    assert!(!eq("<e><b/></e>", "<e><c/></e>", opt));
+    assert!(!eq("<e><b/></e>", "<e><b/><b/></e>", opt));
+    assert!(!eq("<e><?pi a?></e>", "<e><?pi b?></e>", opt));
+    assert!(!eq("<e>a<!--c-->b</e>", "<e>ab</e>", opt));
+
+    let opt = CompareOptions {
+        ignore_comments: true,
+        ..CompareOptions::default()
+    };
+    assert!(eq("<e>a<!--c-->b</e>", "<e>ab</e>", opt));
+    assert!(eq("<!--c--><e/>", "<e/>", opt));
+    assert!(!eq("<e> <b/></e>", "<e><b/></e>", opt));
+
+    let opt = CompareOptions {
+        ignore_whitespace: true,
+        ..CompareOptions::default()
+    };
+    assert!(eq("<e> <b/>\n</e>", "<e><b/></e>", opt));
+    assert!(!eq("<e> a <b/></e>", "<e>a<b/></e>", opt));
+}
+
+#[test]
+fn canonicalize_01() {
+    use roxmltree::C14nMode;
+
+    let data = "\
+<?xml version='1.0'?>
+<!DOCTYPE doc [<!ATTLIST e9 attr CDATA 'default'>]>
+<?pi-before?>
+<!--before-->
+<doc>
+   <e1   />
+   <e2   ></e2>
+   <e3   name = \"elem3\"   id=\"elem3\"   />
+   <e5 a:attr=\"out\" b:attr=\"sorted\" attr2=\"all\" attr=\"I'm\"
+      xmlns:b=\"http://www.ietf.org\"
+      xmlns:a=\"http://www.w3.org\"
+      xmlns=\"http://example.org\"/>
+   <e6 xmlns=\"\" xmlns:a=\"http://www.w3.org\">
+      <e7 xmlns=\"http://www.ietf.org\">
+         <e8 xmlns=\"\" xmlns:a=\"http://www.w3.org\">
+            <e9 xmlns=\"\" xmlns:a=\"http://www.ietf.org\"/>
+         </e8>
+      </e7>
+   </e6>
+   <t a='&#x9;&#xA;&quot;&lt;'>&lt;&amp;&gt;<![CDATA[>&]]>&#xD;</t>
+</doc>
+<!--after-->";
+
+    let doc = Document::parse_with_options(
+        data,
+        ParsingOptions {
+            allow_dtd: true,
+            apply_attlist_defaults: true,
+            ..ParsingOptions::default()
+        },
+    )
+    .unwrap();
+
+    let body = "\
+<doc>
+   <e1></e1>
+   <e2></e2>
+   <e3 id=\"elem3\" name=\"elem3\"></e3>
+   <e5 xmlns=\"http://example.org\" xmlns:a=\"http://www.w3.org\" xmlns:b=\"http://www.ietf.org\" attr=\"I'm\" attr2=\"all\" b:attr=\"sorted\" a:attr=\"out\"></e5>
+   <e6 xmlns:a=\"http://www.w3.org\">
+      <e7 xmlns=\"http://www.ietf.org\">
+         <e8 xmlns=\"\">
+            <e9 xmlns:a=\"http://www.ietf.org\" attr=\"default\"></e9>
+         </e8>
+      </e7>
+   </e6>
+   <t a=\"&#x9;&#xA;&quot;&lt;\">&lt;&amp;&gt;&gt;&amp;&#xD;</t>
+</doc>";
+
+    assert_eq!(
+        doc.canonicalize(C14nMode::Inclusive),
+        format!("<?pi-before?>\n{}", body)
+    );
+    assert_eq!(
+        doc.canonicalize(C14nMode::InclusiveWithComments),
+        format!("<?pi-before?>\n<!--before-->\n{}\n<!--after-->", body)
+    );
+
+    let e9 = doc.descendants().find(|n| n.has_tag_name("e9")).unwrap();
+    assert_eq!(
+        e9.canonicalize(C14nMode::Inclusive),
+        "<e9 xmlns:a=\"http://www.ietf.org\" attr=\"default\"></e9>"
+    );
+
+    let e7 = doc.descendants().find(|n| n.has_tag_name("e7")).unwrap();
+    assert_eq!(
+        e7.canonicalize(C14nMode::Exclusive),
+        "<e7 xmlns=\"http://www.ietf.org\">
+         <e8 xmlns=\"\">
+            <e9 attr=\"default\"></e9>
+         </e8>
+      </e7>"
+    );
+
+    let doc = Document::parse("<a xml:lang='en' xmlns:x='http://x'><b xml:space='preserve'/></a>")
+        .unwrap();
+    let b = doc.root_element().first_child().unwrap();
+    assert_eq!(
+        b.canonicalize(C14nMode::Inclusive),
+        "<b xmlns:x=\"http://x\" xml:lang=\"en\" xml:space=\"preserve\"></b>"
+    );
+    assert_eq!(
+        b.canonicalize(C14nMode::Exclusive),
+        "<b xml:space=\"preserve\"></b>"
+    );
+}
+
+#[test]
+fn sibling_of_type_01() {
+    let data = "<root><a/>text<!--c1--><?pi?><b/><!--c2-->tail</root>";
+
+    let doc = roxmltree::Document::parse(data).unwrap();
+
+    let a = doc.root_element().first_child().unwrap();
+    let b = doc.descendants().find(|n| n.has_tag_name("b")).unwrap();
+
+    assert_eq!(a.next_sibling_text().unwrap().text(), Some("text"));
+    assert_eq!(a.next_sibling_comment().unwrap().text(), Some("c1"));
+    assert_eq!(a.next_sibling_pi().unwrap().pi().unwrap().target, "pi");
+    assert_eq!(a.next_sibling_of_type(NodeType::Element), Some(b));
+
+    assert_eq!(b.prev_sibling_text().unwrap().text(), Some("text"));
+    assert_eq!(b.prev_sibling_comment().unwrap().text(), Some("c1"));
+    assert_eq!(b.prev_sibling_pi().unwrap().pi().unwrap().target, "pi");
+    assert_eq!(b.next_sibling_comment().unwrap().text(), Some("c2"));
+    assert_eq!(b.prev_sibling_of_type(NodeType::Element), Some(a));
+
+    assert!(a.prev_sibling_text().is_none());
+    assert!(b.next_sibling_pi().is_none());
+    assert!(doc.root().next_sibling_of_type(NodeType::Root).is_none());
+}
+
+#[test]
+fn dump_deep_01() {
+    // Use a small stack to make sure that dumping is not recursive.
+    std::thread::Builder::new()
+        .stack_size(64 * 1024)
+        .spawn(|| {
+            // The parser itself is recursive, so use a builder instead.
+            let depth = 1000;
+            let mut builder = DocumentBuilder::new();
+            for _ in 0..depth {
+                builder.element("e");
+            }
+            builder.text("text");
+            for _ in 0..depth {
+                builder.end();
+            }
+            let doc = builder.finish().unwrap();
+
+            let s = format!("{:?}", doc);
+            assert_eq!(s.matches("Element {").count(), depth);
+
+            let mut s = String::new();
+            doc.dump(&mut s, DumpFormat::Yaml).unwrap();
+            assert_eq!(s.matches("- Element:").count(), depth);
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+#[test]
+fn validate_01() {
+    let opt = ParsingOptions::default();
+    let data = [
+        "<e/>",
+        "<e><a xmlns:p='http://a'><p:b p:c='1'/></a><a xmlns:p='http://b' p:c='1'/></e>",
+        "<e><a xmlns:p='http://a'/><p:b/></e>",
+        "<e xmlns:p='http://a'><a xmlns:p='http://b'/><p:b p:c='1'/></e>",
+        "<e><a x='1'/><b x='1' x='2'/></e>",
+        "<e><a><b></a></b></e>",
+        "<e>text<!--comment--><?pi?></e>",
+        "<!--comment-->",
+        "<e/><e/>",
+        "<e><a/>",
+    ];
+
+    for text in data {
+        let expected = Document::parse_with_options(text, opt).map(|_| ());
+        assert_eq!(roxmltree::validate(text, opt), expected, "{}", text);
+    }
+
+    let opt = ParsingOptions {
+        nodes_limit: 3,
+        ..ParsingOptions::default()
+    };
+    assert_eq!(roxmltree::validate("<e><a/></e>", opt), Ok(()));
+    assert_eq!(
+        roxmltree::validate("<e><a/><b/></e>", opt),
+        Err(Error::NodesLimitReached)
+    );
+}
+
+#[cfg(feature = "positions")]
+#[test]
+fn value_range_01() {
+    let data = "\
+<!DOCTYPE e [<!ENTITY ent 'a<!--c-->b'>]>
+<e><![CDATA[x]]>y<!---->&ent;z<?pi?></e>";
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+
+    let nodes: Vec<_> = doc
+        .root_element()
+        .children()
+        .map(|n| (&data[n.range()], n.value_range().map(|r| &data[r])))
+        .collect();
+    assert_eq!(
+        nodes,
+        [
+            ("<![CDATA[x]]>y", None),
+            ("<!---->", Some("")),
+            ("&ent;z", None),
+            ("&ent;z", None),
+            ("&ent;z", None),
+            ("<?pi?>", None),
+        ]
+    );
+
+    let data = "<e><![CDATA[]]><!--c-->a&amp;b</e>";
+    let doc = Document::parse(data).unwrap();
+    let nodes: Vec<_> = doc
+        .root_element()
+        .children()
+        .map(|n| n.value_range().map(|r| &data[r]))
+        .collect();
+    assert_eq!(nodes, [Some(""), Some("c"), Some("a&amp;b")]);
+}
+
+#[cfg(feature = "positions")]
+#[test]
+fn entity_ranges_01() {
+    let data = "\
+<!DOCTYPE e [<!ENTITY ent '<a><!--c--><b/>t</a>'>]>
+<e>x&ent;<c/></e>";
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+
+    // All nodes produced by an entity share the range of the reference.
+    let ranges: Vec<_> = doc
+        .root_element()
+        .descendants()
+        .skip(1)
+        .map(|n| &data[n.range()])
+        .collect();
+    assert_eq!(
+        ranges,
+        ["x&ent;", "x&ent;", "x&ent;", "x&ent;", "x&ent;", "<c/>"]
+    );
+
+    // Ranges are in the document order.
+    let starts: Vec<_> = doc.descendants().map(|n| n.range().start).collect();
+    assert!(starts.windows(2).all(|w| w[0] <= w[1]));
+}
+
+#[test]
+fn allow_trailing_content_01() {
+    let data = "<e/>\n<?pi?>\n<extra-garbage>\n<!--";
+
+    assert_eq!(
+        Document::parse(data).unwrap_err(),
+        Error::UnknownToken(TextPos::new(3, 1))
+    );
+
+    let opt = ParsingOptions {
+        allow_trailing_content: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    assert_eq!(doc.trailing_content_offset(), Some(12));
+    assert_eq!(doc.root().children().count(), 2);
+
+    let doc = Document::parse_with_options("<e/><!--c-->", opt).unwrap();
+    assert_eq!(doc.trailing_content_offset(), None);
+
+    // Malformed comments and processing instructions are trailing content as well.
+    let doc = Document::parse_with_options("<e/><!--c--><!--", opt).unwrap();
+    assert_eq!(doc.trailing_content_offset(), Some(12));
+    assert_eq!(doc.root().children().count(), 2);
+
+    let doc = Document::parse_with_options("<e/>\n<?pi", opt).unwrap();
+    assert_eq!(doc.trailing_content_offset(), Some(5));
+
+    // Only after the root element.
+    assert_eq!(
+        Document::parse_with_options("text<e/>", opt).unwrap_err(),
+        Error::UnknownToken(TextPos::new(1, 1))
+    );
+    assert_eq!(
+        Document::parse_with_options("<e/>", ParsingOptions::default())
+            .unwrap()
+            .trailing_content_offset(),
+        None
+    );
+}
+
+#[test]
+fn namespace_is_default_01() {
+    let doc = Document::parse("<e xmlns='http://a' xmlns:n='http://b'><b xmlns=''/></e>").unwrap();
+
+    let e = doc.root_element();
+    let list: Vec<_> = e
+        .namespaces()
+        .map(|ns| (ns.prefix(), ns.is_default()))
+        .collect();
+    assert_eq!(list, [(None, true), (Some("n"), false)]);
+
+    let b = e.first_child().unwrap();
+    let ns = b.namespaces().find(|ns| ns.is_default()).unwrap();
+    assert_eq!(ns.uri(), "");
+    assert_eq!(ns.prefix(), ns.name());
+}
+
+#[test]
+fn parse_attribute_01() {
+    let doc = Document::parse("<e xmlns:n='http://a' x='1.5' n:y='-2' z='true' w=' 1'/>").unwrap();
+    let e = doc.root_element();
+
+    assert_eq!(e.parse_attribute::<f64, _>("x"), Some(Ok(1.5)));
+    assert_eq!(e.parse_attribute::<i32, _>(("http://a", "y")), Some(Ok(-2)));
+    assert_eq!(e.parse_attribute::<bool, _>("z"), Some(Ok(true)));
+    assert!(matches!(e.parse_attribute::<i32, _>("w"), Some(Err(_))));
+    assert_eq!(e.parse_attribute::<i32, _>("y"), None);
+}
+
+#[test]
+fn is_whitespace_01() {
+    let doc = Document::parse("<e> \t\r\n<a/>&#x20;<!-- --><b> x </b>&#xA0;</e>").unwrap();
+
+    let list: Vec<_> = doc
+        .root_element()
+        .children()
+        .map(|n| n.is_whitespace())
+        .collect();
+    assert_eq!(list, [true, false, true, false, false, false]);
+    assert!(!doc.root().is_whitespace());
+}
+
+#[test]
+fn reserve_capacity_01() {
+    let data = "<e a='1'><!-- < < < --><b c='2' d='3'/>text</e>";
+    let expected = Document::parse(data).unwrap();
+
+    for hint in [Some(0), Some(1), Some(1000), None] {
+        let opt = ParsingOptions {
+            reserve_nodes: hint,
+            reserve_attributes: hint,
+            ..ParsingOptions::default()
+        };
+        let doc = Document::parse_with_options(data, opt).unwrap();
+        assert!(doc.structural_eq(&expected, CompareOptions::default()));
+        assert_eq!(doc.stats().nodes, 5);
+        assert_eq!(doc.stats().attributes, 3);
+    }
+}
+
+#[test]
+fn try_root_element_01() {
+    let doc = Document::parse("<?pi?><e/>").unwrap();
+    assert_eq!(doc.try_root_element(), Some(doc.root_element()));
+
+    let doc = Document::parse_fragment("<!--c--><a/><b/>", ParsingOptions::default()).unwrap();
+    assert!(doc.try_root_element().unwrap().has_tag_name("a"));
+}
+
+#[test]
+fn namespace_id_01() {
+    let data = "\
+<e xmlns='http://a' xmlns:a='http://a' xmlns:b='http://b'>
+    <a:c b:x='1' a:y='2' z='3'/>
+    <c b:x='4'/>
+</e>";
+    let doc = Document::parse(data).unwrap();
+
+    let e = doc.root_element();
+    let mut iter = e.children().filter(|n| n.is_element());
+    let c1 = iter.next().unwrap();
+    let c2 = iter.next().unwrap();
+
+    // Same URI, but different prefixes.
+    assert_ne!(c1.tag_namespace_id(), c2.tag_namespace_id());
+    assert_eq!(c2.tag_namespace_id(), e.tag_namespace_id());
+    assert_eq!(
+        c1.tag_namespace_id(),
+        c1.attributes().nth(1).unwrap().namespace_id()
+    );
+
+    let x1 = c1.attribute_node(("http://b", "x")).unwrap();
+    let x2 = c2.attribute_node(("http://b", "x")).unwrap();
+    assert_eq!(x1.namespace_id(), x2.namespace_id());
+    assert_eq!(c1.attribute_node("z").unwrap().namespace_id(), None);
+    assert_eq!(doc.root().tag_namespace_id(), None);
+}
+
+#[test]
+fn parse_with_diagnostics_01() {
+    let data = "\
+<?xml-stylesheet href='a.css'?>
+<?xml-custom?>
+<e xmlns='http://a' xmlns:b='http://b'>
+    <c xmlns='http://a' xmlns:b='http://c' a=''/>
+    <d xmlns:b='http://b'/>
+</e>";
+    let opt = ParsingOptions::default();
+    let (doc, diagnostics) = Document::parse_with_diagnostics(data, opt).unwrap();
+    assert_eq!(doc.descendants().count(), 9);
+
+    let codes: Vec<_> = diagnostics.iter().map(|d| (d.code(), d.pos())).collect();
+    assert_eq!(
+        codes,
+        vec![
+            (DiagnosticCode::ReservedPITarget, TextPos::new(2, 1)),
+            (DiagnosticCode::RedundantNamespace, TextPos::new(4, 8)),
+            (DiagnosticCode::EmptyAttributeValue, TextPos::new(4, 44)),
+            (DiagnosticCode::RedundantNamespace, TextPos::new(5, 8)),
+        ]
+    );
+    assert_eq!(
+        diagnostics[3].to_string(),
+        "namespace 'b' is already declared by the parent at 5:8"
+    );
+
+    // Diagnostics do not affect errors.
+    assert!(Document::parse_with_diagnostics("<e a=''>", opt).is_err());
+}
+
+#[test]
+fn parse_with_diagnostics_02() {
+    // A multibyte character inside the first three bytes of a PI target.
+    let opt = ParsingOptions::default();
+    let (_, diagnostics) = Document::parse_with_diagnostics("<?a中?><e/>", opt).unwrap();
+    assert!(diagnostics.is_empty());
+
+    let (_, diagnostics) = Document::parse_with_diagnostics("<?XmL中?><e/>", opt).unwrap();
+    assert_eq!(diagnostics[0].code(), DiagnosticCode::ReservedPITarget);
+}
+
+#[cfg(feature = "quick-xml")]
+#[test]
+fn quick_xml_events_01() {
+    let data = "\
+<?pi value?>
+<e xmlns='http://a' xmlns:b='http://b' b:x='&amp;1'>
+    <c xmlns='' a='&quot;'><!--c--><b:d/></c>
+    text &lt;
+</e>";
+    let doc = Document::parse(data).unwrap();
+
+    let mut writer = quick_xml::Writer::new(Vec::new());
+    for event in doc.root().quick_xml_events() {
+        writer.write_event(event).unwrap();
+    }
+    let text = String::from_utf8(writer.into_inner()).unwrap();
+    assert_eq!(
+        text,
+        "\
+<?pi value?>\
+<e xmlns=\"http://a\" xmlns:b=\"http://b\" b:x=\"&amp;1\">
+    <c xmlns=\"\" a=\"&quot;\"><!--c--><b:d></b:d></c>
+    text &lt;
+</e>"
+    );
+
+    let c = doc.descendants().find(|n| n.has_tag_name("c")).unwrap();
+    let mut writer = quick_xml::Writer::new(Vec::new());
+    for event in c.quick_xml_events() {
+        writer.write_event(event).unwrap();
+    }
+    let text = String::from_utf8(writer.into_inner()).unwrap();
+    assert_eq!(
+        text,
+        "<c xmlns:b=\"http://b\" a=\"&quot;\"><!--c--><b:d></b:d></c>"
+    );
+}
+
+#[test]
+fn siblings_01() {
+    let doc = Document::parse("<e><a/>text<b/><c/></e>").unwrap();
+    let e = doc.root_element();
+    let b = e.children().nth(2).unwrap();
+
+    assert!(b.siblings().eq(e.children()));
+    assert!(b.siblings().rev().eq(e.children().rev()));
+    assert!(e.siblings().eq(core::iter::once(e)));
+    assert!(doc.root().siblings().eq(core::iter::once(doc.root())));
+}
+
+#[test]
+fn allow_leading_whitespace_before_declaration_01() {
+    let data = "\n  <?xml version='1.0'?>\n<e/>";
+    assert_eq!(
+        Document::parse(data).unwrap_err(),
+        Error::UnexpectedDeclaration(TextPos::new(2, 3))
+    );
+
+    let opt = ParsingOptions {
+        allow_leading_whitespace_before_declaration: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    assert!(doc.root_element().has_tag_name("e"));
+
+    // The declaration is still validated.
+    let data = "  <?xml encoding='UTF-8'?><e/>";
+    assert!(Document::parse_with_options(data, opt).is_err());
+
+    // And its encoding is still checked.
+    let data = b" <?xml version='1.0' encoding='windows-1251'?><e/>";
+    assert_eq!(
+        Document::parse_bytes(data, opt).unwrap_err(),
+        Error::UnsupportedEncoding("windows-1251".to_string(), TextPos::new(1, 32))
+    );
+}
+
+#[test]
+fn attributes_position_of_01() {
+    let doc = Document::parse("<e xmlns:n='http://n' a='1' n:b='2' b='3'/>").unwrap();
+    let e = doc.root_element();
+
+    let mut attrs = e.attributes();
+    let idx = attrs.position_of("b").unwrap();
+    assert_eq!(idx, 2);
+    assert_eq!(e.attribute_at(idx).unwrap().value(), "3");
+    assert_eq!(attrs.position_of(("http://n", "b")), Some(1));
+
+    // Relative to the remaining attributes.
+    attrs.next();
+    assert_eq!(attrs.position_of("b"), Some(1));
+    assert_eq!(attrs.position_of("a"), None);
+}
+
+#[test]
+fn is_ignorable_whitespace_01() {
+    let data = "\
+<!DOCTYPE n:e [
+    <!ELEMENT n:e ( n:a | b )*>
+    <!ELEMENT n:a (#PCDATA | b)*>
+    <!ELEMENT b EMPTY>
+    <!ELEMENT n:e ANY>
+]>
+<n:e xmlns:n='http://n'>
+    <n:a> <b/> </n:a>
+    <b> </b>
+</n:e>";
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        detect_ignorable_whitespace: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+
+    let ignorable: Vec<_> = doc
+        .descendants()
+        .filter(|n| n.is_whitespace())
+        .map(|n| {
+            (
+                n.parent().unwrap().tag_name().name(),
+                n.is_ignorable_whitespace(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        ignorable,
+        [
+            ("e", true),
+            ("a", false),
+            ("a", false),
+            ("e", true),
+            ("b", false),
+            ("e", true),
+        ]
+    );
+
+    // Declarations are skipped by default.
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    assert!(!doc.descendants().any(|n| n.is_ignorable_whitespace()));
+}
+
+#[test]
+fn is_ignorable_whitespace_02() {
+    // Declarations with parameter entity references are skipped
+    // and only the first declaration of an element is binding.
+    let data = "\
+<!DOCTYPE e [
+    <!ENTITY % name 'a'>
+    <!ELEMENT %name; (e)>
+    <!ELEMENT e (#PCDATA | a)*>
+    <!ELEMENT e (a)>
+]>
+<e> <a/> </e>";
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        detect_ignorable_whitespace: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    assert!(!doc.descendants().any(|n| n.is_ignorable_whitespace()));
+}
+
+#[test]
+fn is_empty_01() {
+    let doc = Document::parse("<e><a/><b><!--c--></b><c><?pi?></c>text</e>").unwrap();
+    let e = doc.root_element();
+
+    let flags: Vec<_> = e.children().map(|n| n.is_empty()).collect();
+    assert_eq!(flags, [true, false, false, false]);
+    assert!(!e.first_child().unwrap().has_children());
+    assert!(!doc.root().is_empty());
+}
+
+#[cfg(feature = "positions")]
+#[test]
+fn end_tag_range_01() {
+    let text =
+        "<e a='/>'>\n    <b></b>\n    <c a='>'/>\n    <n:d xmlns:n='http://n'>text</n:d\n>\n</e>";
+    let doc = Document::parse(text).unwrap();
+
+    let ranges: Vec<_> = doc
+        .descendants()
+        .map(|n| n.end_tag_range().map(|r| &text[r]))
+        .collect();
+    assert_eq!(
+        ranges,
+        [
+            None,
+            Some("</e>"),
+            None,
+            Some("</b>"),
+            None,
+            None,
+            None,
+            Some("</n:d\n>"),
+            None,
+            None,
+        ]
+    );
+
+    // Unclosed elements have no close tag.
+    let (doc, _) = Document::parse_lossy("<e><b>text", ParsingOptions::default()).unwrap();
+    assert!(doc.descendants().all(|n| n.end_tag_range().is_none()));
+}
+
+#[test]
+fn has_local_name_01() {
+    let doc = Document::parse("<e xmlns='http://a'><n:e xmlns:n='http://b'/>e</e>").unwrap();
+
+    let matched: Vec<_> = doc.descendants().map(|n| n.has_local_name("e")).collect();
+    assert_eq!(matched, [false, true, true, false]);
+}
+
+#[test]
+fn standalone_and_external_subset_01() {
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+
+    let check = |text: &str, standalone: Option<bool>, external_subset: bool| {
+        let doc = Document::parse_with_options(text, opt).unwrap();
+        assert_eq!(doc.is_standalone(), standalone, "{}", text);
+        assert_eq!(doc.has_external_subset(), external_subset, "{}", text);
+    };
+
+    check("<e/>", None, false);
+    check("<?xml version='1.0'?><e/>", None, false);
+    check(
+        "<?xml version='1.0' standalone='no'?><e/>",
+        Some(false),
+        false,
+    );
+    check(
+        "<?xml version='1.0' encoding='UTF-8' standalone=\"yes\"?><e/>",
+        Some(true),
+        false,
+    );
+    check("<?xml version='1.0' standalone='maybe'?><e/>", None, false);
+    check("<!DOCTYPE e><e/>", None, false);
+    check("<!DOCTYPE e [<!ENTITY a 'b'>]><e/>", None, false);
+    check("<!DOCTYPE e SYSTEM 'e.dtd'><e/>", None, true);
+    check(
+        "<?xml version='1.0' standalone='yes'?>\n<!DOCTYPE e PUBLIC 'id' 'e.dtd' []><e/>",
+        Some(true),
+        true,
+    );
+
+    let mut builder = DocumentBuilder::new();
+    builder.element("e").end();
+    let doc = builder.finish().unwrap();
+    assert_eq!(doc.is_standalone(), None);
+    assert!(!doc.has_external_subset());
+}
+
+#[test]
+fn descendants_with_depth_01() {
+    let doc = Document::parse("<e><a><b><c/>t</b></a><d/>t<f><g/></f></e>").unwrap();
+
+    for node in doc.descendants() {
+        let base = node.ancestors().count();
+        for (n, depth) in node.descendants_with_depth() {
+            assert_eq!(n.ancestors().count() - base, depth);
+        }
+
+        assert_eq!(
+            node.descendants_with_depth().len(),
+            node.descendants().count()
+        );
+    }
+}
+
+#[test]
+fn owned_document_cow_01() {
+    use std::borrow::Cow;
+
+    let text: Cow<str> = Cow::Borrowed("<e a='b'/>");
+    let doc = Document::parse(&text).unwrap();
+    assert_eq!(doc.root_element().attribute("a"), Some("b"));
+
+    let doc = OwnedDocument::parse(text).unwrap();
+    assert_eq!(doc.document().root_element().attribute("a"), Some("b"));
+
+    let text: Cow<str> = Cow::Owned(String::from("<e a='c'/>"));
+    let doc = OwnedDocument::parse_with_options(text, ParsingOptions::default()).unwrap();
+    assert_eq!(doc.into_text(), "<e a='c'/>");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_01() {
+    let data = "<?pi v?><n:e xmlns:n='http://n' n:a='1' b='2'><!--c-->t<c/></n:e>";
+    let doc = Document::parse(data).unwrap();
+
+    assert_eq!(
+        serde_json::to_string(&doc).unwrap(),
+        "{\"children\":[\
+            {\"target\":\"pi\",\"value\":\"v\"},\
+            {\"tag\":\"{http://n}e\",\"attributes\":{\"{http://n}a\":\"1\",\"b\":\"2\"},\"children\":[\
+                {\"comment\":\"c\"},\
+                \"t\",\
+                {\"tag\":\"c\",\"attributes\":{},\"children\":[]}\
+            ]}\
+        ]}"
+    );
+
+    let root = doc.root_element();
+    assert_eq!(
+        serde_json::to_string(&root.serializable(NameStyle::Local)).unwrap(),
+        "{\"tag\":\"e\",\"attributes\":{\"a\":\"1\",\"b\":\"2\"},\"children\":[\
+            {\"comment\":\"c\"},\
+            \"t\",\
+            {\"tag\":\"c\",\"attributes\":{},\"children\":[]}\
+        ]}"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_02() {
+    // Colliding local names fall back to the Clark notation.
+    let data = "<e xmlns:a='http://a' xmlns:b='http://b' a:x='1' b:x='2' x='3' y='4'/>";
+    let doc = Document::parse(data).unwrap();
+    let root = doc.root_element();
+    assert_eq!(
+        serde_json::to_string(&root.serializable(NameStyle::Local)).unwrap(),
+        "{\"tag\":\"e\",\"attributes\":{\
+            \"{http://a}x\":\"1\",\"{http://b}x\":\"2\",\"x\":\"3\",\"y\":\"4\"\
+        },\"children\":[]}"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_name_style_default() {
+    assert_eq!(NameStyle::default(), NameStyle::Clark);
+
+    let doc = Document::parse("<e xmlns='http://n'/>").unwrap();
+    let root = doc.root_element();
+    assert_eq!(
+        serde_json::to_string(&root).unwrap(),
+        serde_json::to_string(&root.serializable(NameStyle::default())).unwrap()
+    );
+}
+
+#[test]
+fn builder_03() {
+    let check = |f: fn(&mut DocumentBuilder<'static>)| {
+        let mut builder = DocumentBuilder::new();
+        builder.element("root");
+        f(&mut builder);
+        builder.end();
+        builder.finish().unwrap_err()
+    };
+
+    let err = Error::InvalidName(TextPos::new(0, 0));
+    assert_eq!(check(|b| _ = b.element("1e").end()), err);
+    assert_eq!(check(|b| _ = b.element("a:b:c").end()), err);
+    assert_eq!(check(|b| _ = b.element(":e").end()), err);
+    assert_eq!(check(|b| _ = b.attribute("a b", "1")), err);
+    assert_eq!(check(|b| _ = b.namespace(Some("1n"), "http://n")), err);
+    assert_eq!(check(|b| _ = b.pi("", None)), err);
+
+    let mut builder = DocumentBuilder::new();
+    builder.element("root").text("").comment("c").text("").end();
+    let doc = builder.finish().unwrap();
+    assert_eq!(doc.root_element().children().count(), 1);
+}
+
+#[cfg(feature = "positions")]
+#[test]
+fn builder_attribute_ranges_01() {
+    let mut builder = DocumentBuilder::new();
+    builder.element("e").attribute("a", "1").end();
+    let doc = builder.finish().unwrap();
+
+    let attr = doc.root_element().attribute_node("a").unwrap();
+    assert_eq!(attr.range(), 0..0);
+    assert_eq!(attr.range_qname(), 0..0);
+    assert_eq!(attr.range_value(), 0..0);
+    assert_eq!(attr.quote_char(), b'"');
+    assert_eq!(attr.raw_value(), "");
+
+    // A copy doesn't have an input text as well.
+    let doc = Document::parse("<e a='1'/>").unwrap();
+    let copy = doc.root_element().to_document().unwrap();
+    let attr = copy.root_element().attribute_node("a").unwrap();
+    assert_eq!(attr.range_value(), 0..0);
+}
+
+#[test]
+fn duplicated_attribute_01() {
+    // Both positions are reported with and without the `positions` feature.
+    let error = Document::parse("<e a='1'\n   a='2'/>").unwrap_err();
+    assert_eq!(
+        error,
+        Error::DuplicatedAttribute("a".to_string(), TextPos::new(2, 4), TextPos::new(1, 4))
+    );
+}
+
+#[cfg(feature = "positions")]
+#[test]
+fn attribute_range_value_01() {
+    let text = "<e a='value' b = \"\"/>";
+    let doc = Document::parse(text).unwrap();
+
+    let mut attrs = doc.root_element().attributes();
+    let a = attrs.next().unwrap();
+    assert_eq!(&text[a.range_value()], "value");
+
+    let b = attrs.next().unwrap();
+    assert_eq!(b.range_value(), 18..18);
+}
+
+#[test]
+fn entities_01() {
+    let data = "\
+<!DOCTYPE e [
+    <!ENTITY a 'a1'>
+    <!ENTITY b SYSTEM 'b.xml'>
+    <!ENTITY % c 'c1'>
+    <!ENTITY a 'a2'>
+    <!ENTITY b 'b2'>
+    <!ENTITY d PUBLIC 'd' 'd.xml' NDATA d>
+]>
+<e/>";
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    let entities: Vec<_> = doc.entities().collect();
+    assert_eq!(entities, [("a", Some("a1")), ("b", None), ("d", None)]);
+}
+
+#[test]
+fn apply_attlist_defaults_02() {
+    // Declarations with parameter entity references are skipped.
+    let data = "\
+<!DOCTYPE e [
+    <!ENTITY % atts \"a CDATA 'x'\">
+    <!ATTLIST e %atts;>
+    <!ATTLIST e b CDATA 'y'>
+]>
+<e/>";
+
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        apply_attlist_defaults: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    let attrs: Vec<_> = doc
+        .root_element()
+        .attributes()
+        .map(|a| (a.name(), a.value()))
+        .collect();
+    assert_eq!(attrs, [("b", "y")]);
+}
+
+#[cfg(feature = "positions")]
+#[test]
+fn attribute_long_parts_01() {
+    let name = "a".repeat(70_000);
+    let data = format!("<e {}{}='v'/>", name, " ".repeat(300));
+    let doc = Document::parse(&data).unwrap();
+    let attr = doc.root_element().attribute_node(name.as_str()).unwrap();
+    assert_eq!(&data[attr.range_qname()], name);
+    assert_eq!(&data[attr.range_value()], "v");
+}
+
+#[test]
+fn canonicalize_02() {
+    use roxmltree::C14nMode;
+
+    // Canonical XML 1.0, 3.3 Start and End Tags.
+    let data = r#"<!DOCTYPE doc [<!ATTLIST e9 attr CDATA "default">]>
+<doc>
+   <e1   />
+   <e2   ></e2>
+   <e3   name = "elem3"   id="elem3"   />
+   <e4   name="elem4"   id="elem4"   ></e4>
+   <e5 a:attr="out" b:attr="sorted" attr2="all" attr="I'm"
+      xmlns:b="http://www.ietf.org"
+      xmlns:a="http://www.w3.org"
+      xmlns="http://example.org"/>
+   <e6 xmlns="" xmlns:a="http://www.w3.org">
+      <e7 xmlns="http://www.ietf.org">
+         <e8 xmlns="" xmlns:a="http://www.w3.org">
+            <e9 xmlns="" xmlns:a="http://www.ietf.org"/>
+         </e8>
+      </e7>
+   </e6>
+</doc>"#;
+
+    let expected = r#"<doc>
+   <e1></e1>
+   <e2></e2>
+   <e3 id="elem3" name="elem3"></e3>
+   <e4 id="elem4" name="elem4"></e4>
+   <e5 xmlns="http://example.org" xmlns:a="http://www.w3.org" xmlns:b="http://www.ietf.org" attr="I'm" attr2="all" b:attr="sorted" a:attr="out"></e5>
+   <e6 xmlns:a="http://www.w3.org">
+      <e7 xmlns="http://www.ietf.org">
+         <e8 xmlns="">
+            <e9 xmlns:a="http://www.ietf.org" attr="default"></e9>
+         </e8>
+      </e7>
+   </e6>
+</doc>"#;
+
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        apply_attlist_defaults: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+    assert_eq!(doc.canonicalize(C14nMode::Inclusive), expected);
+}
+
+#[test]
+fn validate_02() {
+    // Dropped nodes still count toward the memory limit.
+    let data = "\
+<!DOCTYPE e [<!ENTITY t 'text'>]>
+<e>
+    <a x='&amp;1' y='2'>a&t;b</a>
+    <a xmlns:p='http://a' p:x='&amp;3'>&t;<!--c-->&t;</a>
+    <a x='&amp;4'>&amp;&t;<b/></a>
+</e>";
+
+    for limit in 0..4096 {
+        let opt = ParsingOptions {
+            allow_dtd: true,
+            memory_limit: Some(limit),
+            ..ParsingOptions::default()
+        };
+        let expected = Document::parse_with_options(data, opt).map(|_| ());
+        assert_eq!(roxmltree::validate(data, opt), expected, "{}", limit);
+    }
+}
+
+#[test]
+fn tag_name_offset_01() {
+    let data = "<!DOCTYPE e [<!ENTITY b '<p:b/>'>]><e xmlns:p='http://p'><e/>&b;</e>";
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(data, opt).unwrap();
+
+    let offsets: Vec<_> = doc.descendants().map(|n| n.tag_name_offset()).collect();
+    assert_eq!(offsets, vec![None, Some(36), Some(58), Some(28)]);
+
+    let mut builder = DocumentBuilder::new();
+    builder.element("e").end();
+    let doc = builder.finish().unwrap();
+    assert_eq!(doc.root_element().tag_name_offset(), None);
+}
@@ -1,7 +1,6 @@
 use roxmltree::*;
 
 use std::fmt;
-use std::fmt::Write;
 use std::fs;
 use std::io::Read;
 use std::path;
@@ -15,20 +14,6 @@ impl<'a> fmt::Debug for TStr<'a> {
     }
 }
 
-trait HasExtension {
-    fn has_extension(&self, ext: &str) -> bool;
-}
-
-impl HasExtension for path::Path {
-    fn has_extension(&self, ext: &str) -> bool {
-        if let Some(e) = self.extension() {
-            e == ext
-        } else {
-            false
-        }
-    }
-}
-
 fn actual_test(path: &str) {
     let path = path::Path::new(path);
     let expected = load_file(&path.with_extension("yaml"));
@@ -59,137 +44,33 @@ fn load_file(path: &path::Path) -> String {
 
 fn to_yaml(doc: &Document) -> String {
     let mut s = String::new();
-    _to_yaml(doc, &mut s).unwrap();
+    doc.dump(&mut s, DumpFormat::Yaml).unwrap();
     s
 }
 
-fn _to_yaml(doc: &Document, s: &mut String) -> Result<(), fmt::Error> {
-    if !doc.root().has_children() {
-        return write!(s, "Document:");
-    }
-
-    macro_rules! writeln_indented {
-        ($depth:expr, $f:expr, $fmt:expr) => {
-            for _ in 0..$depth { write!($f, "  ")?; }
-            writeln!($f, $fmt)?;
-        };
-        ($depth:expr, $f:expr, $fmt:expr, $($arg:tt)*) => {
-            for _ in 0..$depth { write!($f, "  ")?; }
-            writeln!($f, $fmt, $($arg)*)?;
-        };
-    }
-
-    fn print_children(parent: Node, depth: usize, s: &mut String) -> Result<(), fmt::Error> {
-        for child in parent.children() {
-            match child.node_type() {
-                NodeType::Element => {
-                    writeln_indented!(depth, s, "- Element:");
-
-                    match child.tag_name().namespace() {
-                        Some(ns) => {
-                            if ns.is_empty() {
-                                writeln_indented!(
-                                    depth + 2,
-                                    s,
-                                    "tag_name: {}",
-                                    child.tag_name().name()
-                                );
-                            } else {
-                                writeln_indented!(
-                                    depth + 2,
-                                    s,
-                                    "tag_name: {}@{}",
-                                    child.tag_name().name(),
-                                    ns
-                                );
-                            }
-                        }
-                        None => {
-                            writeln_indented!(
-                                depth + 2,
-                                s,
-                                "tag_name: {}",
-                                child.tag_name().name()
-                            );
-                        }
-                    }
-
-                    let attributes = child.attributes();
-                    if attributes.len() != 0 {
-                        let mut attrs = Vec::new();
-                        for attr in attributes {
-                            match attr.namespace() {
-                                Some(ns) => {
-                                    attrs.push((format!("{}@{}", attr.name(), ns), attr.value()));
-                                }
-                                None => {
-                                    attrs.push((attr.name().to_string(), attr.value()));
-                                }
-                            }
-                        }
-                        attrs.sort_by(|a, b| a.0.cmp(&b.0));
-
-                        writeln_indented!(depth + 2, s, "attributes:");
-                        for (name, value) in attrs {
-                            writeln_indented!(depth + 3, s, "{}: {:?}", name, value);
-                        }
-                    }
-
-                    let namespaces = child.namespaces();
-                    if namespaces.len() != 0 {
-                        let mut ns_list = Vec::new();
-                        for ns in namespaces {
-                            let name = ns.name().unwrap_or("None");
-                            let uri = if ns.uri().is_empty() {
-                                "\"\""
-                            } else {
-                                ns.uri()
-                            };
-                            ns_list.push((name, uri));
-                        }
-                        ns_list.sort_by(|a, b| a.0.cmp(b.0));
-
-                        writeln_indented!(depth + 2, s, "namespaces:");
-                        for (name, uri) in ns_list {
-                            writeln_indented!(depth + 3, s, "{}: {}", name, uri);
-                        }
-                    }
-
-                    if child.has_children() {
-                        writeln_indented!(depth + 2, s, "children:");
-                        print_children(child, depth + 3, s)?;
-                    }
-                }
-                NodeType::Text => {
-                    writeln_indented!(depth, s, "- Text: {:?}", child.text().unwrap());
-                }
-                NodeType::Comment => {
-                    writeln_indented!(depth, s, "- Comment: {:?}", child.text().unwrap());
-                }
-                NodeType::PI => {
-                    if child.parent().unwrap().is_root() {
-                        continue;
-                    }
-
-                    writeln_indented!(depth, s, "- PI:");
+// `validate` must accept and reject the same files as the parser.
+#[test]
+fn validate_files() {
+    let opt = ParsingOptions {
+        allow_dtd: true,
+        ..roxmltree::ParsingOptions::default()
+    };
 
-                    let pi = child.pi().unwrap();
-                    writeln_indented!(depth + 2, s, "target: {:?}", pi.target);
-                    if let Some(value) = pi.value {
-                        writeln_indented!(depth + 2, s, "value: {:?}", value);
-                    }
-                }
-                NodeType::Root => {}
-            }
+    for entry in fs::read_dir("tests/files").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension() != Some("xml".as_ref()) {
+            continue;
         }
 
-        Ok(())
-    }
-
-    writeln!(s, "Document:")?;
-    print_children(doc.root(), 1, s)?;
+        // Non-UTF-8 files are not supported.
+        let Ok(input_xml) = String::from_utf8(fs::read(&path).unwrap()) else {
+            continue;
+        };
 
-    Ok(())
+        let expected = Document::parse_with_options(&input_xml, opt).map(|_| ());
+        let actual = roxmltree::validate(&input_xml, opt);
+        assert_eq!(actual, expected, "{}", path.display());
+    }
 }
 
 macro_rules! test {
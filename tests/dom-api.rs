@@ -129,11 +129,7 @@ fn last_element_child() {
 
     // or
 
-    let elem = svg_elem
-        .children()
-        .filter(|n| n.is_element())
-        .last()
-        .unwrap();
+    let elem = svg_elem.children().rfind(|n| n.is_element()).unwrap();
     assert!(elem.has_tag_name("rect"));
 }
 
@@ -157,6 +153,24 @@ fn get_element_by_id() {
     assert!(elem.has_tag_name("rect"));
 }
 
+// Document.getElementById, for multiple lookups
+#[test]
+fn get_element_by_id_index() {
+    let data = "\
+<svg id='svg1'>
+    <circle id='circle1'/>
+    <g>
+        <rect id='rect1'/>
+    </g>
+</svg>
+";
+
+    let doc = Document::parse(data).unwrap();
+    let index = doc.build_id_index("id");
+    assert!(index.element_by_id("rect1").unwrap().has_tag_name("rect"));
+    assert!(index.element_by_id("svg1").unwrap().has_tag_name("svg"));
+}
+
 // Node.ownerDocument
 #[test]
 fn owner_document() {
@@ -186,6 +200,15 @@ fn parent_element() {
         .find(|n| n.is_element())
         .unwrap()
         .has_tag_name("svg"));
+
+    // or
+
+    assert!(rect
+        .proper_ancestors()
+        .find(|n| n.is_element())
+        .unwrap()
+        .has_tag_name("svg"));
+    assert_eq!(doc.root().proper_ancestors().count(), 0);
 }
 
 // Node.contains